@@ -1,4 +1,106 @@
-use bevy::prelude::*;
+use bevy::{audio::Volume, input::common_conditions::input_just_pressed, prelude::*};
+
+use crate::constants::TILE_WORLD_SIZE;
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<VolumeSettings>();
+    app.init_resource::<VolumeSettings>();
+    app.register_type::<MusicDuck>();
+    app.init_resource::<MusicDuck>();
+    app.add_observer(scale_new_music);
+    app.add_observer(scale_new_sound_effect);
+    app.add_systems(
+        Update,
+        (
+            toggle_mute.run_if(input_just_pressed(MUTE_KEY)),
+            update_playing_volumes,
+        )
+            .chain(),
+    );
+}
+
+/// Silences or unsilences the game from any screen.
+const MUTE_KEY: KeyCode = KeyCode::KeyM;
+
+/// One of the volumes the player can set in [`VolumeSettings`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum VolumeChannel {
+    Master,
+    Music,
+    SoundEffects,
+}
+
+/// Linear volume factors the player picked. [`Music`] plays at `music` × `master`, and every
+/// [`SoundEffect`] at `sfx` × `master`, on top of its own playback volume. Muting silences
+/// everything without touching the factors, so unmuting brings them back as they were.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Resource)]
+pub struct VolumeSettings {
+    pub master: f32,
+    pub music: f32,
+    pub sfx: f32,
+    pub muted: bool,
+}
+
+impl Default for VolumeSettings {
+    fn default() -> Self {
+        Self {
+            master: 0.3,
+            music: 1.0,
+            sfx: 1.0,
+            muted: false,
+        }
+    }
+}
+
+impl VolumeSettings {
+    pub fn get(&self, channel: VolumeChannel) -> f32 {
+        match channel {
+            VolumeChannel::Master => self.master,
+            VolumeChannel::Music => self.music,
+            VolumeChannel::SoundEffects => self.sfx,
+        }
+    }
+
+    pub fn get_mut(&mut self, channel: VolumeChannel) -> &mut f32 {
+        match channel {
+            VolumeChannel::Master => &mut self.master,
+            VolumeChannel::Music => &mut self.music,
+            VolumeChannel::SoundEffects => &mut self.sfx,
+        }
+    }
+
+    fn music_volume(&self, duck: MusicDuck) -> Volume {
+        self.category_volume(self.music * duck.0)
+    }
+
+    fn sfx_volume(&self) -> Volume {
+        self.category_volume(self.sfx)
+    }
+
+    fn category_volume(&self, factor: f32) -> Volume {
+        if self.muted {
+            return Volume::SILENT;
+        }
+        Volume::Linear(factor * self.master)
+    }
+}
+
+/// Linear factor the music plays at on top of [`VolumeSettings`], to turn it down for a while
+/// (e.g. in the pause menu) without touching what the player picked.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Resource)]
+pub struct MusicDuck(pub f32);
+
+impl Default for MusicDuck {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+/// A sound's own playback volume, before [`VolumeSettings`] scaled it.
+#[derive(Component, Debug, Clone, Copy)]
+struct BaseVolume(Volume);
 
 /// An organizational marker component that should be added to a spawned [`AudioPlayer`] if it's in the
 /// general "music" category (e.g. global background music, soundtrack).
@@ -12,6 +114,12 @@ pub fn music(handle: Handle<AudioSource>) -> impl Bundle {
     (AudioPlayer(handle), PlaybackSettings::LOOP, Music)
 }
 
+/// A looping background sound, like wind or birdsong. Plays in the sound effect category,
+/// so it follows the SFX volume rather than the music one.
+pub fn ambience(handle: Handle<AudioSource>) -> impl Bundle {
+    (AudioPlayer(handle), PlaybackSettings::LOOP, SoundEffect)
+}
+
 /// An organizational marker component that should be added to a spawned [`AudioPlayer`] if it's in the
 /// general "sound effect" category (e.g. footsteps, the sound of a magic spell, a door opening).
 ///
@@ -23,3 +131,88 @@ pub struct SoundEffect;
 pub fn sound_effect(handle: Handle<AudioSource>) -> impl Bundle {
     (AudioPlayer(handle), PlaybackSettings::DESPAWN, SoundEffect)
 }
+
+/// Distance in world units at which a spatial sound effect fades out completely.
+const SPATIAL_SOUND_RANGE: f32 = TILE_WORLD_SIZE * 25.0;
+
+/// A sound effect instance that gets quieter the further `emitter_pos` is from `listener_pos`,
+/// falling off linearly until it is silent at [`SPATIAL_SOUND_RANGE`].
+pub fn spatial_sound_effect(
+    handle: Handle<AudioSource>,
+    emitter_pos: Vec2,
+    listener_pos: Vec2,
+) -> impl Bundle {
+    let falloff = (1.0 - emitter_pos.distance(listener_pos) / SPATIAL_SOUND_RANGE).clamp(0.0, 1.0);
+    (
+        AudioPlayer(handle),
+        PlaybackSettings::DESPAWN.with_volume(Volume::Linear(falloff)),
+        SoundEffect,
+    )
+}
+
+fn scale_new_music(
+    add: On<Add, Music>,
+    mut commands: Commands,
+    mut playback_q: Query<&mut PlaybackSettings>,
+    volume_settings: Res<VolumeSettings>,
+    music_duck: Res<MusicDuck>,
+) {
+    scale_new_sound(
+        add.entity,
+        volume_settings.music_volume(*music_duck),
+        &mut commands,
+        &mut playback_q,
+    );
+}
+
+fn scale_new_sound_effect(
+    add: On<Add, SoundEffect>,
+    mut commands: Commands,
+    mut playback_q: Query<&mut PlaybackSettings>,
+    volume_settings: Res<VolumeSettings>,
+) {
+    scale_new_sound(
+        add.entity,
+        volume_settings.sfx_volume(),
+        &mut commands,
+        &mut playback_q,
+    );
+}
+
+/// Scale a sound by its category's volume before it starts playing, remembering its own
+/// volume so [`update_playing_volumes`] can scale it again later.
+fn scale_new_sound(
+    entity: Entity,
+    category_volume: Volume,
+    commands: &mut Commands,
+    playback_q: &mut Query<&mut PlaybackSettings>,
+) {
+    let Ok(mut playback) = playback_q.get_mut(entity) else {
+        return;
+    };
+    commands.entity(entity).insert(BaseVolume(playback.volume));
+    playback.volume *= category_volume;
+}
+
+/// Apply changed [`VolumeSettings`] and [`MusicDuck`] to the sounds that are already playing.
+/// Volumes are always worked out from scratch, so changes can come in any order.
+fn update_playing_volumes(
+    volume_settings: Res<VolumeSettings>,
+    music_duck: Res<MusicDuck>,
+    mut music_q: Query<(&BaseVolume, &mut AudioSink), With<Music>>,
+    mut sound_effect_q: Query<(&BaseVolume, &mut AudioSink), (With<SoundEffect>, Without<Music>)>,
+) {
+    if !volume_settings.is_changed() && !music_duck.is_changed() {
+        return;
+    }
+    for (base, mut sink) in &mut music_q {
+        sink.set_volume(base.0 * volume_settings.music_volume(*music_duck));
+    }
+    for (base, mut sink) in &mut sound_effect_q {
+        sink.set_volume(base.0 * volume_settings.sfx_volume());
+    }
+}
+
+pub(crate) fn toggle_mute(mut volume_settings: ResMut<VolumeSettings>) {
+    volume_settings.muted = !volume_settings.muted;
+}
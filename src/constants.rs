@@ -1,9 +1,27 @@
 pub const GRID_SIZE_X: u32 = 24;
 pub const GRID_SIZE_Y: u32 = 32;
-pub const PLAYER_SCALE: f32 = 4.0; // Scale for rendering
+pub const PLAYER_SCALE: f32 = TILE_SCALE * 4.0 / 3.0; // Characters are drawn a third larger than the map art
+pub const PLAYER_VISIBLE_WIDTH: u32 = 16; // Width of the character inside a sprite frame, in sprite pixels
+pub const PLAYER_VISIBLE_HEIGHT: u32 = 16; // Height of the character inside a sprite frame, in sprite pixels
+pub const PLAYER_VISIBLE_OFFSET_Y: f32 = -4.0; // How far the character's center sits above the frame's center, in sprite pixels
+pub const CHARACTER_FOOTPRINT_WIDTH: f32 = 6.0; // Width of the box characters collide with, in sprite pixels
+pub const CHARACTER_FOOTPRINT_HEIGHT: f32 = 8.0; // Height of the box characters collide with, in sprite pixels
 pub const PLAYER_Z: f32 = 2.0; // Z position for rendering
-pub const PLAYER_MAX_SPEED: f32 = 800.0; // Max speed for player movement
-pub const TILE_SIZE: i32 = 16;
-pub const TILE_SCALE: f32 = 3.0; // Scale for rendering
-pub const MAP_WIDTH: i32 = 54; // For a larger farm
-pub const MAP_HEIGHT: i32 = 36;
+pub const PLAYER_MAX_SPEED: f32 = TILE_SCALE * 800.0 / 3.0; // Max speed for player movement, 800 world units a second at the default scale
+pub const PLAYER_ACCELERATION: f32 = PLAYER_MAX_SPEED * 10.0; // Speed gained or lost per second, so a tenth of a second to full speed
+pub const INTENT_EPSILON: f32 = 1e-4; // Squared intent length below which a character counts as standing still
+pub const PLAYER_MAX_HEALTH: f32 = 5.0; // Enemy contact hits the player can take
+pub const PLAYER_MAX_STAMINA: f32 = 100.0; // Energy spent on tool actions
+pub const PLAYER_STAMINA_REGEN: f32 = 8.0; // Stamina recovered per second
+pub const TILE_SIZE: i32 = 16; // Side of a map tile, in the map's pixels
+pub const TILE_SCALE: f32 = 3.0; // World units per pixel of map art. Every other world size follows from it
+pub const TILE_WORLD_SIZE: f32 = TILE_SIZE as f32 * TILE_SCALE; // Side of a map tile in world units
+
+// Movement checks a character's footprint against the obstacle tiles (see
+// `character_footprint`). It has to fit in a tile, or characters couldn't walk down
+// one-tile paths.
+const _: () = assert!(
+    CHARACTER_FOOTPRINT_WIDTH * PLAYER_SCALE <= TILE_WORLD_SIZE
+        && CHARACTER_FOOTPRINT_HEIGHT * PLAYER_SCALE <= TILE_WORLD_SIZE,
+    "The character footprint is too big to fit through one-tile gaps",
+);
@@ -1,14 +1,30 @@
 //! Development tools for the game. This plugin is only enabled in dev builds.
 
 use bevy::{
+    camera::primitives::Aabb,
     dev_tools::states::log_transitions,
+    diagnostic::{
+        DiagnosticPath, DiagnosticsStore, EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin,
+    },
     input::common_conditions::{input_just_pressed, input_toggle_active},
     prelude::*,
+    ui::Val::*,
 };
+use bevy_ecs_tilemap::prelude::*;
 use bevy_egui::EguiPlugin;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 
-use crate::states::GameState;
+use crate::{
+    game::{
+        camera::{CameraBounds, WithinBounds},
+        movement::{character_footprint, footprint_corners},
+    },
+    states::{AppState, GameState, GameplayState},
+    world::{
+        tiledhelper::Obstacle,
+        tilemap::{CurrentMap, LoadMapEvent},
+    },
+};
 
 pub(super) fn plugin(app: &mut App) {
     app.add_plugins((
@@ -16,17 +32,168 @@ pub(super) fn plugin(app: &mut App) {
         WorldInspectorPlugin::default().run_if(input_toggle_active(false, KeyCode::F12)),
     ));
     // Log `Screen` state transitions.
-    app.add_systems(Update, log_transitions::<GameState>);
+    app.add_systems(
+        Update,
+        (
+            log_transitions::<GameState>,
+            log_transitions::<GameplayState>,
+        ),
+    );
 
     // Toggle the debug overlay for UI.
     app.add_systems(
         Update,
         toggle_debug_ui.run_if(input_just_pressed(TOGGLE_KEY)),
     );
+
+    // Show live FPS, frame time and entity count in a corner of the screen.
+    app.add_plugins((
+        FrameTimeDiagnosticsPlugin::default(),
+        EntityCountDiagnosticsPlugin::default(),
+    ));
+    app.add_systems(Startup, spawn_diagnostics_overlay);
+    app.add_systems(
+        Update,
+        (
+            toggle_diagnostics_overlay.run_if(input_just_pressed(DIAGNOSTICS_KEY)),
+            update_diagnostics_overlay,
+        )
+            .chain(),
+    );
+
+    // Outline the tiles that block movement.
+    app.add_systems(
+        Update,
+        draw_obstacle_tiles
+            .run_if(in_state(AppState::InGame).and(input_toggle_active(false, OBSTACLES_KEY))),
+    );
+
+    // Draw the camera bounds and the movers' hitboxes.
+    app.add_systems(
+        Update,
+        draw_bounds_and_hitboxes
+            .run_if(in_state(AppState::InGame).and(input_toggle_active(false, BOUNDS_KEY))),
+    );
+
+    // Cycle through the maps at runtime.
+    app.add_systems(
+        Update,
+        cycle_maps.run_if(in_state(GameplayState::Running).and(input_just_pressed(CYCLE_MAPS_KEY))),
+    );
 }
 
 const TOGGLE_KEY: KeyCode = KeyCode::Backquote;
+const CYCLE_MAPS_KEY: KeyCode = KeyCode::F7;
+const DIAGNOSTICS_KEY: KeyCode = KeyCode::F11;
+const OBSTACLES_KEY: KeyCode = KeyCode::F8;
+const OBSTACLE_COLOR: Color = Color::srgb(1.0, 0.2, 0.2);
+const BOUNDS_KEY: KeyCode = KeyCode::F6;
+const CAMERA_BOUNDS_COLOR: Color = Color::srgb(0.2, 0.6, 1.0);
+const HITBOX_COLOR: Color = Color::srgb(0.2, 1.0, 0.2);
+/// Maps cycled through with [`CYCLE_MAPS_KEY`]. `flip_test` shows a tile in each of Tiled's
+/// eight flip orientations, to compare with the editor.
+const MAPS: &[&str] = &["farm", "cave", "flip_test"];
 
 fn toggle_debug_ui(mut options: ResMut<UiDebugOptions>) {
     options.toggle();
 }
+
+fn cycle_maps(current_map: Res<CurrentMap>, mut load_map_events: MessageWriter<LoadMapEvent>) {
+    let index = MAPS.iter().position(|&name| name == current_map.0);
+    let next = index.map_or(0, |index| (index + 1) % MAPS.len());
+    load_map_events.write(LoadMapEvent::new(MAPS[next]));
+}
+
+/// Marks the text showing FPS, frame time and entity count.
+#[derive(Component)]
+struct DiagnosticsOverlay;
+
+fn spawn_diagnostics_overlay(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Diagnostics Overlay"),
+        DiagnosticsOverlay,
+        Text::default(),
+        TextFont::from_font_size(16.0),
+        TextColor(Color::WHITE),
+        BackgroundColor(Color::BLACK.with_alpha(0.5)),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Px(5.0),
+            right: Px(5.0),
+            padding: UiRect::all(Px(4.0)),
+            ..default()
+        },
+        GlobalZIndex(i32::MAX),
+        Pickable::IGNORE,
+    ));
+}
+
+fn toggle_diagnostics_overlay(mut overlay: Single<&mut Visibility, With<DiagnosticsOverlay>>) {
+    overlay.toggle_visible_hidden();
+}
+
+fn update_diagnostics_overlay(
+    diagnostics: Res<DiagnosticsStore>,
+    overlay: Single<(&mut Text, &Visibility), With<DiagnosticsOverlay>>,
+) {
+    let (mut text, visibility) = overlay.into_inner();
+    if *visibility == Visibility::Hidden {
+        return;
+    }
+    let smoothed = |path: &DiagnosticPath| {
+        diagnostics
+            .get(path)
+            .and_then(|diagnostic| diagnostic.smoothed())
+            .unwrap_or_default()
+    };
+    let fps = smoothed(&FrameTimeDiagnosticsPlugin::FPS);
+    let frame_time = smoothed(&FrameTimeDiagnosticsPlugin::FRAME_TIME);
+    let entities = smoothed(&EntityCountDiagnosticsPlugin::ENTITY_COUNT);
+    text.0 = format!("{fps:.0} FPS\n{frame_time:.2} ms\n{entities:.0} entities");
+}
+
+fn draw_obstacle_tiles(
+    mut gizmos: Gizmos,
+    obstacle_q: Query<(&TilePos, &TilemapId), With<Obstacle>>,
+    tilemap_q: Query<(
+        &TilemapSize,
+        &TilemapGridSize,
+        &TilemapTileSize,
+        &TilemapType,
+        &TilemapAnchor,
+        &GlobalTransform,
+    )>,
+) {
+    for (tile_pos, tilemap_id) in &obstacle_q {
+        let Ok((map_size, grid_size, tile_size, map_type, anchor, map_transform)) =
+            tilemap_q.get(tilemap_id.0)
+        else {
+            continue;
+        };
+        let center = tile_pos.center_in_world(map_size, grid_size, tile_size, map_type, anchor);
+        let center = map_transform.transform_point(center.extend(0.0)).xy();
+        let size = Vec2::new(grid_size.x, grid_size.y) * map_transform.scale().xy();
+        gizmos.rect_2d(center, size, OBSTACLE_COLOR);
+    }
+}
+
+/// Draw the area the camera center is clamped to, and the [`Aabb`] of everything that moves,
+/// with dots on the corners of the footprint that movement checks for obstacles.
+fn draw_bounds_and_hitboxes(
+    mut gizmos: Gizmos,
+    camera_bounds: Res<CameraBounds>,
+    mover_q: Query<(&Aabb, &GlobalTransform), With<WithinBounds>>,
+) {
+    let bounds = Rect::from_corners(camera_bounds.min, camera_bounds.max);
+    gizmos.rect_2d(bounds.center(), bounds.size(), CAMERA_BOUNDS_COLOR);
+
+    for (aabb, transform) in &mover_q {
+        let center = transform.transform_point(aabb.center.into()).xy();
+        let size = 2.0 * Vec3::from(aabb.half_extents).xy() * transform.scale().xy();
+        gizmos.rect_2d(center, size, HITBOX_COLOR);
+        let footprint = character_footprint(transform.scale().xy());
+        for corner in footprint_corners(transform.translation().xy(), footprint) {
+            gizmos.circle_2d(corner, 3.0, HITBOX_COLOR);
+        }
+    }
+}
@@ -1,17 +1,39 @@
-use bevy::{prelude::*, sprite::Anchor};
-use rand::{prelude::*, rng};
-use std::time::Duration;
+//! Player sprite animation.
+//!
+//! Input is only ever read in the `player` module, which records movement
+//! intent and the requested [`ActionType`]. This module follows that state:
+//! it picks the animation for the current [`MovementController`] intent and
+//! [`PlayerActionState`], and resolves actions once they complete.
+
+use bevy::{platform::collections::HashMap, prelude::*, sprite::Anchor};
+use rand::prelude::*;
+use std::{sync::LazyLock, time::Duration};
+
+use bevy_ecs_tilemap::prelude::*;
 
 use crate::{
     AppSystems,
     audio::sound_effect,
-    game::{movement::MovementController, player::PlayerAssets},
-    states::GameState,
+    constants::TILE_WORLD_SIZE,
+    game::{
+        movement::MovementController,
+        player::{PlayerAssets, step_sound_effect},
+        rng::GameRng,
+        stamina::Stamina,
+    },
+    states::GameplayState,
+    world::{tiledhelper::TileType, tilemap::TileQuery},
 };
 
 pub(super) fn plugin(app: &mut App) {
     // Animate and play sound effects based on controls.
     app.register_type::<PlayerAnimation>();
+    app.register_type::<Facing>();
+    app.register_type::<ActionConfig>();
+    app.init_resource::<ActionConfig>();
+    app.add_message::<TileInteraction>();
+    app.add_message::<ActionLanded>();
+    app.add_message::<Footstep>();
     app.add_systems(
         Update,
         (
@@ -23,14 +45,14 @@ pub(super) fn plugin(app: &mut App) {
                 trigger_step_sound_effect,
             )
                 .chain()
-                .run_if(resource_exists::<PlayerAssets>.and(in_state(GameState::Gameplay)))
+                .run_if(resource_exists::<PlayerAssets>.and(in_state(GameplayState::Running)))
                 .in_set(AppSystems::Update),
         ),
     );
 }
 
 /// Represents the direction of the player animation.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
 pub enum Direction {
     Top,
     Bottom,
@@ -38,20 +60,180 @@ pub enum Direction {
     Right,
 }
 
+/// How close the two axes of a movement intent must be to count as an exact diagonal.
+const DIAGONAL_TOLERANCE: f32 = 1e-3;
+
+impl Direction {
+    /// The direction to face while moving with `intent`.
+    ///
+    /// The character atlas only has frames for the four cardinal directions, so
+    /// diagonal movement faces along the dominant axis. On an exact diagonal the
+    /// `current` direction is kept if it is one of the two candidates, so the
+    /// sprite doesn't flip back and forth between them.
+    pub fn from_movement(intent: Vec2, current: Direction) -> Direction {
+        let horizontal = if intent.x > 0.0 {
+            Direction::Right
+        } else {
+            Direction::Left
+        };
+        let vertical = if intent.y > 0.0 {
+            Direction::Top
+        } else {
+            Direction::Bottom
+        };
+        let (x, y) = (intent.x.abs(), intent.y.abs());
+        if (x - y).abs() <= DIAGONAL_TOLERANCE && (current == horizontal || current == vertical) {
+            current
+        } else if y > x {
+            vertical
+        } else {
+            horizontal
+        }
+    }
+
+    /// Unit vector pointing in this direction.
+    pub fn as_vec2(self) -> Vec2 {
+        match self {
+            Direction::Top => Vec2::Y,
+            Direction::Bottom => Vec2::NEG_Y,
+            Direction::Left => Vec2::NEG_X,
+            Direction::Right => Vec2::X,
+        }
+    }
+}
+
+/// World position of the center of the tile in front of `position` when facing `direction`.
+pub fn facing_tile_position(position: Vec2, direction: Direction) -> Vec2 {
+    position + direction.as_vec2() * TILE_WORLD_SIZE
+}
+
+/// The direction the player is facing, kept in step with their animation.
+///
+/// Gameplay systems that target the tile in front of the player read this
+/// instead of digging into [`PlayerAnimation`].
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct Facing(pub Direction);
+
+impl Default for Facing {
+    fn default() -> Self {
+        Self(Direction::Bottom)
+    }
+}
+
 #[derive(Component, Debug, Default)]
 pub struct PlayerActionState {
     pub current_action: Option<ActionType>,
     pub action_progress: f32, // 0.0 to 1.0
+    /// Action requested while another was in progress; starts as soon as the current one ends.
+    pub queued_action: Option<ActionType>,
+}
+
+/// Written when the player finishes an action on a tile, once for every layer's tile
+/// at the target. Systems that change the world in response subscribe to this
+/// rather than the animation poking at tiles itself.
+#[derive(Message, Debug, Clone, Copy)]
+pub struct TileInteraction {
+    pub tile_pos: TilePos,
+    pub tile_entity: Entity,
+    pub action: ActionType,
+}
+
+/// Written when the player finishes an action, aimed at the spot in front of them. Unlike
+/// [`TileInteraction`] it's written even when there's no tile there, for whatever isn't a
+/// tile, like enemies.
+#[derive(Message, Debug, Clone, Copy)]
+pub struct ActionLanded {
+    pub action: ActionType,
+    /// One tile in front of the player, where the action struck.
+    pub target: Vec2,
+}
+
+/// Written on every step of a walk cycle, in time with the step sound.
+#[derive(Message, Debug, Clone)]
+pub struct Footstep {
+    pub entity: Entity,
+    /// The ground stepped on, if there's a tile there.
+    pub tile_type: Option<TileType>,
 }
 
 /// Represents the action type of the player animation.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
 pub enum ActionType {
     Hoeing,
     Watering,
     Chopping,
 }
 
+impl ActionType {
+    /// Every action, in the order of the tool hotbar.
+    pub const ALL: [ActionType; 3] = [
+        ActionType::Hoeing,
+        ActionType::Watering,
+        ActionType::Chopping,
+    ];
+
+    /// The tool that performs the action.
+    pub fn tool_name(self) -> &'static str {
+        match self {
+            ActionType::Hoeing => "Hoe",
+            ActionType::Watering => "Watering Can",
+            ActionType::Chopping => "Axe",
+        }
+    }
+}
+
+/// Seconds an action takes when it isn't listed in [`ActionConfig`].
+const DEFAULT_ACTION_DURATION: f32 = 0.6;
+/// Stamina an action costs when it isn't listed in [`ActionConfig`].
+const DEFAULT_ACTION_STAMINA_COST: f32 = 10.0;
+
+/// How long each action takes. This drives both when the action completes and
+/// how fast its animation frames advance, so visuals and logic stay in sync.
+#[derive(Resource, Debug, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct ActionConfig {
+    /// Seconds from starting an action to completing it.
+    pub durations: HashMap<ActionType, f32>,
+    /// [`Stamina`] spent when an action starts.
+    pub stamina_costs: HashMap<ActionType, f32>,
+}
+
+impl Default for ActionConfig {
+    fn default() -> Self {
+        Self {
+            durations: HashMap::from_iter([
+                (ActionType::Hoeing, DEFAULT_ACTION_DURATION),
+                (ActionType::Watering, DEFAULT_ACTION_DURATION),
+                (ActionType::Chopping, DEFAULT_ACTION_DURATION),
+            ]),
+            stamina_costs: HashMap::from_iter([
+                (ActionType::Hoeing, DEFAULT_ACTION_STAMINA_COST),
+                (ActionType::Watering, 5.0),
+                (ActionType::Chopping, 15.0),
+            ]),
+        }
+    }
+}
+
+impl ActionConfig {
+    /// Seconds `action` takes. A negative duration, which the inspector can set, makes it instant.
+    pub fn duration(&self, action: ActionType) -> f32 {
+        self.durations
+            .get(&action)
+            .copied()
+            .unwrap_or(DEFAULT_ACTION_DURATION)
+            .max(0.0)
+    }
+
+    pub fn stamina_cost(&self, action: ActionType) -> f32 {
+        self.stamina_costs
+            .get(&action)
+            .copied()
+            .unwrap_or(DEFAULT_ACTION_STAMINA_COST)
+    }
+}
+
 impl PlayerAnimationState {
     // Get the direction component of this state
     pub fn get_direction(&self) -> Direction {
@@ -71,6 +253,24 @@ impl PlayerAnimationState {
         }
     }
 
+    pub fn idling(direction: Direction) -> Self {
+        match direction {
+            Direction::Top => Self::IdlingT,
+            Direction::Bottom => Self::IdlingB,
+            Direction::Left => Self::IdlingL,
+            Direction::Right => Self::IdlingR,
+        }
+    }
+
+    pub fn walking(direction: Direction) -> Self {
+        match direction {
+            Direction::Top => Self::WalkingT,
+            Direction::Bottom => Self::WalkingB,
+            Direction::Left => Self::WalkingL,
+            Direction::Right => Self::WalkingR,
+        }
+    }
+
     // Create a state from action and direction
     pub fn from_action_and_direction(action: ActionType, direction: Direction) -> Self {
         match (action, direction) {
@@ -93,92 +293,108 @@ impl PlayerAnimationState {
 }
 
 fn update_animation_actions(
+    mut commands: Commands,
     time: Res<Time>,
-    mut player_query: Query<(&mut PlayerAnimation, &mut PlayerActionState)>,
+    action_config: Res<ActionConfig>,
+    player_assets: Res<PlayerAssets>,
+    mut player_query: Query<(
+        &mut PlayerAnimation,
+        &mut PlayerActionState,
+        &GlobalTransform,
+        &Facing,
+        Option<&mut Stamina>,
+    )>,
+    tiles: TileQuery,
+    mut tile_interactions: MessageWriter<TileInteraction>,
+    mut actions_landed: MessageWriter<ActionLanded>,
 ) {
-    let (mut animation, mut action_state) =
-        player_query.single_mut().expect("Player should exist!");
-    let direction = animation.state.get_direction();
+    let Ok((mut animation, mut action_state, transform, facing, stamina)) =
+        player_query.single_mut()
+    else {
+        return;
+    };
+    let direction = facing.0;
 
-    if action_state.current_action.is_none() {
+    let Some(action) = action_state.current_action else {
         return;
-    }
-    let new_state = PlayerAnimationState::from_action_and_direction(
-        action_state.current_action.unwrap(),
-        direction,
-    );
+    };
+    let new_state = PlayerAnimationState::from_action_and_direction(action, direction);
     if animation.state != new_state {
-        animation.update_state(new_state);
+        animation.update_state(new_state, &action_config);
         animation.set_state_changed(true);
     } else {
         action_state.action_progress += time.delta_secs();
 
-        // Check if action is complete (adjust times based on your animations)
-        let action_duration = match action_state.current_action {
-            Some(ActionType::Watering) => 2.0 * PlayerAnimation::WATERING_DURATION, // seconds
-            Some(ActionType::Hoeing) => 2.0 * PlayerAnimation::HOEING_DURATION,     // seconds
-            Some(ActionType::Chopping) => 2.0 * PlayerAnimation::CHOPPING_DURATION, // seconds
-            _ => 0.0,
-        };
-        if action_state.action_progress >= action_duration {
+        // Check if action is complete
+        if action_state.action_progress >= action_config.duration(action) {
+            // Let the world react to what the player just did to the tile in front of them
+            let target = facing_tile_position(transform.translation().xy(), direction);
+            actions_landed.write(ActionLanded { action, target });
+            let targets: Vec<_> = tiles.tiles_at(target).collect();
+            if targets.is_empty() {
+                debug!("{action:?} whiffed: no tile at {target}");
+            }
+            tile_interactions.write_batch(targets.into_iter().map(|(tile_entity, tile_pos)| {
+                TileInteraction {
+                    tile_pos,
+                    tile_entity,
+                    action,
+                }
+            }));
+
+            // Chain straight into a queued action, if any, and if there's stamina left for it
+            if let Some(next_action) = action_state.queued_action.take() {
+                let cost = action_config.stamina_cost(next_action);
+                if stamina.is_none_or(|mut stamina| stamina.try_spend(cost)) {
+                    action_state.current_action = Some(next_action);
+                    action_state.action_progress = 0.0;
+                    return;
+                }
+                commands.spawn(sound_effect(player_assets.denied.clone()));
+            }
+
             // Action complete, return to idle state
             action_state.current_action = None;
 
             // Return to idle state based on current direction
-            let new_state = match direction {
-                Direction::Top => PlayerAnimationState::IdlingT,
-                Direction::Bottom => PlayerAnimationState::IdlingB,
-                Direction::Left => PlayerAnimationState::IdlingL,
-                Direction::Right => PlayerAnimationState::IdlingR,
-            };
-            animation.update_state(new_state);
+            animation.update_state(PlayerAnimationState::idling(direction), &action_config);
         }
     }
 }
 
 /// Update the sprite direction and animation state (idling/walking).
 fn update_animation_movement(
+    action_config: Res<ActionConfig>,
     mut player_query: Query<(
         &MovementController,
         &mut PlayerAnimation,
         &PlayerActionState,
+        &mut Facing,
     )>,
 ) {
-    for (controller, mut animation, state) in &mut player_query {
+    for (controller, mut animation, state, mut facing) in &mut player_query {
         // If the player is performing an action, skip movement animation
         if state.current_action.is_some() {
             continue;
         }
-        let intent = controller.intent;
-        let current_direction = animation.state.get_direction();
+        let current_direction = facing.0;
 
         // Determine new direction and action based on movement
-        let animation_state = if intent == Vec2::ZERO {
-            match current_direction {
-                Direction::Top => PlayerAnimationState::IdlingT,
-                Direction::Bottom => PlayerAnimationState::IdlingB,
-                Direction::Left => PlayerAnimationState::IdlingL,
-                Direction::Right => PlayerAnimationState::IdlingR,
-            }
+        let animation_state = if controller.is_still() {
+            PlayerAnimationState::idling(current_direction)
         } else {
             // Determine direction from movement and set action to walking
-            if intent.y.abs() > intent.x.abs() {
-                if intent.y > 0.0 {
-                    PlayerAnimationState::WalkingT
-                } else {
-                    PlayerAnimationState::WalkingB
-                }
-            } else if intent.x > 0.0 {
-                PlayerAnimationState::WalkingR
-            } else {
-                PlayerAnimationState::WalkingL
-            }
+            PlayerAnimationState::walking(Direction::from_movement(
+                controller.intent,
+                current_direction,
+            ))
         };
 
         if animation.state != animation_state {
             animation.set_state_changed(true);
-            animation.update_state(animation_state);
+            animation.update_state(animation_state, &action_config);
         }
+        facing.set_if_neq(Facing(animation_state.get_direction()));
     }
 }
 
@@ -203,21 +419,30 @@ fn update_animation_atlas(mut query: Query<(&PlayerAnimation, &mut Sprite, &mut
 }
 
 /// If the player is moving, play a step sound effect synchronized with the
-/// animation.
+/// animation, matching the ground they are walking on.
 fn trigger_step_sound_effect(
     mut commands: Commands,
     player_assets: Res<PlayerAssets>,
-    mut step_query: Query<&mut PlayerAnimation>,
+    mut rng: ResMut<GameRng>,
+    mut step_query: Query<(Entity, &mut PlayerAnimation, &GlobalTransform)>,
+    tiles: TileQuery,
+    mut footsteps: MessageWriter<Footstep>,
 ) {
-    for mut animation in &mut step_query {
+    for (entity, mut animation, transform) in &mut step_query {
         if animation.state.is_walking() && animation.changed() {
-            let rng = &mut rng();
+            let tile_type = tiles
+                .tile_at(transform.translation().xy())
+                .map(|(_, _, tile_type)| tile_type);
+            footsteps.write(Footstep {
+                entity,
+                tile_type: tile_type.cloned(),
+            });
             let random_step = player_assets
                 .steps
-                .choose(rng)
+                .choose(&mut rng.0)
                 .expect("Player assets should exist!")
                 .clone();
-            commands.spawn(sound_effect(random_step));
+            commands.spawn(step_sound_effect(random_step, tile_type));
         }
         animation.set_state_changed(false);
     }
@@ -234,7 +459,7 @@ pub struct PlayerAnimation {
     state_changed: bool,
 }
 
-#[derive(Reflect, PartialEq, Debug)]
+#[derive(Reflect, Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum PlayerAnimationState {
     IdlingT,
     IdlingB,
@@ -278,81 +503,155 @@ impl PlayerAnimationState {
                 | PlayerAnimationState::IdlingR
         )
     }
+
+    /// How far the sprite's anchor is shifted on `frame`, so poses where a tool sticks out
+    /// to one side keep the body in place.
     pub fn get_anchor_point(&self, frame: usize) -> Vec2 {
-        match self {
-            PlayerAnimationState::HoeingL => {
-                if frame == 1 {
-                    Vec2::new(0.2, 0.0)
-                } else {
-                    Vec2::ZERO
-                }
-            }
-            PlayerAnimationState::HoeingR => {
-                if frame == 1 {
-                    Vec2::new(-0.2, 0.0)
-                } else {
-                    Vec2::ZERO
-                }
-            }
-            PlayerAnimationState::WateringR => {
-                if frame == 1 {
-                    Vec2::new(-0.2, 0.0)
-                } else {
-                    Vec2::new(-0.25, 0.0)
-                }
-            }
-            PlayerAnimationState::WateringL => {
-                if frame == 1 {
-                    Vec2::new(0.3, 0.0)
-                } else {
-                    Vec2::new(0.25, 0.0)
-                }
-            }
-            PlayerAnimationState::ChoppingR => {
-                if frame == 1 {
-                    Vec2::new(-0.2, 0.0)
-                } else {
-                    Vec2::new(0.2, 0.0)
-                }
-            }
-            PlayerAnimationState::ChoppingL => {
-                if frame == 1 {
-                    Vec2::new(0.2, 0.0)
-                } else {
-                    Vec2::new(-0.2, 0.0)
-                }
-            }
-            PlayerAnimationState::ChoppingT => {
-                if frame == 1 {
-                    Vec2::new(-0.1, 0.0)
-                } else {
-                    Vec2::ZERO
-                }
-            }
-            PlayerAnimationState::ChoppingB => {
-                if frame == 1 {
-                    Vec2::ZERO
-                } else {
-                    Vec2::new(0.1, 0.0)
-                }
+        self.clip()
+            .anchors
+            .get(frame)
+            .copied()
+            .unwrap_or(Vec2::ZERO)
+    }
+
+    fn clip(&self) -> &'static AnimationClip {
+        &ANIMATION_CLIPS[self]
+    }
+}
+
+/// How one [`PlayerAnimationState`] plays from the character atlas.
+#[derive(Debug, Clone)]
+struct AnimationClip {
+    /// Atlas index of the first frame. The other frames follow it.
+    start_index: usize,
+    frames: usize,
+    interval: ClipInterval,
+    /// Anchor offset of each frame. Frames without one stay centered.
+    anchors: Vec<Vec2>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ClipInterval {
+    Fixed(Duration),
+    /// Spread the action's duration from [`ActionConfig`] over the clip's frames.
+    Action(ActionType),
+}
+
+const IDLE_INTERVAL: Duration = Duration::from_millis(500);
+const WALKING_INTERVAL: Duration = Duration::from_millis(150);
+const IDLE_FRAMES: usize = 2;
+const WALKING_FRAMES: usize = 2;
+const ACTION_FRAMES: usize = 2;
+
+impl AnimationClip {
+    fn idling(start_index: usize) -> Self {
+        Self {
+            start_index,
+            frames: IDLE_FRAMES,
+            interval: ClipInterval::Fixed(IDLE_INTERVAL),
+            anchors: Vec::new(),
+        }
+    }
+
+    fn walking(start_index: usize) -> Self {
+        Self {
+            start_index,
+            frames: WALKING_FRAMES,
+            interval: ClipInterval::Fixed(WALKING_INTERVAL),
+            anchors: Vec::new(),
+        }
+    }
+
+    fn action(
+        start_index: usize,
+        action: ActionType,
+        anchors: [(f32, f32); ACTION_FRAMES],
+    ) -> Self {
+        Self {
+            start_index,
+            frames: ACTION_FRAMES,
+            interval: ClipInterval::Action(action),
+            anchors: anchors.map(|(x, y)| Vec2::new(x, y)).to_vec(),
+        }
+    }
+
+    fn interval(&self, action_config: &ActionConfig) -> Duration {
+        match self.interval {
+            ClipInterval::Fixed(interval) => interval,
+            ClipInterval::Action(action) => {
+                Duration::from_secs_f32(action_config.duration(action) / self.frames as f32)
             }
-            _ => Vec2::ZERO,
         }
     }
 }
 
-impl PlayerAnimation {
-    const WATERING_DURATION: f32 = 0.3;
-    const HOEING_DURATION: f32 = 0.3;
-    const CHOPPING_DURATION: f32 = 0.3;
-    const IDLE_INTERVAL: Duration = Duration::from_millis(500);
-    const WALKING_INTERVAL: Duration = Duration::from_millis(150);
-    const WALKING_FRAMES: usize = 2;
-    const HOEING_FRAMES: usize = 2;
-    const WATERING_FRAMES: usize = 2;
-    const IDLE_FRAMES: usize = 2;
-    const CHOPPING_FRAMES: usize = 2;
+/// Every animation the player has. The character sheet has a row per direction,
+/// with two frames each of idling, walking, hoeing, chopping and watering.
+static ANIMATION_CLIPS: LazyLock<HashMap<PlayerAnimationState, AnimationClip>> =
+    LazyLock::new(|| {
+        use ActionType::*;
+        use PlayerAnimationState::*;
+        HashMap::from_iter([
+            (IdlingB, AnimationClip::idling(0)),
+            (WalkingB, AnimationClip::walking(2)),
+            (
+                HoeingB,
+                AnimationClip::action(4, Hoeing, [(0.0, 0.0), (0.0, 0.0)]),
+            ),
+            (
+                ChoppingB,
+                AnimationClip::action(6, Chopping, [(0.1, 0.0), (0.0, 0.0)]),
+            ),
+            (
+                WateringB,
+                AnimationClip::action(8, Watering, [(0.0, 0.0), (0.0, 0.0)]),
+            ),
+            (IdlingT, AnimationClip::idling(16)),
+            (WalkingT, AnimationClip::walking(18)),
+            (
+                HoeingT,
+                AnimationClip::action(20, Hoeing, [(0.0, 0.0), (0.0, 0.0)]),
+            ),
+            (
+                ChoppingT,
+                AnimationClip::action(22, Chopping, [(0.0, 0.0), (-0.1, 0.0)]),
+            ),
+            (
+                WateringT,
+                AnimationClip::action(24, Watering, [(0.0, 0.0), (0.0, 0.0)]),
+            ),
+            (IdlingL, AnimationClip::idling(32)),
+            (WalkingL, AnimationClip::walking(34)),
+            (
+                HoeingL,
+                AnimationClip::action(36, Hoeing, [(0.0, 0.0), (0.2, 0.0)]),
+            ),
+            (
+                ChoppingL,
+                AnimationClip::action(38, Chopping, [(-0.2, 0.0), (0.2, 0.0)]),
+            ),
+            (
+                WateringL,
+                AnimationClip::action(40, Watering, [(0.25, 0.0), (0.3, 0.0)]),
+            ),
+            (IdlingR, AnimationClip::idling(48)),
+            (WalkingR, AnimationClip::walking(50)),
+            (
+                HoeingR,
+                AnimationClip::action(52, Hoeing, [(0.0, 0.0), (-0.2, 0.0)]),
+            ),
+            (
+                ChoppingR,
+                AnimationClip::action(54, Chopping, [(0.2, 0.0), (-0.2, 0.0)]),
+            ),
+            (
+                WateringR,
+                AnimationClip::action(56, Watering, [(-0.25, 0.0), (-0.2, 0.0)]),
+            ),
+        ])
+    });
 
+impl PlayerAnimation {
     fn internal_new(duration: Duration, state: PlayerAnimationState) -> Self {
         Self {
             timer: Timer::new(duration, TimerMode::Repeating),
@@ -363,7 +662,7 @@ impl PlayerAnimation {
     }
 
     pub fn new() -> Self {
-        Self::internal_new(Self::IDLE_INTERVAL, PlayerAnimationState::IdlingB)
+        Self::internal_new(IDLE_INTERVAL, PlayerAnimationState::IdlingB)
     }
 
     /// Update animation timers.
@@ -372,136 +671,13 @@ impl PlayerAnimation {
         if !self.timer.is_finished() {
             return;
         }
-        self.frame = (self.frame + 1)
-            % match self.state {
-                PlayerAnimationState::IdlingB => Self::IDLE_FRAMES,
-                PlayerAnimationState::IdlingT => Self::IDLE_FRAMES,
-                PlayerAnimationState::IdlingL => Self::IDLE_FRAMES,
-                PlayerAnimationState::IdlingR => Self::IDLE_FRAMES,
-                PlayerAnimationState::WalkingT => Self::WALKING_FRAMES,
-                PlayerAnimationState::WalkingL => Self::WALKING_FRAMES,
-                PlayerAnimationState::WalkingR => Self::WALKING_FRAMES,
-                PlayerAnimationState::WalkingB => Self::WALKING_FRAMES,
-                PlayerAnimationState::HoeingT => Self::HOEING_FRAMES,
-                PlayerAnimationState::HoeingL => Self::HOEING_FRAMES,
-                PlayerAnimationState::HoeingR => Self::HOEING_FRAMES,
-                PlayerAnimationState::HoeingB => Self::HOEING_FRAMES,
-                PlayerAnimationState::WateringT => Self::WATERING_FRAMES,
-                PlayerAnimationState::WateringL => Self::WATERING_FRAMES,
-                PlayerAnimationState::WateringR => Self::WATERING_FRAMES,
-                PlayerAnimationState::WateringB => Self::WATERING_FRAMES,
-                PlayerAnimationState::ChoppingT => Self::CHOPPING_FRAMES,
-                PlayerAnimationState::ChoppingB => Self::CHOPPING_FRAMES,
-                PlayerAnimationState::ChoppingL => Self::CHOPPING_FRAMES,
-                PlayerAnimationState::ChoppingR => Self::CHOPPING_FRAMES,
-            };
+        self.frame = (self.frame + 1) % self.state.clip().frames;
     }
 
     /// Update animation state if it changes.
-    pub fn update_state(&mut self, state: PlayerAnimationState) {
+    pub fn update_state(&mut self, state: PlayerAnimationState, action_config: &ActionConfig) {
         if self.state != state {
-            match state {
-                PlayerAnimationState::IdlingB => {
-                    *self = Self::internal_new(Self::IDLE_INTERVAL, PlayerAnimationState::IdlingB)
-                }
-                PlayerAnimationState::IdlingT => {
-                    *self = Self::internal_new(Self::IDLE_INTERVAL, PlayerAnimationState::IdlingT)
-                }
-                PlayerAnimationState::IdlingL => {
-                    *self = Self::internal_new(Self::IDLE_INTERVAL, PlayerAnimationState::IdlingL)
-                }
-                PlayerAnimationState::IdlingR => {
-                    *self = Self::internal_new(Self::IDLE_INTERVAL, PlayerAnimationState::IdlingR)
-                }
-                PlayerAnimationState::WalkingB => {
-                    *self =
-                        Self::internal_new(Self::WALKING_INTERVAL, PlayerAnimationState::WalkingB)
-                }
-                PlayerAnimationState::WalkingT => {
-                    *self =
-                        Self::internal_new(Self::WALKING_INTERVAL, PlayerAnimationState::WalkingT)
-                }
-                PlayerAnimationState::WalkingL => {
-                    *self =
-                        Self::internal_new(Self::WALKING_INTERVAL, PlayerAnimationState::WalkingL)
-                }
-                PlayerAnimationState::WalkingR => {
-                    *self =
-                        Self::internal_new(Self::WALKING_INTERVAL, PlayerAnimationState::WalkingR)
-                }
-                PlayerAnimationState::HoeingT => {
-                    *self = Self::internal_new(
-                        Duration::from_secs_f32(Self::HOEING_DURATION),
-                        PlayerAnimationState::HoeingT,
-                    )
-                }
-                PlayerAnimationState::HoeingB => {
-                    *self = Self::internal_new(
-                        Duration::from_secs_f32(Self::HOEING_DURATION),
-                        PlayerAnimationState::HoeingB,
-                    )
-                }
-                PlayerAnimationState::HoeingL => {
-                    *self = Self::internal_new(
-                        Duration::from_secs_f32(Self::HOEING_DURATION),
-                        PlayerAnimationState::HoeingL,
-                    )
-                }
-                PlayerAnimationState::HoeingR => {
-                    *self = Self::internal_new(
-                        Duration::from_secs_f32(Self::HOEING_DURATION),
-                        PlayerAnimationState::HoeingR,
-                    )
-                }
-                PlayerAnimationState::WateringT => {
-                    *self = Self::internal_new(
-                        Duration::from_secs_f32(Self::WATERING_DURATION),
-                        PlayerAnimationState::WateringT,
-                    )
-                }
-                PlayerAnimationState::WateringB => {
-                    *self = Self::internal_new(
-                        Duration::from_secs_f32(Self::WATERING_DURATION),
-                        PlayerAnimationState::WateringB,
-                    )
-                }
-                PlayerAnimationState::WateringL => {
-                    *self = Self::internal_new(
-                        Duration::from_secs_f32(Self::WATERING_DURATION),
-                        PlayerAnimationState::WateringL,
-                    )
-                }
-                PlayerAnimationState::WateringR => {
-                    *self = Self::internal_new(
-                        Duration::from_secs_f32(Self::WATERING_DURATION),
-                        PlayerAnimationState::WateringR,
-                    )
-                }
-                PlayerAnimationState::ChoppingT => {
-                    *self = Self::internal_new(
-                        Duration::from_secs_f32(Self::CHOPPING_DURATION),
-                        PlayerAnimationState::ChoppingT,
-                    )
-                }
-                PlayerAnimationState::ChoppingB => {
-                    *self = Self::internal_new(
-                        Duration::from_secs_f32(Self::CHOPPING_DURATION),
-                        PlayerAnimationState::ChoppingB,
-                    )
-                }
-                PlayerAnimationState::ChoppingL => {
-                    *self = Self::internal_new(
-                        Duration::from_secs_f32(Self::CHOPPING_DURATION),
-                        PlayerAnimationState::ChoppingL,
-                    )
-                }
-                PlayerAnimationState::ChoppingR => {
-                    *self = Self::internal_new(
-                        Duration::from_secs_f32(Self::CHOPPING_DURATION),
-                        PlayerAnimationState::ChoppingR,
-                    )
-                }
-            }
+            *self = Self::internal_new(state.clip().interval(action_config), state);
         }
     }
 
@@ -521,27 +697,19 @@ impl PlayerAnimation {
 
     /// Return sprite index in the atlas.
     pub fn get_atlas_index(&self) -> usize {
-        match self.state {
-            PlayerAnimationState::IdlingB => self.frame,
-            PlayerAnimationState::WalkingB => 2 + self.frame,
-            PlayerAnimationState::IdlingL => 32 + self.frame,
-            PlayerAnimationState::WalkingL => 34 + self.frame,
-            PlayerAnimationState::IdlingR => 48 + self.frame,
-            PlayerAnimationState::WalkingR => 50 + self.frame,
-            PlayerAnimationState::IdlingT => 16 + self.frame,
-            PlayerAnimationState::WalkingT => 18 + self.frame,
-            PlayerAnimationState::HoeingT => 20 + self.frame,
-            PlayerAnimationState::HoeingB => 4 + self.frame,
-            PlayerAnimationState::HoeingL => 36 + self.frame,
-            PlayerAnimationState::HoeingR => 52 + self.frame,
-            PlayerAnimationState::WateringT => 24 + self.frame,
-            PlayerAnimationState::WateringB => 8 + self.frame,
-            PlayerAnimationState::WateringL => 40 + self.frame,
-            PlayerAnimationState::WateringR => 56 + self.frame,
-            PlayerAnimationState::ChoppingT => 22 + self.frame,
-            PlayerAnimationState::ChoppingB => 6 + self.frame,
-            PlayerAnimationState::ChoppingL => 38 + self.frame,
-            PlayerAnimationState::ChoppingR => 54 + self.frame,
-        }
+        self.state.clip().start_index + self.frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negative_action_durations_give_instant_frames() {
+        let mut action_config = ActionConfig::default();
+        action_config.durations.insert(ActionType::Chopping, -1.0);
+        let clip = &ANIMATION_CLIPS[&PlayerAnimationState::ChoppingB];
+        assert_eq!(clip.interval(&action_config), Duration::ZERO);
     }
 }
@@ -1,32 +1,60 @@
 use bevy::{
-    input::mouse::{MouseScrollUnit, MouseWheel},
+    input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel},
     prelude::*,
     window::{PrimaryWindow, WindowResized},
 };
 
-use crate::{AppSystems, constants::*, states::GameState};
+use crate::{
+    AppSystems,
+    constants::*,
+    states::{GameState, GameplayState},
+    world::tiledhelper::MapDimensions,
+};
 
-use super::player::Player;
+use super::{minimap::MinimapCamera, player::Player};
 
 pub(super) fn plugin(app: &mut App) {
     app.register_type::<WithinBounds>();
+    app.register_type::<WrapMode>();
+    app.init_resource::<WrapMode>();
     app.init_resource::<CameraBounds>();
+    app.register_type::<CursorPos>();
     app.init_resource::<CursorPos>();
+    app.register_type::<CameraOffset>();
+    app.init_resource::<CameraOffset>();
+    app.register_type::<ZoomSettings>();
+    app.init_resource::<ZoomSettings>();
+    app.register_type::<CameraMode>();
+    app.init_resource::<CameraMode>();
+    app.register_type::<ScreenShake>();
+    app.init_resource::<ScreenShake>();
+    app.register_type::<PixelSnap>();
+    app.init_resource::<PixelSnap>();
     app.add_message::<CameraScaleEvent>();
     app.add_systems(OnEnter(GameState::Gameplay), calculate_camera_bounds);
 
     app.add_systems(
         Update,
         (
-            camera_zoom.run_if(on_message::<MouseWheel>),
+            remove_pixel_snap,
+            remove_screen_shake,
             update_cursor_pos,
+            update_camera_mode,
+            camera_zoom.run_if(on_message::<MouseWheel>),
+            calculate_camera_bounds.run_if(
+                on_message::<WindowResized>
+                    .or(on_message::<CameraScaleEvent>)
+                    .or(resource_changed::<MapDimensions>),
+            ),
             apply_screen_wrap,
-            camera_follow_player,
-            calculate_camera_bounds
-                .run_if(on_message::<WindowResized>.or(on_message::<CameraScaleEvent>)),
+            camera_follow_player.run_if(resource_equals(CameraMode::Follow)),
+            camera_pan.run_if(resource_equals(CameraMode::Free)),
+            apply_screen_shake,
+            apply_pixel_snap,
         )
+            .chain()
             .in_set(AppSystems::PostUpdate)
-            .run_if(in_state(GameState::Gameplay)),
+            .run_if(in_state(GameplayState::Running)),
     );
 }
 
@@ -34,19 +62,48 @@ pub(super) fn plugin(app: &mut App) {
 #[reflect(Component)]
 pub struct WithinBounds;
 
-const WRAP_Y_OFFSET: f32 = 12.0;
+/// What happens to a [`WithinBounds`] entity that reaches the edge of the map.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub enum WrapMode {
+    /// Stop at the edge.
+    #[default]
+    Clamp,
+    /// Come back in from the opposite edge, as if the map were a torus.
+    Wrap,
+}
+
+/// Move `value` back inside `min..=max`, either by clamping it or by wrapping it around.
+fn wrap_or_clamp(value: f32, min: f32, max: f32, mode: WrapMode) -> f32 {
+    match mode {
+        WrapMode::Clamp => value.clamp(min, max),
+        WrapMode::Wrap if value < min || value > max => min + (value - min).rem_euclid(max - min),
+        WrapMode::Wrap => value,
+    }
+}
 
-fn apply_screen_wrap(mut wrap_query: Query<&mut Transform, With<WithinBounds>>) {
-    let player_size_x = (GRID_SIZE_X - 8) as f32 * PLAYER_SCALE;
-    let player_size_y = (GRID_SIZE_Y - 14) as f32 * PLAYER_SCALE;
-    let width = MAP_WIDTH as f32 * TILE_SIZE as f32 * TILE_SCALE;
-    let half_width = width / 2.0 - player_size_x / 2.0;
-    let height = MAP_HEIGHT as f32 * TILE_SIZE as f32 * TILE_SCALE;
-    let half_height = height / 2.0 - player_size_y / 2.0;
+fn apply_screen_wrap(
+    wrap_mode: Res<WrapMode>,
+    map_dimensions: Res<MapDimensions>,
+    mut wrap_query: Query<&mut Transform, With<WithinBounds>>,
+) {
+    // Keep the visible character inside the map, not its whole sprite frame, which has
+    // empty space around it and doesn't center the character vertically.
+    let player_size_x = PLAYER_VISIBLE_WIDTH as f32 * PLAYER_SCALE;
+    let player_size_y = PLAYER_VISIBLE_HEIGHT as f32 * PLAYER_SCALE;
+    let player_offset_y = PLAYER_VISIBLE_OFFSET_Y * PLAYER_SCALE;
+    let map_size = map_dimensions.world_size();
+    let half_width = map_size.x / 2.0 - player_size_x / 2.0;
+    let half_height = map_size.y / 2.0 - player_size_y / 2.0;
     for mut transform in &mut wrap_query {
         let position = transform.translation.xy();
-        let clamped_x = position.x.clamp(-half_width, half_width);
-        let clamped_y = position.y.clamp(-half_height + WRAP_Y_OFFSET, half_height);
+        let clamped_x = wrap_or_clamp(position.x, -half_width, half_width, *wrap_mode);
+        let clamped_y = wrap_or_clamp(
+            position.y,
+            -half_height - player_offset_y,
+            half_height - player_offset_y,
+            *wrap_mode,
+        );
         transform.translation = Vec3::new(clamped_x, clamped_y, transform.translation.z);
     }
 }
@@ -72,13 +129,14 @@ impl FromWorld for CameraBounds {
 
 fn calculate_camera_bounds(
     mut camera_bounds: ResMut<CameraBounds>,
+    map_dimensions: Res<MapDimensions>,
     windows: Query<&Window>,
-    projection_query: Query<&Projection, With<Camera2d>>,
+    projection_query: Query<&Projection, (With<Camera2d>, Without<MinimapCamera>)>,
 ) {
-    let window = windows.single().expect("Window should exist!");
-    let projection = projection_query
-        .single()
-        .expect("Camera projection should exist!");
+    // Either may be missing for a frame around state transitions.
+    let (Ok(window), Ok(projection)) = (windows.single(), projection_query.single()) else {
+        return;
+    };
 
     let (win_w, win_h, scale) = match projection {
         Projection::Orthographic(ortho) => (window.width(), window.height(), ortho.scale),
@@ -88,10 +146,9 @@ fn calculate_camera_bounds(
     let half_visible_w = (win_w * 0.5) * scale;
     let half_visible_h = (win_h * 0.5) * scale;
 
-    let map_width = MAP_WIDTH as f32 * TILE_SIZE as f32 * TILE_SCALE;
-    let map_height = MAP_HEIGHT as f32 * TILE_SIZE as f32 * TILE_SCALE;
-    let half_map_w = map_width / 2.0;
-    let half_map_h = map_height / 2.0;
+    let map_size = map_dimensions.world_size();
+    let half_map_w = map_size.x / 2.0;
+    let half_map_h = map_size.y / 2.0;
 
     camera_bounds.min.x = -half_map_w + half_visible_w;
     camera_bounds.max.x = half_map_w - half_visible_w;
@@ -99,20 +156,83 @@ fn calculate_camera_bounds(
     camera_bounds.max.y = half_map_h - half_visible_h;
 }
 
+/// Whether the camera tracks the player or is being panned around freely.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub enum CameraMode {
+    #[default]
+    Follow,
+    /// Held while dragging with the middle mouse button, to survey the map without moving.
+    Free,
+}
+
+fn update_camera_mode(mouse: Res<ButtonInput<MouseButton>>, mut camera_mode: ResMut<CameraMode>) {
+    let mode = if mouse.pressed(MouseButton::Middle) {
+        CameraMode::Free
+    } else {
+        CameraMode::Follow
+    };
+    camera_mode.set_if_neq(mode);
+}
+
+/// Drag the camera with the mouse, still keeping it inside the [`CameraBounds`].
+fn camera_pan(
+    mut motion_evr: MessageReader<MouseMotion>,
+    camera_bounds: Res<CameraBounds>,
+    mut camera_query: Query<
+        (&mut Transform, &Projection),
+        (With<Camera2d>, Without<MinimapCamera>),
+    >,
+) {
+    let delta: Vec2 = motion_evr.read().map(|motion| motion.delta).sum();
+    if delta == Vec2::ZERO {
+        return;
+    }
+    let Ok((mut camera_transform, projection)) = camera_query.single_mut() else {
+        return;
+    };
+    let scale = match projection {
+        Projection::Orthographic(ortho) => ortho.scale,
+        _ => 1.0,
+    };
+
+    // Move the camera against the drag so the map follows the cursor.
+    // Screen space y points down, world space y points up.
+    let translation = &mut camera_transform.translation;
+    translation.x =
+        (translation.x - delta.x * scale).clamp(camera_bounds.min.x, camera_bounds.max.x);
+    translation.y =
+        (translation.y + delta.y * scale).clamp(camera_bounds.min.y, camera_bounds.max.y);
+}
+
+/// Where the camera sits relative to the player, so zooming towards the cursor
+/// isn't undone by the follow on the next frame.
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct CameraOffset(pub Vec2);
+
 fn camera_follow_player(
     _time: Res<Time>,
     player_query: Query<&Transform, With<Player>>,
-    mut camera_query: Query<&mut Transform, (With<Camera2d>, Without<Player>)>,
+    mut camera_query: Query<
+        &mut Transform,
+        (With<Camera2d>, Without<Player>, Without<MinimapCamera>),
+    >,
     camera_bounds: Res<CameraBounds>,
+    camera_offset: Res<CameraOffset>,
 ) {
-    let player_transform = player_query.single().expect("Player should exist!");
-    let mut camera_transform = camera_query.single_mut().expect("Camera should exist!");
+    // The player isn't spawned yet on the first frame of a new level.
+    let (Ok(player_transform), Ok(mut camera_transform)) =
+        (player_query.single(), camera_query.single_mut())
+    else {
+        return;
+    };
 
-    let player_pos = player_transform.translation.xy();
-    let mut target_x = player_pos.x;
-    let mut target_y = player_pos.y;
+    let follow_pos = player_transform.translation.xy() + camera_offset.0;
+    let mut target_x = follow_pos.x;
+    let mut target_y = follow_pos.y;
     target_x = target_x.clamp(camera_bounds.min.x, camera_bounds.max.x);
-    target_y = target_y.clamp(camera_bounds.min.y - WRAP_Y_OFFSET, camera_bounds.max.y);
+    target_y = target_y.clamp(camera_bounds.min.y, camera_bounds.max.y);
 
     let target_position = Vec3::new(target_x, target_y, camera_transform.translation.z);
 
@@ -122,10 +242,207 @@ fn camera_follow_player(
     camera_transform.translation = target_position;
 }
 
+/// Furthest the camera is pushed away from where it should be at full trauma, in world units.
+const MAX_SHAKE_OFFSET: f32 = TILE_SCALE * 4.0;
+/// How fast the shake wobbles back and forth.
+const SHAKE_FREQUENCY: f32 = 30.0;
+
+/// Shakes the camera after impacts. Add trauma to start a shake; it decays back to nothing
+/// on its own.
+#[derive(Resource, Debug, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct ScreenShake {
+    /// How violent the shake is, from 0 to 1. The offset grows with its square, so small
+    /// hits stay subtle.
+    pub trauma: f32,
+    /// Trauma lost per second.
+    pub decay: f32,
+    /// Offset applied to the camera this frame, taken off again before it moves next frame.
+    applied: Vec2,
+    /// Trauma added since [`ScreenShake::take_impacts`] was last called, so other feedback
+    /// like controller rumble can follow the same impacts.
+    impacts: f32,
+}
+
+impl Default for ScreenShake {
+    fn default() -> Self {
+        Self {
+            trauma: 0.0,
+            decay: 1.5,
+            applied: Vec2::ZERO,
+            impacts: 0.0,
+        }
+    }
+}
+
+impl ScreenShake {
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).min(1.0);
+        self.impacts += amount;
+    }
+
+    /// The trauma added since this was last called.
+    pub fn take_impacts(&mut self) -> f32 {
+        std::mem::take(&mut self.impacts)
+    }
+}
+
+/// Take last frame's shake off the camera so following, panning and zooming work
+/// from its steady position.
+fn remove_screen_shake(
+    mut screen_shake: ResMut<ScreenShake>,
+    mut camera_query: Query<&mut Transform, (With<Camera2d>, Without<MinimapCamera>)>,
+) {
+    if screen_shake.applied == Vec2::ZERO {
+        return;
+    }
+    if let Ok(mut camera_transform) = camera_query.single_mut() {
+        camera_transform.translation -= screen_shake.applied.extend(0.0);
+    }
+    screen_shake.applied = Vec2::ZERO;
+}
+
+/// Offset the camera by a wobble proportional to the trauma, without showing anything
+/// outside the [`CameraBounds`].
+fn apply_screen_shake(
+    time: Res<Time>,
+    camera_bounds: Res<CameraBounds>,
+    mut screen_shake: ResMut<ScreenShake>,
+    mut camera_query: Query<&mut Transform, (With<Camera2d>, Without<MinimapCamera>)>,
+) {
+    if screen_shake.trauma <= 0.0 {
+        return;
+    }
+    let Ok(mut camera_transform) = camera_query.single_mut() else {
+        return;
+    };
+    let t = time.elapsed_secs() * SHAKE_FREQUENCY;
+    // Two sines at unrelated rates per axis read as noise without needing an RNG.
+    let wobble = Vec2::new(
+        (t * 1.0).sin() + (t * 2.3 + 1.7).sin() * 0.5,
+        (t * 1.3 + 4.1).sin() + (t * 2.9 + 0.6).sin() * 0.5,
+    ) / 1.5;
+    let offset = wobble * MAX_SHAKE_OFFSET * screen_shake.trauma.powi(2);
+
+    let steady = camera_transform.translation.xy();
+    let shaken = Vec2::new(
+        (steady.x + offset.x).clamp(camera_bounds.min.x, camera_bounds.max.x),
+        (steady.y + offset.y).clamp(camera_bounds.min.y, camera_bounds.max.y),
+    );
+    camera_transform.translation = shaken.extend(camera_transform.translation.z);
+    screen_shake.applied = shaken - steady;
+    screen_shake.trauma = (screen_shake.trauma - screen_shake.decay * time.delta_secs()).max(0.0);
+}
+
+/// Rounds the camera's final position to the art's pixel grid, a [`TILE_SCALE`] world units
+/// wide, so pixel art doesn't shimmer as the camera glides over it. Can be turned off, since
+/// the rounding makes slow camera movement step instead of glide.
+#[derive(Resource, Debug, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct PixelSnap {
+    pub enabled: bool,
+    /// Offset applied to the camera this frame, taken off again before it moves next frame.
+    applied: Vec2,
+}
+
+impl Default for PixelSnap {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            applied: Vec2::ZERO,
+        }
+    }
+}
+
+/// Round `value` to a multiple of `step`, staying within `min..=max` when the range holds one.
+fn snap_within(value: f32, step: f32, min: f32, max: f32) -> f32 {
+    let lowest = (min / step).ceil() * step;
+    let highest = (max / step).floor() * step;
+    if lowest > highest {
+        return value;
+    }
+    ((value / step).round() * step).clamp(lowest, highest)
+}
+
+/// Take last frame's rounding off the camera, so panning by less than a pixel still adds up.
+fn remove_pixel_snap(
+    mut pixel_snap: ResMut<PixelSnap>,
+    mut camera_query: Query<&mut Transform, (With<Camera2d>, Without<MinimapCamera>)>,
+) {
+    if pixel_snap.applied == Vec2::ZERO {
+        return;
+    }
+    if let Ok(mut camera_transform) = camera_query.single_mut() {
+        camera_transform.translation -= pixel_snap.applied.extend(0.0);
+    }
+    pixel_snap.applied = Vec2::ZERO;
+}
+
+fn apply_pixel_snap(
+    camera_bounds: Res<CameraBounds>,
+    mut pixel_snap: ResMut<PixelSnap>,
+    mut camera_query: Query<&mut Transform, (With<Camera2d>, Without<MinimapCamera>)>,
+) {
+    if !pixel_snap.enabled {
+        return;
+    }
+    let Ok(mut camera_transform) = camera_query.single_mut() else {
+        return;
+    };
+    let position = camera_transform.translation.xy();
+    let snapped = Vec2::new(
+        snap_within(
+            position.x,
+            TILE_SCALE,
+            camera_bounds.min.x,
+            camera_bounds.max.x,
+        ),
+        snap_within(
+            position.y,
+            TILE_SCALE,
+            camera_bounds.min.y,
+            camera_bounds.max.y,
+        ),
+    );
+    camera_transform.translation = snapped.extend(camera_transform.translation.z);
+    pixel_snap.applied = snapped - position;
+}
+
+/// Limits and sensitivity of the mouse wheel zoom.
+#[derive(Resource, Debug, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct ZoomSettings {
+    /// Smallest orthographic scale, i.e. the furthest you can zoom in.
+    pub min_scale: f32,
+    /// Largest orthographic scale. Never exceeds what still fits inside the map.
+    pub max_scale: f32,
+    /// Fraction of the scale changed per scroll line.
+    pub speed: f32,
+}
+
+impl Default for ZoomSettings {
+    fn default() -> Self {
+        Self {
+            min_scale: 0.2,
+            max_scale: 1.0,
+            speed: 0.1,
+        }
+    }
+}
+
 fn camera_zoom(
     mut scroll_evr: MessageReader<MouseWheel>,
     mut ew: MessageWriter<CameraScaleEvent>,
-    mut query: Query<&mut Projection, With<Camera2d>>,
+    zoom_settings: Res<ZoomSettings>,
+    map_dimensions: Res<MapDimensions>,
+    windows: Query<&Window>,
+    cursor_pos: Res<CursorPos>,
+    mut camera_offset: ResMut<CameraOffset>,
+    player_query: Query<&Transform, With<Player>>,
+    mut query: Query<
+        (&mut Projection, &Transform),
+        (With<Camera2d>, Without<Player>, Without<MinimapCamera>),
+    >,
 ) {
     // Calculate the total scroll amount from all events
     let scroll_amount = scroll_evr.read().fold(0.0, |acc, ev| {
@@ -140,19 +457,41 @@ fn camera_zoom(
     }
 
     // Apply zoom to all 2D cameras
-    let mut projection = query.single_mut().expect("Camera should exist!");
-    // Adjust zoom speed/sensitivity
-    let zoom_speed = 0.1;
+    let (Ok((mut projection, camera_transform)), Ok(player_transform), Ok(window)) =
+        (query.single_mut(), player_query.single(), windows.single())
+    else {
+        return;
+    };
+
+    // Never zoom out so far that the view is larger than the map, which would
+    // leave nothing valid for the camera bounds to clamp to.
+    let map_size = map_dimensions.world_size();
+    let max_scale = zoom_settings
+        .max_scale
+        .min(map_size.x / window.width())
+        .min(map_size.y / window.height());
 
     // Adjust scale - smaller values zoom in
     if let Projection::Orthographic(ref mut ortho) = *projection {
-        ortho.scale *= 1.0 - scroll_amount * zoom_speed;
-        // Clamp to reasonable limits
-        ortho.scale = ortho.scale.clamp(0.2, 1.0);
+        let old_scale = ortho.scale;
+        ortho.scale *= 1.0 - scroll_amount * zoom_settings.speed;
+        ortho.scale = ortho.scale.clamp(zoom_settings.min_scale, max_scale);
+
+        // Keep the world point under the cursor in place: its distance to the
+        // camera center shrinks and grows along with the scale.
+        let center = camera_transform.translation.xy();
+        let zoomed_center = cursor_pos.0 + (center - cursor_pos.0) * (ortho.scale / old_scale);
+        // Fully zoomed out, recenter on the player again.
+        camera_offset.0 = if ortho.scale >= max_scale {
+            Vec2::ZERO
+        } else {
+            zoomed_center - player_transform.translation.xy()
+        };
         ew.write(CameraScaleEvent);
     }
 }
-#[derive(Resource)]
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
 pub struct CursorPos(pub Vec2);
 impl Default for CursorPos {
     fn default() -> Self {
@@ -162,12 +501,12 @@ impl Default for CursorPos {
     }
 }
 fn update_cursor_pos(
-    camera_q: Query<(&GlobalTransform, &Camera)>,
+    camera_q: Query<(&GlobalTransform, &Camera), Without<MinimapCamera>>,
     q_window: Query<&Window, With<PrimaryWindow>>,
     mut cursor_pos: ResMut<CursorPos>,
 ) {
-    let (cam_t, cam) = camera_q.single().expect("Camera should exist!");
-    if let Ok(window) = q_window.single()
+    if let Ok((cam_t, cam)) = camera_q.single()
+        && let Ok(window) = q_window.single()
         && let Some(pos) = window.cursor_position()
         && let Ok(pos) = cam.viewport_to_world_2d(cam_t, pos)
     {
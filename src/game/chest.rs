@@ -0,0 +1,409 @@
+//! Chests placed on the map that hold items for the player.
+//!
+//! A chest is an object on one of the map's object layers with a `chest` property set to
+//! true. Its contents are kept per map in [`ChestContents`], so they survive leaving the map
+//! and end up in the save.
+
+use bevy::{ecs::spawn::SpawnWith, platform::collections::HashMap, prelude::*, ui::Val::*};
+
+use crate::{
+    AppSystems,
+    constants::{PLAYER_Z, TILE_WORLD_SIZE},
+    game::{
+        animation::Facing,
+        dialogue::dialogue_open,
+        interaction::{INTERACT_KEY, Interactable, in_front},
+        inventory::{Inventory, ItemKind},
+        level::LevelSpawnSystems,
+        movement::MovementController,
+        npc::talk_to_npcs,
+        player::Player,
+        y_sort::YSort,
+    },
+    states::{DestroyOnEnter, GameState, GameplayState, VisibleInState},
+    theme::{prelude::*, widget::Modal},
+    world::{
+        tiledhelper::{TiledMap, TiledMapHandle, process_loaded_maps},
+        tilemap::CurrentMap,
+    },
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<Chest>();
+    app.register_type::<Storage>();
+    app.register_type::<ChestContents>();
+    app.register_type::<OpenChest>();
+    app.init_resource::<ChestContents>();
+    app.init_resource::<OpenChest>();
+    app.add_systems(
+        OnEnter(GameState::Gameplay),
+        reset_chest_contents.in_set(LevelSpawnSystems),
+    );
+    app.add_systems(OnEnter(GameState::Title), close_chest);
+    app.add_systems(OnEnter(GameState::GameOver), close_chest);
+    app.add_systems(
+        Update,
+        (
+            (respawn_edited_chests, spawn_map_chests)
+                .chain()
+                .after(process_loaded_maps)
+                .in_set(AppSystems::PreUpdate),
+            (
+                (close_chest_on_escape, stop_player)
+                    .run_if(chest_open)
+                    .in_set(AppSystems::RecordInput),
+                open_chests
+                    .run_if(not(dialogue_open).and(not(chest_open)))
+                    // Talking takes priority when an NPC is standing by the chest.
+                    .after(talk_to_npcs)
+                    .in_set(AppSystems::RecordInput),
+            ),
+            record_chest_contents.in_set(AppSystems::Update),
+            (sync_chest_panel, update_chest_counts)
+                .chain()
+                .in_set(AppSystems::PostUpdate),
+        )
+            .run_if(in_state(GameplayState::Running)),
+    );
+}
+
+/// Size of a chest sprite in world units.
+const CHEST_SIZE: f32 = TILE_WORLD_SIZE * 5.0 / 6.0;
+/// Placeholder color used to draw chests until they have proper art.
+const CHEST_COLOR: Color = Color::srgb(0.478, 0.294, 0.129);
+const CHEST_PANEL_BACKGROUND: Color = Color::srgba(0.05, 0.05, 0.1, 0.85);
+
+/// A chest on the map, identified by its Tiled object id.
+#[derive(Component, Debug, Clone, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct Chest {
+    /// The map the chest is on.
+    pub map: String,
+    pub id: u32,
+}
+
+/// Items kept in a chest.
+#[derive(Component, Debug, Default, Clone, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct Storage {
+    items: HashMap<ItemKind, u32>,
+}
+
+impl Storage {
+    pub fn count(&self, kind: ItemKind) -> u32 {
+        self.items.get(&kind).copied().unwrap_or(0)
+    }
+
+    pub fn add(&mut self, kind: ItemKind, amount: u32) {
+        *self.items.entry(kind).or_insert(0) += amount;
+    }
+
+    /// Remove `amount` items of `kind` if there are that many, returning whether they were removed.
+    pub fn take(&mut self, kind: ItemKind, amount: u32) -> bool {
+        match self.items.get_mut(&kind) {
+            Some(count) if *count >= amount => {
+                *count -= amount;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The contents of every chest the player has used, by map and object id. Chests are
+/// respawned with their map, so this is what remembers what's in them.
+#[derive(Resource, Debug, Default, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct ChestContents(pub HashMap<(String, u32), Storage>);
+
+/// The chest whose panel is open, if any.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub struct OpenChest(pub Option<Entity>);
+
+/// Run condition that's true while a chest's panel is open.
+pub fn chest_open(open_chest: Res<OpenChest>) -> bool {
+    open_chest.0.is_some()
+}
+
+/// Marks a map whose chests have been spawned.
+#[derive(Component)]
+struct ChestsSpawned;
+
+pub fn chest(chest: Chest, storage: Storage, pos: Vec2) -> impl Bundle {
+    (
+        Name::new(format!("Chest {}", chest.id)),
+        chest,
+        storage,
+        Sprite::from_color(CHEST_COLOR, Vec2::splat(CHEST_SIZE)),
+        Transform::from_translation(pos.extend(PLAYER_Z)),
+        Interactable::new("Open"),
+        YSort,
+        VisibleInState(vec![GameState::Gameplay]),
+        DestroyOnEnter(vec![GameState::Title, GameState::GameOver]),
+    )
+}
+
+/// A new game starts with every chest empty.
+fn reset_chest_contents(mut chest_contents: ResMut<ChestContents>) {
+    chest_contents.0.clear();
+}
+
+/// Respawn the chests of a map edited while the game runs, so they follow the edit.
+fn respawn_edited_chests(
+    mut commands: Commands,
+    mut map_events: MessageReader<AssetEvent<TiledMap>>,
+    map_q: Query<(Entity, &TiledMapHandle), With<ChestsSpawned>>,
+    chest_q: Query<Entity, With<Chest>>,
+) {
+    let modified: Vec<_> = map_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+    for (map_entity, handle) in &map_q {
+        if !modified.contains(&handle.0.id()) {
+            continue;
+        }
+        for entity in &chest_q {
+            commands.entity(entity).despawn();
+        }
+        commands.entity(map_entity).remove::<ChestsSpawned>();
+    }
+}
+
+/// Spawn the chests of a map once it has loaded, filled with whatever was left in them.
+fn spawn_map_chests(
+    mut commands: Commands,
+    maps: Res<Assets<TiledMap>>,
+    map_q: Query<(Entity, &TiledMapHandle), Without<ChestsSpawned>>,
+    current_map: Res<CurrentMap>,
+    chest_contents: Res<ChestContents>,
+) {
+    for (map_entity, handle) in &map_q {
+        let Some(tiled_map) = maps.get(&handle.0) else {
+            continue;
+        };
+        for object in tiled_map.objects() {
+            let Some(tiled::PropertyValue::BoolValue(true)) = object.properties.get("chest") else {
+                continue;
+            };
+            let chest_data = Chest {
+                map: current_map.0.clone(),
+                id: object.id(),
+            };
+            let storage = chest_contents
+                .0
+                .get(&(chest_data.map.clone(), chest_data.id))
+                .cloned()
+                .unwrap_or_default();
+            let position = tiled_map.to_world(Vec2::new(object.x, object.y));
+            commands.spawn(chest(chest_data, storage, position));
+        }
+        commands.entity(map_entity).insert(ChestsSpawned);
+    }
+}
+
+/// Open the chest the player is facing.
+fn open_chests(
+    input: Res<ButtonInput<KeyCode>>,
+    player_q: Query<(&GlobalTransform, &Facing), With<Player>>,
+    chest_q: Query<(Entity, &GlobalTransform), With<Chest>>,
+    mut open_chest: ResMut<OpenChest>,
+) {
+    if !input.just_pressed(INTERACT_KEY) {
+        return;
+    }
+    let Ok((player_transform, facing)) = player_q.single() else {
+        return;
+    };
+    if let Some(chest) = in_front(player_transform.translation().xy(), *facing, chest_q) {
+        open_chest.0 = Some(chest);
+    }
+}
+
+fn close_chest(mut open_chest: ResMut<OpenChest>) {
+    open_chest.0 = None;
+}
+
+/// Close the chest on Escape instead of pausing the game.
+fn close_chest_on_escape(
+    mut input: ResMut<ButtonInput<KeyCode>>,
+    mut open_chest: ResMut<OpenChest>,
+) {
+    if input.just_pressed(KeyCode::Escape) {
+        input.clear_just_pressed(KeyCode::Escape);
+        open_chest.0 = None;
+    }
+}
+
+/// Keep the player standing still while they're rummaging through a chest.
+fn stop_player(mut controller_q: Query<&mut MovementController, With<Player>>) {
+    for mut controller in &mut controller_q {
+        controller.intent = Vec2::ZERO;
+    }
+}
+
+/// Remember what's in each chest so it outlives the chest's entity.
+fn record_chest_contents(
+    chest_q: Query<(&Chest, &Storage), Changed<Storage>>,
+    mut chest_contents: ResMut<ChestContents>,
+) {
+    for (chest, storage) in &chest_q {
+        chest_contents
+            .0
+            .insert((chest.map.clone(), chest.id), storage.clone());
+    }
+}
+
+/// The panel for moving items between the [`Inventory`] and the open chest.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+struct ChestPanel(Entity);
+
+/// Shows how many items of a kind are carried, or stored in the open chest.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+enum ChestCountLabel {
+    Carried(ItemKind),
+    Stored(ItemKind),
+}
+
+fn chest_panel(chest: Entity) -> impl Bundle {
+    (
+        Name::new("Chest Panel"),
+        ChestPanel(chest),
+        Modal,
+        Node {
+            position_type: PositionType::Absolute,
+            width: Percent(100.0),
+            height: Percent(100.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        BackgroundColor(ui_palette::MODAL_BACKDROP),
+        VisibleInState(vec![GameState::Gameplay]),
+        DestroyOnEnter(vec![GameState::Title, GameState::GameOver]),
+        children![(
+            Name::new("Chest Window"),
+            Node {
+                padding: UiRect::all(Px(24.0)),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Px(16.0),
+                ..default()
+            },
+            BackgroundColor(CHEST_PANEL_BACKGROUND),
+            BorderRadius::all(Px(8.0)),
+            children![
+                widget::header("Chest"),
+                transfer_grid(chest),
+                widget::label("Esc: close"),
+            ],
+        )],
+    )
+}
+
+/// One row per item: what's carried, buttons to move one item either way, and what's stored.
+fn transfer_grid(chest: Entity) -> impl Bundle {
+    (
+        Name::new("Transfer Grid"),
+        Node {
+            display: Display::Grid,
+            row_gap: Px(10.0),
+            column_gap: Px(20.0),
+            grid_template_columns: vec![
+                GridTrack::px(200.0),
+                GridTrack::px(60.0),
+                GridTrack::auto(),
+                GridTrack::auto(),
+                GridTrack::px(60.0),
+            ],
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        Children::spawn(SpawnWith(move |parent: &mut ChildSpawner| {
+            for kind in ItemKind::ALL {
+                parent.spawn(widget::label(kind.name()));
+                parent.spawn((widget::label(""), ChestCountLabel::Carried(kind)));
+                parent.spawn(widget::button_small(
+                    ">",
+                    move |_: On<Pointer<Click>>,
+                          mut inventory: ResMut<Inventory>,
+                          mut storage_q: Query<&mut Storage>| {
+                        if let Ok(mut storage) = storage_q.get_mut(chest)
+                            && inventory.take(kind, 1)
+                        {
+                            storage.add(kind, 1);
+                        }
+                    },
+                ));
+                parent.spawn(widget::button_small(
+                    "<",
+                    move |_: On<Pointer<Click>>,
+                          mut inventory: ResMut<Inventory>,
+                          mut storage_q: Query<&mut Storage>| {
+                        if let Ok(mut storage) = storage_q.get_mut(chest)
+                            && storage.take(kind, 1)
+                        {
+                            inventory.add(kind, 1);
+                        }
+                    },
+                ));
+                parent.spawn((widget::label(""), ChestCountLabel::Stored(kind)));
+            }
+        })),
+    )
+}
+
+/// Show the panel of the open chest, or nothing once it's closed.
+fn sync_chest_panel(
+    mut commands: Commands,
+    mut open_chest: ResMut<OpenChest>,
+    chest_q: Query<(), With<Chest>>,
+    panel_q: Query<(Entity, &ChestPanel)>,
+) {
+    // The chest can disappear under the panel, e.g. when a save is loaded.
+    if open_chest.0.is_some_and(|chest| !chest_q.contains(chest)) {
+        open_chest.0 = None;
+    }
+    if !open_chest.is_changed() {
+        return;
+    }
+    for (entity, panel) in &panel_q {
+        if Some(panel.0) != open_chest.0 {
+            commands.entity(entity).despawn();
+        }
+    }
+    if let Some(chest) = open_chest.0
+        && panel_q.iter().all(|(_, panel)| panel.0 != chest)
+    {
+        commands.spawn(chest_panel(chest));
+    }
+}
+
+fn update_chest_counts(
+    open_chest: Res<OpenChest>,
+    inventory: Res<Inventory>,
+    storage_q: Query<Ref<Storage>>,
+    mut label_q: Query<(&mut Text, &ChestCountLabel)>,
+    new_label_q: Query<(), Added<ChestCountLabel>>,
+) {
+    let Some(Ok(storage)) = open_chest.0.map(|chest| storage_q.get(chest)) else {
+        return;
+    };
+    // A freshly opened panel still needs its first counts.
+    if !inventory.is_changed() && !storage.is_changed() && new_label_q.is_empty() {
+        return;
+    }
+    for (mut text, label) in &mut label_q {
+        let count = match *label {
+            ChestCountLabel::Carried(kind) => inventory.count(kind),
+            ChestCountLabel::Stored(kind) => storage.count(kind),
+        };
+        text.0 = count.to_string();
+    }
+}
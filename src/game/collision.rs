@@ -1,3 +1,19 @@
-pub(super) fn plugin(app: &mut App) {}
-#[derive(Component, Default)]
-struct Collider;
+//! Overlap helpers shared by gameplay systems.
+
+use bevy::{camera::primitives::Aabb, prelude::*};
+
+/// The world-space rectangle covered by an entity's [`Aabb`].
+/// The [`Aabb`] is computed in the sprite's local space, so it has to be
+/// scaled and moved by the entity's [`GlobalTransform`].
+pub fn world_rect(transform: &GlobalTransform, aabb: &Aabb) -> Rect {
+    let center = transform.transform_point(Vec3::from(aabb.center)).xy();
+    let half_size = (Vec3::from(aabb.half_extents) * transform.scale())
+        .xy()
+        .abs();
+    Rect::from_center_half_size(center, half_size)
+}
+
+/// Whether two world-space rectangles overlap.
+pub fn overlaps(a: Rect, b: Rect) -> bool {
+    !a.intersect(b).is_empty()
+}
@@ -0,0 +1,226 @@
+//! Health, damage, the contact damage enemies deal to the player and the chops the
+//! player deals back.
+
+use bevy::{camera::primitives::Aabb, prelude::*};
+
+use crate::{
+    AppSystems,
+    constants::{TILE_SCALE, TILE_WORLD_SIZE},
+    game::{
+        animation::{ActionLanded, ActionType},
+        camera::ScreenShake,
+        collision::{overlaps, world_rect},
+        effects::Burst,
+        enemy::{Enemy, EnemyKind},
+        inventory::pickup,
+        player::Player,
+        rng::GameRng,
+    },
+    states::{GameState, GameplayState},
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<Health>();
+    app.register_type::<Invulnerable>();
+    app.add_systems(
+        Update,
+        (
+            tick_invulnerability.in_set(AppSystems::TickTimers),
+            (apply_enemy_contact_damage, check_player_death)
+                .chain()
+                .in_set(AppSystems::Update),
+            (chop_enemies, defeat_enemies)
+                .chain()
+                .in_set(AppSystems::PostUpdate),
+        )
+            .run_if(in_state(GameplayState::Running)),
+    );
+}
+
+const ENEMY_CONTACT_DAMAGE: f32 = 1.0;
+/// [`ScreenShake`] trauma added when the player gets hit.
+const HIT_TRAUMA: f32 = 0.6;
+const CHOP_DAMAGE: f32 = 1.0;
+/// Size of the area a chop hits: the whole tile in front of the player.
+const CHOP_HITBOX_SIZE: Vec2 = Vec2::splat(TILE_WORLD_SIZE);
+/// [`ScreenShake`] trauma added when the player lands a chop on an enemy.
+const CHOP_TRAUMA: f32 = 0.2;
+/// The puff an enemy leaves behind when defeated.
+const DEATH_BURST: Burst = Burst {
+    color: Color::srgb(0.9, 0.9, 0.95),
+    count: 12,
+    speed: TILE_SCALE * 73.0,
+};
+/// How long the player is immune to further damage after being hit.
+const INVULNERABILITY_SECS: f32 = 1.0;
+/// How fast the sprite blinks while invulnerable.
+const BLINK_INTERVAL_SECS: f32 = 0.1;
+const BLINK_ALPHA: f32 = 0.3;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Self { current: max, max }
+    }
+
+    pub fn damage(&mut self, amount: f32) {
+        self.current = (self.current - amount).max(0.0);
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.0
+    }
+}
+
+/// Invulnerability frames granted after taking a hit. Removed when the timer finishes.
+#[derive(Component, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct Invulnerable(pub Timer);
+
+impl Default for Invulnerable {
+    fn default() -> Self {
+        Self(Timer::from_seconds(INVULNERABILITY_SECS, TimerMode::Once))
+    }
+}
+
+fn tick_invulnerability(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Invulnerable, &mut Sprite)>,
+) {
+    for (entity, mut invulnerable, mut sprite) in &mut query {
+        invulnerable.0.tick(time.delta());
+        if invulnerable.0.is_finished() {
+            sprite.color.set_alpha(1.0);
+            commands.entity(entity).remove::<Invulnerable>();
+            continue;
+        }
+        let blink = (invulnerable.0.elapsed_secs() / BLINK_INTERVAL_SECS) as u32 % 2 == 1;
+        sprite
+            .color
+            .set_alpha(if blink { BLINK_ALPHA } else { 1.0 });
+    }
+}
+
+fn apply_enemy_contact_damage(
+    mut commands: Commands,
+    mut player_query: Query<
+        (Entity, &GlobalTransform, &Aabb, &mut Health),
+        (With<Player>, Without<Invulnerable>),
+    >,
+    enemy_query: Query<(&GlobalTransform, &Aabb), With<Enemy>>,
+    mut screen_shake: ResMut<ScreenShake>,
+) {
+    for (entity, transform, aabb, mut health) in &mut player_query {
+        let player_rect = world_rect(transform, aabb);
+        let touching_enemy = enemy_query.iter().any(|(enemy_transform, enemy_aabb)| {
+            overlaps(player_rect, world_rect(enemy_transform, enemy_aabb))
+        });
+        if !touching_enemy {
+            continue;
+        }
+        health.damage(ENEMY_CONTACT_DAMAGE);
+        screen_shake.add_trauma(HIT_TRAUMA);
+        commands.entity(entity).insert(Invulnerable::default());
+    }
+}
+
+fn check_player_death(
+    player_query: Query<&Health, (With<Player>, Changed<Health>)>,
+    mut next_screen: ResMut<NextState<GameState>>,
+) {
+    if player_query.iter().any(Health::is_dead) {
+        next_screen.set(GameState::GameOver);
+    }
+}
+
+/// When a chop lands, every enemy overlapping the hitbox in front of the player gets
+/// hurt, then a moment of blinking immunity so one swing can't land twice.
+fn chop_enemies(
+    mut commands: Commands,
+    mut actions_landed: MessageReader<ActionLanded>,
+    mut enemy_query: Query<
+        (Entity, &GlobalTransform, &Aabb, &mut Health),
+        (With<Enemy>, Without<Invulnerable>),
+    >,
+    mut screen_shake: ResMut<ScreenShake>,
+) {
+    for landed in actions_landed.read() {
+        if landed.action != ActionType::Chopping {
+            continue;
+        }
+        let hitbox = Rect::from_center_size(landed.target, CHOP_HITBOX_SIZE);
+        for (entity, transform, aabb, mut health) in &mut enemy_query {
+            if !overlaps(hitbox, world_rect(transform, aabb)) {
+                continue;
+            }
+            health.damage(CHOP_DAMAGE);
+            screen_shake.add_trauma(CHOP_TRAUMA);
+            commands.entity(entity).insert(Invulnerable::default());
+        }
+    }
+}
+
+fn defeat_enemies(
+    mut commands: Commands,
+    enemy_query: Query<(Entity, &EnemyKind, &GlobalTransform, &Health), Changed<Health>>,
+    mut rng: ResMut<GameRng>,
+) {
+    for (entity, kind, transform, health) in &enemy_query {
+        if !health.is_dead() {
+            continue;
+        }
+        let position = transform.translation().xy();
+        commands.entity(entity).despawn();
+        DEATH_BURST.spawn(&mut commands, &mut rng, position);
+        if let Some((item, amount)) = kind.loot() {
+            commands.spawn(pickup(item, amount, position));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    fn chop_at(target: Vec2, enemy_position: Vec2) -> Health {
+        let mut world = World::new();
+        world.init_resource::<ScreenShake>();
+        world.init_resource::<Messages<ActionLanded>>();
+        let enemy = world
+            .spawn((
+                Enemy,
+                GlobalTransform::from_translation(enemy_position.extend(0.0)),
+                Aabb::from_min_max(Vec3::splat(-4.0), Vec3::splat(4.0)),
+                Health::new(3.0),
+            ))
+            .id();
+        world.write_message(ActionLanded {
+            action: ActionType::Chopping,
+            target,
+        });
+        world.run_system_once(chop_enemies).unwrap();
+        *world.get::<Health>(enemy).unwrap()
+    }
+
+    #[test]
+    fn chops_hit_where_they_land() {
+        let target = Vec2::new(10.0, 0.0) * TILE_WORLD_SIZE;
+        assert_eq!(chop_at(target, target).current, 2.0);
+    }
+
+    #[test]
+    fn chops_miss_enemies_away_from_the_target() {
+        let target = Vec2::new(10.0, 0.0) * TILE_WORLD_SIZE;
+        let elsewhere = target + Vec2::new(0.0, 2.0 * TILE_WORLD_SIZE);
+        assert_eq!(chop_at(target, elsewhere).current, 3.0);
+    }
+}
@@ -0,0 +1,627 @@
+//! Farming: tilling soil, growing crops on it and harvesting them once they're ripe.
+//!
+//! Hoeing grass or dirt tills it, and hoeing tilled soil plants the [`SelectedSeed`]
+//! there. A [`Crop`] grows one stage at a time, and chopping it once it reaches its
+//! last stage harvests it, leaving the soil tilled for the next one. Watering tilled
+//! soil makes the crop on it grow faster until the soil dries out again.
+//!
+//! Leaving a map keeps its tilled soil and crops in [`Farmland`], and they come back
+//! with its tiles. Nothing grows or dries out on a map while the player is away.
+
+use std::time::Duration;
+
+use bevy::{
+    ecs::system::SystemParam,
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+};
+use bevy_ecs_tilemap::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AppSystems,
+    audio::sound_effect,
+    constants::TILE_SCALE,
+    game::{
+        animation::{ActionType, TileInteraction},
+        inventory::{Inventory, ItemKind, pickup},
+        level::LevelSpawnSystems,
+        movement::ObstacleGrid,
+        notifications::NotifyEvent,
+        player::PlayerAssets,
+    },
+    states::{DestroyOnEnter, GameState, GameplayState, VisibleInState},
+    world::{
+        tiledhelper::{HighlightedTile, TileType, process_loaded_maps, set_tile_color},
+        tilemap::{CurrentMap, LoadMapEvent, TileQuery, TilemapLayout, switch_map},
+    },
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<Tilled>();
+    app.register_type::<Crop>();
+    app.register_type::<Watered>();
+    app.register_type::<SelectedSeed>();
+    app.register_type::<WateringConfig>();
+    app.register_type::<Farmland>();
+    app.init_resource::<SelectedSeed>();
+    app.init_resource::<WateringConfig>();
+    app.init_resource::<Farmland>();
+    app.add_systems(
+        OnEnter(GameState::Gameplay),
+        reset_farmland.in_set(LevelSpawnSystems),
+    );
+    app.add_systems(
+        Update,
+        (
+            stash_plots
+                .before(switch_map)
+                .run_if(in_state(GameplayState::Running)),
+            restore_plots.after(process_loaded_maps),
+        )
+            .in_set(AppSystems::PreUpdate),
+    );
+    app.add_systems(
+        Update,
+        (
+            (grow_crops, dry_out_soil).in_set(AppSystems::TickTimers),
+            (hoe_soil, water_soil, harvest_crops).in_set(AppSystems::PostUpdate),
+        )
+            .run_if(in_state(GameplayState::Running)),
+    );
+}
+
+/// Tint of a tilled tile.
+const TILLED_COLOR: Color = Color::srgb(0.65, 0.5, 0.4);
+/// Tint of tilled soil that is still wet from watering.
+const WATERED_COLOR: Color = Color::srgb(0.42, 0.32, 0.28);
+/// Z position for rendering crops, just below pickups.
+const CROP_Z: f32 = 1.4;
+/// Size of a fully grown crop sprite in world units.
+const CROP_SIZE: f32 = TILE_SCALE * 12.0;
+/// Placeholder color of a crop that is still growing.
+const UNRIPE_CROP_COLOR: Color = Color::srgb(0.4, 0.7, 0.3);
+/// Growth stages after the seedling one.
+const CROP_MAX_STAGE: u8 = 3;
+/// Seconds a crop spends in each stage before growing into the next.
+const CROP_STAGE_SECS: f32 = 10.0;
+/// Pumpkins take longer to grow, but yield more.
+const PUMPKIN_STAGE_SECS: f32 = 15.0;
+/// Crops dropped by a harvested plant.
+const CROP_YIELD: u32 = 2;
+const PUMPKIN_YIELD: u32 = 3;
+
+/// The seed the player plants when hoeing tilled soil. One of [`ItemKind::SEEDS`].
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub struct SelectedSeed(pub ItemKind);
+
+impl Default for SelectedSeed {
+    fn default() -> Self {
+        Self(ItemKind::SEEDS[0])
+    }
+}
+
+/// How watering affects tilled soil.
+#[derive(Resource, Debug, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct WateringConfig {
+    /// Seconds watered soil stays wet.
+    pub dry_out_secs: f32,
+    /// How many times faster a crop grows on wet soil.
+    pub growth_multiplier: f32,
+}
+
+impl Default for WateringConfig {
+    fn default() -> Self {
+        Self {
+            dry_out_secs: 30.0,
+            growth_multiplier: 2.0,
+        }
+    }
+}
+
+/// Tilled soil that was watered. Removed once the timer finishes and the soil is dry again.
+#[derive(Component, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct Watered(pub Timer);
+
+/// A ground tile that was hoed and can have a [`Crop`] planted on it.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+#[reflect(Component)]
+pub struct Tilled;
+
+/// A plant growing on a [`Tilled`] tile.
+#[derive(Component, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct Crop {
+    /// The tilled tile the crop grows on.
+    pub tile: Entity,
+    /// The seed it was grown from.
+    pub seed: ItemKind,
+    pub stage: u8,
+    pub max_stage: u8,
+    /// Time left in the current stage.
+    pub growth: Timer,
+}
+
+impl Crop {
+    pub fn new(tile: Entity, seed: ItemKind) -> Self {
+        let stage_secs = match seed {
+            ItemKind::PumpkinSeed => PUMPKIN_STAGE_SECS,
+            _ => CROP_STAGE_SECS,
+        };
+        Self {
+            tile,
+            seed,
+            stage: 0,
+            max_stage: CROP_MAX_STAGE,
+            growth: Timer::from_seconds(stage_secs, TimerMode::Repeating),
+        }
+    }
+
+    /// Crops dropped when harvesting it.
+    fn crop_yield(&self) -> u32 {
+        match self.seed {
+            ItemKind::PumpkinSeed => PUMPKIN_YIELD,
+            _ => CROP_YIELD,
+        }
+    }
+
+    pub fn is_mature(&self) -> bool {
+        self.stage >= self.max_stage
+    }
+
+    /// Placeholder sprite for the crop's current stage, growing with each one.
+    fn sprite(&self) -> Sprite {
+        let color = if self.is_mature() {
+            ItemKind::Crop.color()
+        } else {
+            UNRIPE_CROP_COLOR
+        };
+        let growth = (self.stage + 1) as f32 / (self.max_stage + 1) as f32;
+        Sprite::from_color(color, Vec2::splat(CROP_SIZE * growth))
+    }
+}
+
+/// A seedling grown from `seed` on `tile`, drawn at `position`.
+pub fn crop(tile: Entity, seed: ItemKind, position: Vec2) -> impl Bundle {
+    grown_crop(Crop::new(tile, seed), position)
+}
+
+/// `crop` as far as it has grown, drawn at `position`.
+fn grown_crop(crop: Crop, position: Vec2) -> impl Bundle {
+    (
+        Name::new("Crop"),
+        crop.sprite(),
+        crop,
+        Transform::from_translation(position.extend(CROP_Z)),
+        VisibleInState(vec![GameState::Gameplay]),
+        DestroyOnEnter(vec![GameState::Title, GameState::GameOver]),
+    )
+}
+
+/// A tilled tile kept while its map isn't spawned, or written to a save.
+#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct Plot {
+    /// Seconds until the soil dries out, `None` if it's dry.
+    pub wet_secs: Option<f32>,
+    /// What's growing on it.
+    pub crop: Option<PlotCrop>,
+}
+
+/// A [`Crop`] kept with its [`Plot`].
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct PlotCrop {
+    pub seed: ItemKind,
+    pub stage: u8,
+    /// Seconds spent in the current stage so far.
+    pub stage_secs: f32,
+}
+
+/// The [`Plot`]s of every map but the spawned one, by map name and tile position, so the
+/// farm is still there when the player comes back to a map or loads a save. The spawned
+/// map's plots are taken out as its tiles come in, and put back when it's left.
+#[derive(Resource, Debug, Default, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct Farmland(pub HashMap<String, HashMap<UVec2, Plot>>);
+
+impl Farmland {
+    /// Keep `plots` as `map`'s, next to any of its plots that haven't been spawned yet.
+    pub fn keep(&mut self, map: &str, plots: HashMap<UVec2, Plot>) {
+        self.0.entry(map.to_string()).or_default().extend(plots);
+    }
+}
+
+/// Read access to the tilled tiles of the spawned map.
+#[derive(SystemParam)]
+pub struct SpawnedPlots<'w, 's> {
+    tile_q: Query<'w, 's, (Entity, &'static TilePos, Option<&'static Watered>), With<Tilled>>,
+    crop_q: Query<'w, 's, &'static Crop>,
+}
+
+impl SpawnedPlots<'_, '_> {
+    /// Every tilled tile of the spawned map, by tile position.
+    pub fn plots(&self) -> HashMap<UVec2, Plot> {
+        let crops: HashMap<Entity, &Crop> =
+            self.crop_q.iter().map(|crop| (crop.tile, crop)).collect();
+        self.tile_q
+            .iter()
+            .map(|(entity, tile_pos, watered)| {
+                let plot = Plot {
+                    wet_secs: watered.map(|watered| watered.0.remaining_secs()),
+                    crop: crops.get(&entity).map(|crop| PlotCrop {
+                        seed: crop.seed,
+                        stage: crop.stage,
+                        stage_secs: crop.growth.elapsed_secs(),
+                    }),
+                };
+                ((*tile_pos).into(), plot)
+            })
+            .collect()
+    }
+}
+
+fn reset_farmland(mut farmland: ResMut<Farmland>) {
+    farmland.0.clear();
+}
+
+/// Keep the plots of the map being left before its tiles are despawned.
+fn stash_plots(
+    mut load_map_events: MessageReader<LoadMapEvent>,
+    current_map: Res<CurrentMap>,
+    plots: SpawnedPlots,
+    mut farmland: ResMut<Farmland>,
+) {
+    if load_map_events.read().last().is_some() {
+        farmland.keep(&current_map.0, plots.plots());
+    }
+}
+
+/// Till, water and replant the freshly spawned tiles of the current map as they were left.
+/// Each plot goes to the first grass or dirt tile at its position.
+pub(super) fn restore_plots(
+    mut commands: Commands,
+    mut farmland: ResMut<Farmland>,
+    current_map: Res<CurrentMap>,
+    mut new_tiles_q: Query<
+        (
+            Entity,
+            &TilePos,
+            &TilemapId,
+            &TileType,
+            &mut TileColor,
+            Option<&mut HighlightedTile>,
+        ),
+        Added<TileType>,
+    >,
+    // Layers are spawned this frame, before their `GlobalTransform` is propagated,
+    // but they have no parent so their `Transform` is already the global one.
+    tilemap_q: Query<(
+        &TilemapSize,
+        &TilemapGridSize,
+        &TilemapTileSize,
+        &TilemapType,
+        &TilemapAnchor,
+        &Transform,
+    )>,
+) {
+    if new_tiles_q.is_empty() {
+        return;
+    }
+    let Some(mut plots) = farmland.0.remove(&current_map.0) else {
+        return;
+    };
+    let mut restored = HashSet::new();
+    for (entity, tile_pos, tilemap_id, tile_type, mut tile_color, highlight) in &mut new_tiles_q {
+        if !matches!(tile_type, TileType::Grass | TileType::Dirt) || restored.contains(tile_pos) {
+            continue;
+        }
+        let Some(plot) = plots.remove(&UVec2::from(*tile_pos)) else {
+            continue;
+        };
+        restored.insert(*tile_pos);
+        commands.entity(entity).insert(Tilled);
+        let color = match plot.wet_secs {
+            Some(secs) => {
+                commands
+                    .entity(entity)
+                    .insert(Watered(Timer::from_seconds(secs.max(0.0), TimerMode::Once)));
+                WATERED_COLOR
+            }
+            None => TILLED_COLOR,
+        };
+        set_tile_color(&mut tile_color, highlight.map(Mut::into_inner), color);
+        let Some(saved) = plot.crop else {
+            continue;
+        };
+        let Ok((map_size, grid_size, tile_size, map_type, anchor, transform)) =
+            tilemap_q.get(tilemap_id.0)
+        else {
+            continue;
+        };
+        let layout = TilemapLayout::new(
+            *map_size,
+            *grid_size,
+            *tile_size,
+            *map_type,
+            *anchor,
+            transform.compute_affine(),
+        );
+        let mut crop = Crop::new(entity, saved.seed);
+        crop.stage = saved.stage.min(crop.max_stage);
+        crop.growth
+            .set_elapsed(Duration::from_secs_f32(saved.stage_secs.max(0.0)));
+        commands.spawn(grown_crop(crop, layout.tile_center(tile_pos)));
+    }
+    if !plots.is_empty() {
+        warn!(
+            "{} plots of map {:?} have no grass or dirt tile left to go to",
+            plots.len(),
+            current_map.0
+        );
+    }
+}
+
+fn grow_crops(
+    time: Res<Time>,
+    watering_config: Res<WateringConfig>,
+    mut crop_q: Query<(&mut Crop, &mut Sprite)>,
+    watered_q: Query<(), With<Watered>>,
+) {
+    for (mut crop, mut sprite) in &mut crop_q {
+        if crop.is_mature() {
+            continue;
+        }
+        let delta = if watered_q.contains(crop.tile) {
+            time.delta().mul_f32(watering_config.growth_multiplier)
+        } else {
+            time.delta()
+        };
+        crop.growth.tick(delta);
+        if crop.growth.is_finished() {
+            crop.stage += 1;
+            *sprite = crop.sprite();
+        }
+    }
+}
+
+/// Till the grass and dirt tiles the player hoed, unless something stands on them, and plant
+/// the [`SelectedSeed`] in tilled soil that has nothing growing yet. Where layers stack
+/// several soil tiles on one spot, only the first is hoed.
+fn hoe_soil(
+    mut commands: Commands,
+    mut tile_interactions: MessageReader<TileInteraction>,
+    mut tile_q: Query<(
+        &TileType,
+        &TilemapId,
+        &mut TileColor,
+        Option<&mut HighlightedTile>,
+        Has<Tilled>,
+    )>,
+    crop_q: Query<&Crop>,
+    tile_pos_q: Query<&TilePos>,
+    tiles: TileQuery,
+    obstacles: Res<ObstacleGrid>,
+    selected_seed: Res<SelectedSeed>,
+    mut inventory: ResMut<Inventory>,
+    player_assets: Res<PlayerAssets>,
+    mut notify_events: MessageWriter<NotifyEvent>,
+) {
+    let mut hoed = HashSet::new();
+    for interaction in tile_interactions.read() {
+        if interaction.action != ActionType::Hoeing || hoed.contains(&interaction.tile_pos) {
+            continue;
+        }
+        let Ok((tile_type, tilemap_id, mut tile_color, highlight, tilled)) =
+            tile_q.get_mut(interaction.tile_entity)
+        else {
+            continue;
+        };
+        if !matches!(tile_type, TileType::Grass | TileType::Dirt) {
+            continue;
+        }
+        let Some(center) = tiles.tile_center(tilemap_id.0, &interaction.tile_pos) else {
+            continue;
+        };
+        if obstacles.is_obstacle(center) {
+            continue;
+        }
+        hoed.insert(interaction.tile_pos);
+
+        if !tilled {
+            set_tile_color(
+                &mut tile_color,
+                highlight.map(Mut::into_inner),
+                TILLED_COLOR,
+            );
+            commands.entity(interaction.tile_entity).insert(Tilled);
+            continue;
+        }
+        if crop_q.iter().any(|crop| {
+            tile_pos_q
+                .get(crop.tile)
+                .is_ok_and(|tile_pos| *tile_pos == interaction.tile_pos)
+        }) {
+            continue;
+        }
+        let seed = selected_seed.0;
+        if !inventory.take(seed, 1) {
+            notify_events.write(NotifyEvent(format!("No {} left to plant", seed.name())));
+            commands.spawn(sound_effect(player_assets.denied.clone()));
+            continue;
+        }
+        commands.spawn(crop(interaction.tile_entity, seed, center));
+    }
+}
+
+/// Wet the tilled soil the player watered. Watering it again while it's still wet starts
+/// the dry-out timer over.
+fn water_soil(
+    mut commands: Commands,
+    mut tile_interactions: MessageReader<TileInteraction>,
+    mut tile_q: Query<(&mut TileColor, Option<&mut HighlightedTile>), With<Tilled>>,
+    watering_config: Res<WateringConfig>,
+) {
+    for interaction in tile_interactions.read() {
+        if interaction.action != ActionType::Watering {
+            continue;
+        }
+        let Ok((mut tile_color, highlight)) = tile_q.get_mut(interaction.tile_entity) else {
+            continue;
+        };
+        water_tile(
+            &mut commands,
+            interaction.tile_entity,
+            &mut tile_color,
+            highlight.map(Mut::into_inner),
+            &watering_config,
+        );
+    }
+}
+
+/// Wet `tile`, tinting it and starting its dry-out timer.
+pub fn water_tile(
+    commands: &mut Commands,
+    tile: Entity,
+    tile_color: &mut TileColor,
+    highlight: Option<&mut HighlightedTile>,
+    watering_config: &WateringConfig,
+) {
+    set_tile_color(tile_color, highlight, WATERED_COLOR);
+    // A negative time, set through reflection, would make the timer panic.
+    commands.entity(tile).insert(Watered(Timer::from_seconds(
+        watering_config.dry_out_secs.max(0.0),
+        TimerMode::Once,
+    )));
+}
+
+fn dry_out_soil(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut tile_q: Query<(
+        Entity,
+        &mut Watered,
+        &mut TileColor,
+        Option<&mut HighlightedTile>,
+    )>,
+) {
+    for (entity, mut watered, mut tile_color, highlight) in &mut tile_q {
+        watered.0.tick(time.delta());
+        if !watered.0.is_finished() {
+            continue;
+        }
+        set_tile_color(
+            &mut tile_color,
+            highlight.map(Mut::into_inner),
+            TILLED_COLOR,
+        );
+        commands.entity(entity).remove::<Watered>();
+    }
+}
+
+/// Harvest the ripe crops the player chopped, dropping their produce. Chopping one that
+/// is still growing does nothing but play the denied sound.
+fn harvest_crops(
+    mut commands: Commands,
+    mut tile_interactions: MessageReader<TileInteraction>,
+    crop_q: Query<(Entity, &Crop, &GlobalTransform)>,
+    player_assets: Res<PlayerAssets>,
+) {
+    for interaction in tile_interactions.read() {
+        if interaction.action != ActionType::Chopping {
+            continue;
+        }
+        let Some((crop_entity, crop, transform)) = crop_q
+            .iter()
+            .find(|(_, crop, _)| crop.tile == interaction.tile_entity)
+        else {
+            continue;
+        };
+        if !crop.is_mature() {
+            debug!("Crop at {:?} isn't ripe yet", interaction.tile_pos);
+            commands.spawn(sound_effect(player_assets.denied.clone()));
+            continue;
+        }
+        commands.entity(crop_entity).despawn();
+        commands.spawn(pickup(
+            ItemKind::Crop,
+            crop.crop_yield(),
+            transform.translation().xy(),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    /// A one-tile grass layer at the origin, as a map layer would spawn it.
+    fn spawn_grass_layer(world: &mut World) -> Entity {
+        let map_size = TilemapSize { x: 1, y: 1 };
+        let tilemap = world
+            .spawn((
+                map_size,
+                TilemapGridSize { x: 16.0, y: 16.0 },
+                TilemapTileSize { x: 16.0, y: 16.0 },
+                TilemapType::Square,
+                TilemapAnchor::Center,
+                TileStorage::empty(map_size),
+                GlobalTransform::default(),
+            ))
+            .id();
+        world
+            .spawn((
+                TilePos { x: 0, y: 0 },
+                TilemapId(tilemap),
+                TileType::Grass,
+                TileColor::default(),
+            ))
+            .id()
+    }
+
+    /// Hoe the origin, reaching `tiles` in that order like the player's action does.
+    fn hoe(world: &mut World, tiles: &[Entity]) {
+        let mut messages = world.resource_mut::<Messages<TileInteraction>>();
+        messages.clear();
+        for &tile_entity in tiles {
+            messages.write(TileInteraction {
+                tile_pos: TilePos { x: 0, y: 0 },
+                tile_entity,
+                action: ActionType::Hoeing,
+            });
+        }
+        world.run_system_once(hoe_soil).expect("Hoeing should run");
+    }
+
+    #[test]
+    fn stacked_grass_layers_are_hoed_as_one_tile() {
+        let mut world = World::new();
+        world.init_resource::<Messages<TileInteraction>>();
+        world.init_resource::<Messages<NotifyEvent>>();
+        world.init_resource::<ObstacleGrid>();
+        world.init_resource::<SelectedSeed>();
+        world.init_resource::<Inventory>();
+        world.insert_resource(PlayerAssets::placeholder());
+        let seed = world.resource::<SelectedSeed>().0;
+        world.resource_mut::<Inventory>().add(seed, 2);
+        let lower = spawn_grass_layer(&mut world);
+        let upper = spawn_grass_layer(&mut world);
+
+        hoe(&mut world, &[lower, upper]);
+        let tilled = world
+            .query_filtered::<(), With<Tilled>>()
+            .iter(&world)
+            .count();
+        assert_eq!(tilled, 1);
+
+        hoe(&mut world, &[lower, upper]);
+        // Reaching the untilled layer first only tills it, as a crop already grows there.
+        hoe(&mut world, &[upper, lower]);
+        hoe(&mut world, &[upper, lower]);
+        assert_eq!(world.query::<&Crop>().iter(&world).count(), 1);
+        assert_eq!(world.resource::<Inventory>().count(seed), 1);
+    }
+}
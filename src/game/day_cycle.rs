@@ -0,0 +1,213 @@
+//! An in-game clock and the day/night tint it drives.
+
+use bevy::{color::Mix, prelude::*, ui::Val::*};
+
+use crate::{
+    AppSystems,
+    game::{level::LevelSpawnSystems, weather::Weather},
+    states::{GameState, GameplayState},
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<GameTime>();
+    app.register_type::<DayCycleSettings>();
+    app.init_resource::<GameTime>();
+    app.init_resource::<DayCycleSettings>();
+    app.add_systems(
+        OnEnter(GameState::Gameplay),
+        (
+            spawn_day_night_overlay,
+            reset_game_time.in_set(LevelSpawnSystems),
+        ),
+    );
+    app.add_systems(
+        Update,
+        (
+            advance_game_time.in_set(AppSystems::TickTimers),
+            apply_day_night_tint.in_set(AppSystems::PostUpdate),
+        )
+            .run_if(in_state(GameplayState::Running)),
+    );
+}
+
+/// In-game seconds in a full day.
+pub const SECONDS_PER_DAY: f32 = 24.0 * 60.0 * 60.0;
+/// Time of day a new game starts at (8:00).
+const START_OF_DAY_SECS: f32 = 8.0 * 60.0 * 60.0;
+/// Shortest day the clock runs at, whatever [`DayCycleSettings`] asks for.
+const MIN_DAY_LENGTH_SECS: f32 = 1.0;
+
+/// The in-game clock.
+#[derive(Resource, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Resource)]
+pub struct GameTime {
+    /// In-game seconds since midnight, between 0 and [`SECONDS_PER_DAY`].
+    pub seconds_of_day: f32,
+    /// Number of days elapsed since the game started.
+    pub day: u32,
+}
+
+impl Default for GameTime {
+    fn default() -> Self {
+        Self {
+            seconds_of_day: START_OF_DAY_SECS,
+            day: 0,
+        }
+    }
+}
+
+impl GameTime {
+    /// How far through the current day we are, between 0 (midnight) and 1.
+    pub fn fraction_of_day(&self) -> f32 {
+        self.seconds_of_day / SECONDS_PER_DAY
+    }
+}
+
+#[derive(Resource, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Resource)]
+pub struct DayCycleSettings {
+    /// Real-time seconds a full in-game day lasts. Lengths below [`MIN_DAY_LENGTH_SECS`],
+    /// which the inspector will happily set, are treated as that.
+    pub day_length_secs: f32,
+}
+
+impl Default for DayCycleSettings {
+    fn default() -> Self {
+        Self {
+            day_length_secs: 10.0 * 60.0,
+        }
+    }
+}
+
+/// A fresh level starts on the first morning.
+fn reset_game_time(mut game_time: ResMut<GameTime>) {
+    *game_time = GameTime::default();
+}
+
+fn advance_game_time(
+    time: Res<Time>,
+    settings: Res<DayCycleSettings>,
+    mut game_time: ResMut<GameTime>,
+) {
+    // `max` also turns a NaN length into the minimum.
+    let day_length = settings.day_length_secs.max(MIN_DAY_LENGTH_SECS);
+    let seconds = game_time.seconds_of_day + time.delta_secs() * SECONDS_PER_DAY / day_length;
+    game_time.day += (seconds / SECONDS_PER_DAY) as u32;
+    game_time.seconds_of_day = seconds % SECONDS_PER_DAY;
+}
+
+/// Full-screen node that tints the scene according to the time of day.
+#[derive(Component)]
+struct DayNightOverlay;
+
+fn spawn_day_night_overlay(mut commands: Commands, game_time: Res<GameTime>) {
+    commands.spawn((
+        Name::new("Day/Night Overlay"),
+        DayNightOverlay,
+        Node {
+            position_type: PositionType::Absolute,
+            width: Percent(100.0),
+            height: Percent(100.0),
+            ..default()
+        },
+        BackgroundColor(day_night_tint(game_time.fraction_of_day())),
+        // Stay below the HUD and menus, and don't swallow clicks.
+        GlobalZIndex(-1),
+        Pickable::IGNORE,
+        DespawnOnExit(GameState::Gameplay),
+    ));
+}
+
+const NIGHT_TINT: Color = Color::srgba(0.02, 0.03, 0.15, 0.6);
+const TWILIGHT_TINT: Color = Color::srgba(0.85, 0.45, 0.25, 0.25);
+const DAY_TINT: Color = Color::srgba(1.0, 1.0, 1.0, 0.0);
+/// Grey laid over the time-of-day tint while it rains.
+const RAIN_TINT: Color = Color::srgba(0.15, 0.18, 0.25, 0.25);
+
+/// Tint keyframes as (fraction of day, tint), sorted by time and spanning the whole day.
+const TINT_KEYFRAMES: [(f32, Color); 8] = [
+    (0.0, NIGHT_TINT),
+    (0.2, NIGHT_TINT),
+    (0.27, TWILIGHT_TINT),
+    (0.33, DAY_TINT),
+    (0.71, DAY_TINT),
+    (0.79, TWILIGHT_TINT),
+    (0.875, NIGHT_TINT),
+    (1.0, NIGHT_TINT),
+];
+
+/// Smoothly interpolate the overlay color for a point in the day.
+fn day_night_tint(fraction_of_day: f32) -> Color {
+    let fraction = fraction_of_day.clamp(0.0, 1.0);
+    for window in TINT_KEYFRAMES.windows(2) {
+        let [(start, from), (end, to)] = [window[0], window[1]];
+        if fraction <= end {
+            let t = ((fraction - start) / (end - start)).clamp(0.0, 1.0);
+            let from = LinearRgba::from(from);
+            let to = LinearRgba::from(to);
+            return from.mix(&to, t).into();
+        }
+    }
+    NIGHT_TINT
+}
+
+/// Lay the [`RAIN_TINT`] over `tint`, as if it were a second overlay on top.
+fn rain_tint(tint: Color) -> Color {
+    let below = LinearRgba::from(tint);
+    let above = LinearRgba::from(RAIN_TINT);
+    let alpha = above.alpha + below.alpha * (1.0 - above.alpha);
+    let color = below.mix(&above, above.alpha / alpha);
+    color.with_alpha(alpha).into()
+}
+
+fn apply_day_night_tint(
+    game_time: Res<GameTime>,
+    weather: Res<Weather>,
+    mut overlay_query: Query<&mut BackgroundColor, With<DayNightOverlay>>,
+) {
+    let mut tint = day_night_tint(game_time.fraction_of_day());
+    if *weather == Weather::Rain {
+        tint = rain_tint(tint);
+    }
+    for mut background in &mut overlay_query {
+        background.0 = tint;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    /// The clock after `secs` of real time with days lasting `day_length_secs`.
+    fn advance(day_length_secs: f32, secs: f32) -> GameTime {
+        let mut world = World::new();
+        let mut time = Time::<()>::default();
+        time.advance_by(Duration::from_secs_f32(secs));
+        world.insert_resource(time);
+        world.insert_resource(DayCycleSettings { day_length_secs });
+        world.init_resource::<GameTime>();
+        world.run_system_once(advance_game_time).unwrap();
+        world.resource::<GameTime>().clone()
+    }
+
+    #[test]
+    fn days_roll_over() {
+        // Two and a half days pass, starting from 8:00.
+        let game_time = advance(10.0, 25.0);
+        assert_eq!(game_time.day, 2);
+        let expected = START_OF_DAY_SECS + SECONDS_PER_DAY / 2.0;
+        assert!((game_time.seconds_of_day - expected).abs() < 1.0);
+    }
+
+    #[test]
+    fn degenerate_day_lengths_run_at_the_minimum() {
+        let expected = advance(MIN_DAY_LENGTH_SECS, 2.5);
+        for day_length_secs in [0.0, -10.0, f32::NAN] {
+            assert_eq!(advance(day_length_secs, 2.5), expected, "{day_length_secs}");
+        }
+    }
+}
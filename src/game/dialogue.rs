@@ -0,0 +1,181 @@
+//! Conversations shown one page at a time in a panel at the bottom of the screen.
+//!
+//! While a conversation is open the player can't move or use tools, but the world keeps
+//! running around them.
+
+use bevy::{prelude::*, ui::Val::*};
+
+use crate::{
+    AppSystems,
+    game::{movement::MovementController, player::Player},
+    states::{DestroyOnEnter, GameState, GameplayState, VisibleInState},
+    theme::prelude::*,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<DialogueState>();
+    app.register_type::<DialogueBox>();
+    app.init_resource::<DialogueState>();
+    app.add_systems(OnEnter(GameState::Title), close_dialogue);
+    app.add_systems(OnEnter(GameState::GameOver), close_dialogue);
+    app.add_systems(
+        Update,
+        (
+            (advance_dialogue, stop_player)
+                .run_if(dialogue_open)
+                .in_set(AppSystems::RecordInput),
+            sync_dialogue_box.in_set(AppSystems::PostUpdate),
+        )
+            .run_if(in_state(GameplayState::Running)),
+    );
+}
+
+/// Turns to the next page, closing the dialogue after the last one.
+const ADVANCE_KEYS: [KeyCode; 3] = [KeyCode::Space, KeyCode::Enter, KeyCode::NumpadEnter];
+const DIALOGUE_BACKGROUND: Color = Color::srgba(0.05, 0.05, 0.1, 0.85);
+
+/// The conversation being shown, if any.
+#[derive(Resource, Debug, Clone, Default, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub struct DialogueState {
+    pub speaker: String,
+    /// Every page of the conversation. Empty while no conversation is open.
+    pub pages: Vec<String>,
+    /// The page being shown.
+    pub page: usize,
+}
+
+impl DialogueState {
+    /// Start a conversation with `speaker` from its first page.
+    pub fn start(&mut self, speaker: impl Into<String>, pages: Vec<String>) {
+        self.speaker = speaker.into();
+        self.pages = pages;
+        self.page = 0;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.page < self.pages.len()
+    }
+
+    fn current_page(&self) -> Option<&str> {
+        self.pages.get(self.page).map(String::as_str)
+    }
+
+    fn is_last_page(&self) -> bool {
+        self.page + 1 >= self.pages.len()
+    }
+
+    /// Turn to the next page, or close the conversation after the last one.
+    fn advance(&mut self) {
+        if self.is_last_page() {
+            *self = Self::default();
+        } else {
+            self.page += 1;
+        }
+    }
+}
+
+/// Run condition that's true while a conversation is open.
+pub fn dialogue_open(dialogue: Res<DialogueState>) -> bool {
+    dialogue.is_open()
+}
+
+/// Placeholder conversations until dialogue is loaded from data.
+pub fn dialogue_pages(dialogue_id: &str) -> Vec<String> {
+    let pages: &[&str] = match dialogue_id {
+        "farmer_greeting" => &[
+            "Fine weather for planting, isn't it?",
+            "Till the soil with your hoe, then hoe it again to plant a seed.",
+            "Water it now and then and it'll grow twice as fast.",
+        ],
+        _ => &["..."],
+    };
+    pages.iter().map(|page| page.to_string()).collect()
+}
+
+/// The panel showing the current page of the conversation.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+#[reflect(Component)]
+pub struct DialogueBox;
+
+fn dialogue_box(speaker: &str, page: &str, is_last_page: bool) -> impl Bundle {
+    let hint = if is_last_page {
+        "Space: close"
+    } else {
+        "Space: next"
+    };
+    (
+        Name::new("Dialogue Box"),
+        DialogueBox,
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Px(20.0),
+            left: Percent(10.0),
+            width: Percent(80.0),
+            padding: UiRect::all(Px(16.0)),
+            flex_direction: FlexDirection::Column,
+            row_gap: Px(8.0),
+            ..default()
+        },
+        BackgroundColor(DIALOGUE_BACKGROUND),
+        BorderRadius::all(Px(8.0)),
+        Pickable::IGNORE,
+        VisibleInState(vec![GameState::Gameplay]),
+        DestroyOnEnter(vec![GameState::Title, GameState::GameOver]),
+        children![
+            widget::header(speaker),
+            widget::label(page),
+            (
+                widget::label(hint),
+                Node {
+                    align_self: AlignSelf::End,
+                    ..default()
+                },
+            ),
+        ],
+    )
+}
+
+fn close_dialogue(mut dialogue: ResMut<DialogueState>) {
+    *dialogue = DialogueState::default();
+}
+
+pub(super) fn advance_dialogue(
+    mut input: ResMut<ButtonInput<KeyCode>>,
+    mut dialogue: ResMut<DialogueState>,
+) {
+    let Some(&key) = ADVANCE_KEYS.iter().find(|&&key| input.just_pressed(key)) else {
+        return;
+    };
+    // Don't let the same press start another conversation right after this one closes.
+    input.clear_just_pressed(key);
+    dialogue.advance();
+}
+
+/// Keep the player standing still while they're reading.
+fn stop_player(mut controller_q: Query<&mut MovementController, With<Player>>) {
+    for mut controller in &mut controller_q {
+        controller.intent = Vec2::ZERO;
+    }
+}
+
+/// Show the current page of the conversation, or nothing once it's closed.
+fn sync_dialogue_box(
+    mut commands: Commands,
+    dialogue: Res<DialogueState>,
+    dialogue_box_q: Query<Entity, With<DialogueBox>>,
+) {
+    if !dialogue.is_changed() {
+        return;
+    }
+    for entity in &dialogue_box_q {
+        commands.entity(entity).despawn();
+    }
+    if let Some(page) = dialogue.current_page() {
+        commands.spawn(dialogue_box(
+            &dialogue.speaker,
+            page,
+            dialogue.is_last_page(),
+        ));
+    }
+}
@@ -0,0 +1,193 @@
+//! Short-lived visual effects, like the burst of particles when an action lands on a tile.
+
+use bevy::{camera::primitives::Aabb, platform::collections::HashSet, prelude::*};
+use bevy_ecs_tilemap::prelude::*;
+use rand::Rng;
+
+use crate::{
+    AppSystems,
+    constants::TILE_SCALE,
+    game::{
+        animation::{ActionType, Footstep, TileInteraction},
+        collision::world_rect,
+        rng::GameRng,
+    },
+    states::{DestroyOnEnter, GameState, GameplayState, VisibleInState},
+    world::{tiledhelper::TileType, tilemap::TileQuery},
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<Lifetime>();
+    app.register_type::<Particle>();
+    app.add_systems(
+        Update,
+        (
+            tick_lifetimes.in_set(AppSystems::TickTimers),
+            (spawn_action_bursts, spawn_footstep_dust).in_set(AppSystems::PostUpdate),
+            move_particles.in_set(AppSystems::Update),
+        )
+            .run_if(in_state(GameplayState::Running)),
+    );
+}
+
+/// Z position for rendering particles, above the player.
+const PARTICLE_Z: f32 = 3.0;
+/// Size of a particle sprite in world units.
+const PARTICLE_SIZE: f32 = TILE_SCALE * 2.0;
+const PARTICLE_LIFETIME_SECS: f32 = 0.4;
+/// Downward acceleration pulling particles back towards the ground, in world units per second squared.
+const PARTICLE_GRAVITY: f32 = TILE_SCALE * 200.0;
+
+/// Despawns the entity once the timer finishes, fading its sprite out along the way.
+#[derive(Component, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct Lifetime(pub Timer);
+
+/// A particle flying away from where it was spawned.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct Particle {
+    pub velocity: Vec2,
+}
+
+/// A handful of particles flying out from a point, like dirt clods for hoeing, droplets
+/// for watering and wood chips for chopping.
+pub struct Burst {
+    pub color: Color,
+    pub count: usize,
+    /// Fastest a particle flies off, in world units per second.
+    pub speed: f32,
+}
+
+impl Burst {
+    fn for_action(action: ActionType) -> Self {
+        match action {
+            ActionType::Hoeing => Self {
+                color: Color::srgb(0.45, 0.3, 0.18),
+                count: 6,
+                speed: TILE_SCALE * 53.0,
+            },
+            ActionType::Watering => Self {
+                color: Color::srgb(0.4, 0.65, 0.95),
+                count: 8,
+                speed: TILE_SCALE * 40.0,
+            },
+            ActionType::Chopping => Self {
+                color: Color::srgb(0.76, 0.6, 0.36),
+                count: 5,
+                speed: TILE_SCALE * 67.0,
+            },
+        }
+    }
+
+    /// Send the particles flying out from `center` in random directions.
+    pub fn spawn(&self, commands: &mut Commands, rng: &mut GameRng, center: Vec2) {
+        for _ in 0..self.count {
+            let angle = rng.0.random_range(0.0..std::f32::consts::TAU);
+            let speed = self.speed * rng.0.random_range(0.5..1.0);
+            commands.spawn(particle(
+                self.color,
+                center,
+                Vec2::from_angle(angle) * speed,
+            ));
+        }
+    }
+}
+
+fn particle(color: Color, position: Vec2, velocity: Vec2) -> impl Bundle {
+    (
+        Name::new("Particle"),
+        Particle { velocity },
+        Lifetime(Timer::from_seconds(PARTICLE_LIFETIME_SECS, TimerMode::Once)),
+        Sprite::from_color(color, Vec2::splat(PARTICLE_SIZE)),
+        Transform::from_translation(position.extend(PARTICLE_Z)),
+        VisibleInState(vec![GameState::Gameplay]),
+        DestroyOnEnter(vec![GameState::Title, GameState::GameOver]),
+    )
+}
+
+/// Pop a burst of particles over every tile an action landed on.
+fn spawn_action_bursts(
+    mut commands: Commands,
+    mut tile_interactions: MessageReader<TileInteraction>,
+    tilemap_id_q: Query<&TilemapId>,
+    tiles: TileQuery,
+    mut rng: ResMut<GameRng>,
+) {
+    // An action reports every layer's tile at its target, but should only pop once.
+    let mut popped = HashSet::new();
+    for interaction in tile_interactions.read() {
+        if !popped.insert(interaction.tile_pos) {
+            continue;
+        }
+        let Some(center) = tilemap_id_q
+            .get(interaction.tile_entity)
+            .ok()
+            .and_then(|tilemap_id| tiles.tile_center(tilemap_id.0, &interaction.tile_pos))
+        else {
+            continue;
+        };
+        Burst::for_action(interaction.action).spawn(&mut commands, &mut rng, center);
+    }
+}
+
+/// The puff of dust a footstep kicks up from the ground, if it kicks up any at all.
+fn footstep_dust(tile_type: Option<&TileType>) -> Option<Burst> {
+    let color = match tile_type? {
+        TileType::Grass => Color::srgb(0.55, 0.6, 0.35),
+        TileType::Dirt => Color::srgb(0.5, 0.38, 0.25),
+        TileType::Sand => Color::srgb(0.87, 0.78, 0.55),
+        TileType::Rock => Color::srgb(0.6, 0.6, 0.6),
+        TileType::Water => return None,
+    };
+    Some(Burst {
+        color,
+        count: 3,
+        speed: TILE_SCALE * 27.0,
+    })
+}
+
+/// Kick up a little dust at the walker's feet on every step.
+fn spawn_footstep_dust(
+    mut commands: Commands,
+    mut footsteps: MessageReader<Footstep>,
+    walker_q: Query<(&GlobalTransform, &Aabb)>,
+    mut rng: ResMut<GameRng>,
+) {
+    for footstep in footsteps.read() {
+        let Some(dust) = footstep_dust(footstep.tile_type.as_ref()) else {
+            continue;
+        };
+        let Ok((transform, aabb)) = walker_q.get(footstep.entity) else {
+            continue;
+        };
+        let rect = world_rect(transform, aabb);
+        let feet = Vec2::new(rect.center().x, rect.min.y);
+        dust.spawn(&mut commands, &mut rng, feet);
+    }
+}
+
+fn move_particles(time: Res<Time>, mut particle_q: Query<(&mut Particle, &mut Transform)>) {
+    let dt = time.delta_secs();
+    for (mut particle, mut transform) in &mut particle_q {
+        particle.velocity.y -= PARTICLE_GRAVITY * dt;
+        transform.translation += (particle.velocity * dt).extend(0.0);
+    }
+}
+
+fn tick_lifetimes(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut lifetime_q: Query<(Entity, &mut Lifetime, Option<&mut Sprite>)>,
+) {
+    for (entity, mut lifetime, sprite) in &mut lifetime_q {
+        lifetime.0.tick(time.delta());
+        if lifetime.0.is_finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        if let Some(mut sprite) = sprite {
+            sprite.color.set_alpha(1.0 - lifetime.0.fraction());
+        }
+    }
+}
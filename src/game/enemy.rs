@@ -1,21 +1,75 @@
 use crate::asset_tracking::LoadResource;
 use crate::constants::*;
-use crate::states::VisibleInState;
+use crate::states::{DestroyOnEnter, GameplayState, VisibleInState};
 use crate::{
     AppSystems,
-    game::{camera::WithinBounds, movement::MovementController},
+    audio::spatial_sound_effect,
+    game::{
+        camera::WithinBounds,
+        combat::Health,
+        inventory::ItemKind,
+        level::LevelSpawnSystems,
+        minimap::MinimapCamera,
+        movement::{
+            FixedMovement, MovementController, ObstacleGrid, Waypoints, character_footprint,
+            rebuild_obstacle_grid,
+        },
+        player::{Player, PlayerAssets},
+        rng::GameRng,
+        y_sort::YSort,
+    },
     states::GameState,
+    world::tiledhelper::TileType,
 };
 use bevy::image::{ImageLoaderSettings, ImageSampler};
 use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
 pub(super) fn plugin(app: &mut App) {
     app.register_type::<EnemyAssets>();
+    app.register_type::<RoamTimer>();
+    app.register_type::<EnemyKind>();
+    app.register_type::<Culled>();
+    app.register_type::<Chase>();
+    app.register_type::<EnemySpawnConfig>();
+    app.init_resource::<EnemySpawnConfig>();
     app.load_resource::<EnemyAssets>();
-    app.add_systems(OnEnter(GameState::Gameplay), spawn_enemies);
+    app.add_systems(
+        OnEnter(GameState::Gameplay),
+        queue_enemy_spawn.in_set(LevelSpawnSystems),
+    );
+    app.add_systems(
+        Update,
+        spawn_enemies
+            .run_if(resource_exists::<PendingEnemySpawn>)
+            .in_set(AppSystems::PreUpdate)
+            .after(rebuild_obstacle_grid),
+    );
+    app.add_systems(
+        Update,
+        (tick_roam_timers, tick_chase_timers)
+            .run_if(in_state(GameplayState::Running))
+            .in_set(AppSystems::TickTimers),
+    );
+    app.add_systems(
+        Update,
+        cull_distant_enemies
+            .run_if(in_state(GameplayState::Running))
+            .in_set(AppSystems::PreUpdate),
+    );
     app.add_systems(
         Update,
-        (apply_roaming,)
-            .run_if(in_state(GameState::Gameplay))
+        (apply_roaming, apply_chase)
+            .chain()
+            .run_if(in_state(GameplayState::Running))
+            .in_set(AppSystems::RecordInput),
+    );
+    app.add_systems(
+        Update,
+        play_enemy_steps
+            .run_if(in_state(GameplayState::Running))
             .in_set(AppSystems::Update),
     );
 }
@@ -24,82 +78,450 @@ pub(super) fn plugin(app: &mut App) {
 #[reflect(Component)]
 pub(crate) struct Enemy;
 
+/// How many enemies a fresh level starts with, and where they're scattered.
+/// Tune it to make the game harder or easier.
+#[derive(Resource, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Resource)]
+pub struct EnemySpawnConfig {
+    pub count: usize,
+    /// World-space area spawn positions are picked from.
+    pub region: Rect,
+}
+
+impl Default for EnemySpawnConfig {
+    fn default() -> Self {
+        Self {
+            count: 5,
+            region: Rect::from_center_half_size(
+                Vec2::ZERO,
+                Vec2::new(21.0, 12.5) * TILE_WORLD_SIZE,
+            ),
+        }
+    }
+}
+
+/// How many random spots to try for an enemy before leaving it out.
+const MAX_SPAWN_ATTEMPTS: usize = 20;
+
+/// The enemies of a fresh level or a newly entered map still have to be spawned, once
+/// the map is in and the [`ObstacleGrid`] knows where they can't go.
+#[derive(Resource, Debug, Default)]
+pub struct PendingEnemySpawn;
+
+/// Marks an enemy too far outside the camera's view to be worth updating.
+/// Culled enemies hold still until the camera comes close again.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+#[reflect(Component)]
+pub struct Culled;
+
+/// How far outside the visible area enemies keep being updated, so they don't
+/// visibly freeze right at the screen edge.
+const CULL_MARGIN: f32 = TILE_WORLD_SIZE * 5.0;
+
+/// Repeating timer that decides when an enemy picks a new roaming direction.
+/// Each enemy has its own so they don't all turn on the same frame.
+#[derive(Component, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct RoamTimer(pub Timer);
+
+impl RoamTimer {
+    pub fn from_seconds(duration: f32) -> Self {
+        Self(Timer::from_seconds(duration, TimerMode::Repeating))
+    }
+}
+
+/// How close the player has to come before an enemy gives up roaming and chases them.
+const CHASE_RANGE: f32 = TILE_WORLD_SIZE * 8.0;
+/// Least time between two route searches of one enemy.
+const CHASE_REPATH_SECS: f32 = 0.5;
+/// How many tiles the player can move away from the end of an enemy's route before
+/// the enemy looks for a new one.
+const CHASE_REPATH_TILES: u32 = 2;
+
+/// An enemy's route to the player, around the obstacles in between.
+/// Only followed while the player is within [`CHASE_RANGE`].
+#[derive(Component, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct Chase {
+    /// Throttles how often the route is searched for again.
+    pub repath_timer: Timer,
+    /// The player's tile when the route was searched for, `None` while not chasing.
+    pub goal: Option<TilePos>,
+    /// Positions left to walk through, next one first (see [`ObstacleGrid::route`]).
+    /// `None` if the player can't be reached, so the enemy keeps roaming.
+    pub path: Option<Waypoints>,
+}
+
+impl Default for Chase {
+    fn default() -> Self {
+        Self {
+            repath_timer: Timer::from_seconds(CHASE_REPATH_SECS, TimerMode::Repeating),
+            goal: None,
+            path: None,
+        }
+    }
+}
+
+/// The different kinds of enemies roaming the farm.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
+pub enum EnemyKind {
+    /// Plods around and rarely changes its mind.
+    Slow,
+    /// Darts around, turning often.
+    Fast,
+    /// Average speed, but tinted so it stands out from the others.
+    Wanderer,
+}
+
+impl EnemyKind {
+    pub const ALL: [EnemyKind; 3] = [EnemyKind::Slow, EnemyKind::Fast, EnemyKind::Wanderer];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EnemyKind::Slow => "Slow",
+            EnemyKind::Fast => "Fast",
+            EnemyKind::Wanderer => "Wanderer",
+        }
+    }
+
+    /// Movement speed as a fraction of [`PLAYER_MAX_SPEED`].
+    fn speed_multiplier(self) -> f32 {
+        match self {
+            EnemyKind::Slow => 1.0 / 12.0,
+            EnemyKind::Fast => 1.0 / 5.0,
+            EnemyKind::Wanderer => 1.0 / 8.0,
+        }
+    }
+
+    /// Row of the enemy sheet to draw this kind from.
+    fn atlas_row(self) -> usize {
+        match self {
+            EnemyKind::Slow | EnemyKind::Wanderer => 0,
+            EnemyKind::Fast => 1,
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            EnemyKind::Slow | EnemyKind::Fast => Color::WHITE,
+            EnemyKind::Wanderer => Color::srgb(0.6, 1.0, 0.6),
+        }
+    }
+
+    /// Chops it takes to defeat this kind.
+    pub fn max_health(self) -> f32 {
+        match self {
+            EnemyKind::Slow => 3.0,
+            EnemyKind::Fast => 1.0,
+            EnemyKind::Wanderer => 2.0,
+        }
+    }
+
+    /// What this kind drops when defeated, if anything.
+    pub fn loot(self) -> Option<(ItemKind, u32)> {
+        match self {
+            EnemyKind::Slow => Some((ItemKind::Crop, 2)),
+            EnemyKind::Fast => Some((ItemKind::PumpkinSeed, 1)),
+            EnemyKind::Wanderer => None,
+        }
+    }
+
+    /// Range in seconds to pick this kind's roam interval from.
+    fn roam_interval_secs(self) -> RangeInclusive<f32> {
+        match self {
+            EnemyKind::Slow => 2.5..=3.5,
+            EnemyKind::Fast => 0.8..=1.5,
+            EnemyKind::Wanderer => 1.5..=2.5,
+        }
+    }
+}
+
+/// Columns in the enemy sheet.
+const ENEMY_ATLAS_COLUMNS: u32 = 3;
+
 pub fn enemy(
-    player_assets: &EnemyAssets,
-    texture_atlas_layouts: &mut Assets<TextureAtlasLayout>,
+    enemy_assets: &EnemyAssets,
     pos: &Vec2,
+    kind: EnemyKind,
+    roam_timer: RoamTimer,
 ) -> impl Bundle {
-    // A texture atlas is a way to split a single image into a grid of related images.
-    // You can learn more in this example: https://github.com/bevyengine/bevy/blob/latest/examples/2d/texture_atlas.rs
-    let layout =
-        TextureAtlasLayout::from_grid(UVec2::new(GRID_SIZE_X, GRID_SIZE_Y), 3, 2, None, None);
-    let texture_atlas_layout = texture_atlas_layouts.add(layout);
-
     (
         Enemy,
+        kind,
         Sprite {
-            image: player_assets.enemies.clone(),
+            image: enemy_assets.enemies.clone(),
             texture_atlas: Some(TextureAtlas {
-                layout: texture_atlas_layout,
-                index: 0,
+                layout: enemy_assets.layout.clone(),
+                index: kind.atlas_row() * ENEMY_ATLAS_COLUMNS as usize,
             }),
+            color: kind.color(),
             ..default()
         },
         Transform::from_translation(pos.extend(PLAYER_Z))
             .with_scale(Vec2::splat(PLAYER_SCALE).extend(1.0)),
         MovementController {
-            max_speed: PLAYER_MAX_SPEED / 8.0,
+            max_speed: PLAYER_MAX_SPEED * kind.speed_multiplier(),
             ..default()
         },
+        Health::new(kind.max_health()),
         WithinBounds,
+        roam_timer,
+        Chase::default(),
+        YSort,
+        VisibleInState(vec![GameState::Gameplay]),
+        DestroyOnEnter(vec![GameState::Title, GameState::GameOver]),
     )
 }
 
+/// A roam timer with a random interval from `kind`'s range.
+pub fn random_roam_timer(kind: EnemyKind, rng: &mut GameRng) -> RoamTimer {
+    RoamTimer::from_seconds(rng.0.random_range(kind.roam_interval_secs()))
+}
+
+/// A random spot in `region` where an enemy isn't stuck in an obstacle, if one turns up
+/// within [`MAX_SPAWN_ATTEMPTS`] tries.
+fn random_spawn_position(region: Rect, grid: &ObstacleGrid, rng: &mut GameRng) -> Option<Vec2> {
+    if region.is_empty() {
+        return None;
+    }
+    // Enemies are drawn at the same scale as the player.
+    let footprint = character_footprint(Vec2::splat(PLAYER_SCALE));
+    (0..MAX_SPAWN_ATTEMPTS)
+        .map(|_| {
+            Vec2::new(
+                rng.0.random_range(region.min.x..=region.max.x),
+                rng.0.random_range(region.min.y..=region.max.y),
+            )
+        })
+        .find(|&position| !grid.is_move_blocked(position, footprint, Vec2::ZERO))
+}
+
+fn queue_enemy_spawn(mut commands: Commands) {
+    commands.init_resource::<PendingEnemySpawn>();
+}
+
 fn spawn_enemies(
     mut commands: Commands,
+    mut rng: ResMut<GameRng>,
     enemy_assets: Res<EnemyAssets>,
-    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    config: Res<EnemySpawnConfig>,
+    grid: Res<ObstacleGrid>,
+    tile_q: Query<(), With<TileType>>,
 ) {
-    // Spawn a few enemies at random positions.
-    for i in 0..5 {
-        let position = Vec2::new(100.0 * (i as f32 + 1.0), 100.0);
+    // The old map is gone by now, so any tile means the new one is in and the grid is built.
+    if tile_q.is_empty() {
+        return;
+    }
+    commands.remove_resource::<PendingEnemySpawn>();
+    // Smaller maps than the farm only get the part of the region they cover.
+    let region = grid.layout().map_or(config.region, |layout| {
+        config.region.intersect(layout.world_rect())
+    });
+    for i in 0..config.count {
+        let Some(position) = random_spawn_position(region, &grid, &mut rng) else {
+            warn!("No free spot found for enemy {i} in {region:?}");
+            continue;
+        };
+        let kind = *EnemyKind::ALL
+            .choose(&mut rng.0)
+            .expect("There should be enemy kinds to pick from");
+        let roam_timer = random_roam_timer(kind, &mut rng);
         commands.spawn((
-            Name::new(format!("Enemy {i}")),
-            enemy(&enemy_assets, &mut texture_atlas_layouts, &position),
-            VisibleInState(vec![GameState::Gameplay]),
+            Name::new(format!("{} Enemy {i}", kind.name())),
+            enemy(&enemy_assets, &position, kind, roam_timer),
         ));
     }
 }
 
+fn tick_roam_timers(time: Res<Time>, mut roam_query: Query<&mut RoamTimer>) {
+    for mut roam_timer in &mut roam_query {
+        roam_timer.0.tick(time.delta());
+    }
+}
+
+fn tick_chase_timers(time: Res<Time>, mut chase_query: Query<&mut Chase>) {
+    for mut chase in &mut chase_query {
+        chase.repath_timer.tick(time.delta());
+    }
+}
+
+/// Mark the enemies outside the camera's view (plus [`CULL_MARGIN`]) as [`Culled`].
+fn cull_distant_enemies(
+    mut commands: Commands,
+    camera: Single<(&GlobalTransform, &Projection), (With<Camera2d>, Without<MinimapCamera>)>,
+    enemy_query: Query<(Entity, &GlobalTransform, Has<Culled>), With<Enemy>>,
+) {
+    let (camera_transform, projection) = *camera;
+    let Projection::Orthographic(ortho) = projection else {
+        return;
+    };
+    let camera_pos = camera_transform.translation().xy();
+    let view = Rect::from_corners(ortho.area.min + camera_pos, ortho.area.max + camera_pos)
+        .inflate(CULL_MARGIN);
+    for (entity, transform, is_culled) in &enemy_query {
+        let should_cull = !view.contains(transform.translation().xy());
+        if should_cull && !is_culled {
+            commands.entity(entity).insert(Culled);
+        } else if !should_cull && is_culled {
+            commands.entity(entity).remove::<Culled>();
+        }
+    }
+}
+
+/// Pick the roaming direction for everything with a [`RoamTimer`], i.e. enemies and NPCs.
+/// The actual displacement is applied by the shared movement system, so they respect
+/// obstacles just like the player.
 fn apply_roaming(
-    time: Res<Time>,
-    mut movement_query: Query<(&mut MovementController, &mut Transform), With<Enemy>>,
+    fixed_time: Res<Time<Fixed>>,
+    mut rng: ResMut<GameRng>,
+    mut movement_query: Query<
+        (
+            &mut MovementController,
+            &RoamTimer,
+            &FixedMovement,
+            &Transform,
+        ),
+        Without<Culled>,
+    >,
+    obstacles: Res<ObstacleGrid>,
 ) {
-    for (mut controller, mut transform) in &mut movement_query {
-        // Randomly change direction every 2 seconds.
-        if time.elapsed_secs() % 2.0 < 0.1 {
-            controller.intent = Vec2::new(
-                rand::random::<f32>() * 2.0 - 1.0,
-                rand::random::<f32>() * 2.0 - 1.0,
-            )
-            .normalize_or_zero();
+    for (mut controller, roam_timer, movement, transform) in &mut movement_query {
+        // Pick a new random direction whenever its roam timer elapses,
+        // or right away if it was just spawned standing still.
+        if roam_timer.0.just_finished() || controller.intent == Vec2::ZERO {
+            controller.intent = random_intent(&mut rng);
+        }
+        // Turn away immediately instead of stalling against an obstacle,
+        // looking one simulation step ahead.
+        let delta_movement = controller.target_velocity() * fixed_time.timestep().as_secs_f32();
+        let footprint = character_footprint(transform.scale.xy());
+        if obstacles.is_move_blocked(movement.position(), footprint, delta_movement) {
+            controller.intent = random_intent(&mut rng);
+        }
+    }
+}
+
+/// Steer the enemies near the player along a route to them, overriding their roaming.
+/// The route is searched for as soon as the player comes within [`CHASE_RANGE`], then
+/// again at most every [`CHASE_REPATH_SECS`] once the player has moved more than
+/// [`CHASE_REPATH_TILES`] from its end. At the end of the route they head straight in.
+fn apply_chase(
+    fixed_time: Res<Time<Fixed>>,
+    mut chase_query: Query<
+        (
+            &mut MovementController,
+            &mut Chase,
+            &FixedMovement,
+            &Transform,
+        ),
+        (With<Enemy>, Without<Culled>),
+    >,
+    player: Single<&Transform, With<Player>>,
+    obstacles: Res<ObstacleGrid>,
+) {
+    let Some(layout) = obstacles.layout() else {
+        return;
+    };
+    let player_pos = player.translation.xy();
+    // The player stands on the tile under their footprint, not the one under their sprite.
+    let player_feet = player_pos + character_footprint(player.scale.xy()).center();
+    let player_tile = layout.tile_pos(player_feet);
+    for (mut controller, mut chase, movement, transform) in &mut chase_query {
+        let position = movement.position();
+        let Some(player_tile) =
+            player_tile.filter(|_| position.distance(player_pos) <= CHASE_RANGE)
+        else {
+            chase.goal = None;
+            chase.path = None;
+            continue;
+        };
+        let player_moved = chase.goal.is_none_or(|goal| {
+            goal.x
+                .abs_diff(player_tile.x)
+                .max(goal.y.abs_diff(player_tile.y))
+                > CHASE_REPATH_TILES
+        });
+        if chase.goal.is_none() || (chase.repath_timer.just_finished() && player_moved) {
+            chase.goal = Some(player_tile);
+            let footprint = character_footprint(transform.scale.xy());
+            chase.path = obstacles.route(position, footprint, player_feet);
         }
-        let velocity = controller.max_speed * controller.intent;
-        transform.translation += velocity.extend(0.0) * time.delta_secs();
+        let Some(path) = chase.path.as_mut() else {
+            continue;
+        };
+        let step = controller.max_speed * fixed_time.timestep().as_secs_f32();
+        let target = path.next(position, step).unwrap_or(player_pos);
+        controller.intent = (target - position).normalize_or_zero();
+    }
+}
+
+/// Enemies scuff the ground whenever they set off in a new direction,
+/// louder the closer they are to the player.
+fn play_enemy_steps(
+    mut commands: Commands,
+    player_assets: Res<PlayerAssets>,
+    mut rng: ResMut<GameRng>,
+    player: Single<&GlobalTransform, With<Player>>,
+    enemy_query: Query<(&RoamTimer, &GlobalTransform), (With<Enemy>, Without<Culled>)>,
+) {
+    let listener_pos = player.translation().xy();
+    for (roam_timer, transform) in &enemy_query {
+        if !roam_timer.0.just_finished() {
+            continue;
+        }
+        let Some(step) = player_assets.steps.choose(&mut rng.0) else {
+            continue;
+        };
+        commands.spawn(spatial_sound_effect(
+            step.clone(),
+            transform.translation().xy(),
+            listener_pos,
+        ));
     }
 }
 
+/// A unit intent in a uniformly random direction. Normalizing a random point in a square
+/// instead would favor diagonals, and leave the enemy standing still on the zero vector.
+fn random_intent(rng: &mut GameRng) -> Vec2 {
+    Vec2::from_angle(rng.0.random_range(0.0..std::f32::consts::TAU))
+}
+
 #[derive(Resource, Asset, Clone, Reflect)]
 #[reflect(Resource)]
 pub struct EnemyAssets {
     #[dependency]
     enemies: Handle<Image>,
+    /// How the enemy sheet splits into frames. Built once and shared by every spawn.
+    layout: Handle<TextureAtlasLayout>,
+}
+
+#[cfg(test)]
+impl EnemyAssets {
+    /// Default handles, for spawning enemies without loading anything.
+    pub fn placeholder() -> Self {
+        Self {
+            enemies: default(),
+            layout: default(),
+        }
+    }
 }
 
 impl FromWorld for EnemyAssets {
     fn from_world(world: &mut World) -> Self {
+        let layout = TextureAtlasLayout::from_grid(
+            UVec2::new(GRID_SIZE_X, GRID_SIZE_Y),
+            ENEMY_ATLAS_COLUMNS,
+            2,
+            None,
+            None,
+        );
+        let layout = world
+            .resource_mut::<Assets<TextureAtlasLayout>>()
+            .add(layout);
         let assets = world.resource::<AssetServer>();
         Self {
+            layout,
             enemies: assets.load_with_settings(
                 "images/enemies.png",
                 |settings: &mut ImageLoaderSettings| {
@@ -109,3 +531,55 @@ impl FromWorld for EnemyAssets {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::game::movement::tests::{STEPS_PER_SEC, grid, walk_route};
+
+    #[test]
+    fn chase_routes_clear_the_obstacles_below_them() {
+        // A wall in the middle of the lower two rows. The way around it runs right above it.
+        let grid = grid(TILE_SCALE, [TilePos { x: 4, y: 0 }, TilePos { x: 4, y: 1 }]);
+        let layout = grid.layout().expect("The grid should have a map");
+        let scale = Vec3::new(PLAYER_SCALE, PLAYER_SCALE, 1.0);
+        let footprint = character_footprint(scale.xy());
+        let player_pos = layout.tile_center(&TilePos { x: 6, y: 1 }) - footprint.center();
+
+        let mut world = World::new();
+        world.init_resource::<Time<Fixed>>();
+        world.insert_resource(grid);
+        world.spawn((
+            Player,
+            Transform::from_translation(player_pos.extend(0.0)).with_scale(scale),
+        ));
+        // `FixedMovement` starts out at the origin, in the middle of tile (3, 1).
+        let enemy = world
+            .spawn((
+                Enemy,
+                MovementController::default(),
+                Chase::default(),
+                FixedMovement::default(),
+                Transform::from_scale(scale),
+            ))
+            .id();
+        world
+            .run_system_once(apply_chase)
+            .expect("The chase should run");
+        let chase = world.get::<Chase>(enemy).expect("The enemy should chase");
+        assert_eq!(chase.goal, Some(TilePos { x: 6, y: 1 }));
+        let path = chase.path.clone().expect("The player should be reachable");
+        assert_eq!(path.0.len(), 5);
+
+        let step = PLAYER_MAX_SPEED * EnemyKind::Fast.speed_multiplier() / STEPS_PER_SEC;
+        walk_route(
+            world.resource::<ObstacleGrid>(),
+            Vec2::ZERO,
+            footprint,
+            path,
+            step,
+        );
+    }
+}
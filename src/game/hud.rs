@@ -0,0 +1,297 @@
+//! The heads-up display shown on top of the gameplay screen.
+
+use bevy::{ecs::spawn::SpawnIter, prelude::*, ui::Val::*};
+
+use crate::{
+    AppSystems,
+    game::{
+        animation::ActionType,
+        crops::SelectedSeed,
+        inventory::{Inventory, ItemKind},
+        player::{Player, SelectedTool},
+        stamina::Stamina,
+        weather::Weather,
+    },
+    states::{GameState, GameplayState},
+    theme::prelude::*,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<InventoryCountLabel>();
+    app.register_type::<StaminaBar>();
+    app.register_type::<StaminaBarFill>();
+    app.register_type::<SelectedSeedLabel>();
+    app.register_type::<WeatherLabel>();
+    app.register_type::<HotbarSlot>();
+    app.add_systems(OnEnter(GameState::Gameplay), (spawn_hud, spawn_hotbar));
+    app.add_systems(
+        Update,
+        (
+            update_inventory_labels,
+            update_stamina_bar,
+            update_selected_seed_label,
+            update_weather_label,
+            update_hotbar,
+        )
+            .run_if(in_state(GameplayState::Running))
+            .in_set(AppSystems::PostUpdate),
+    );
+}
+
+const STAMINA_BAR_WIDTH: f32 = 120.0;
+const STAMINA_BAR_HEIGHT: f32 = 10.0;
+const STAMINA_BAR_BACKGROUND: Color = Color::srgba(0.0, 0.0, 0.0, 0.5);
+const STAMINA_BAR_COLOR: Color = Color::srgb(0.95, 0.8, 0.2);
+const HOTBAR_SLOT_BACKGROUND: Color = Color::srgba(0.0, 0.0, 0.0, 0.5);
+const HOTBAR_SLOT_BORDER: Color = Color::srgba(1.0, 1.0, 1.0, 0.2);
+const SELECTED_SLOT_BACKGROUND: Color = Color::srgba(0.95, 0.8, 0.2, 0.35);
+const SELECTED_SLOT_BORDER: Color = Color::srgb(0.95, 0.8, 0.2);
+
+/// Marks the text showing how many of an [`ItemKind`] the player carries.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct InventoryCountLabel(ItemKind);
+
+/// Marks the text showing which seed the player plants.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct SelectedSeedLabel;
+
+/// Marks the text showing the current [`Weather`].
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct WeatherLabel;
+
+/// A slot of the tool hotbar, highlighted while its tool is the [`SelectedTool`].
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct HotbarSlot(ActionType);
+
+/// The frame of the player's stamina bar. Hidden when the player has no [`Stamina`].
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct StaminaBar;
+
+/// The part of the stamina bar that shrinks as stamina is spent.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct StaminaBarFill;
+
+fn spawn_hud(
+    mut commands: Commands,
+    inventory: Res<Inventory>,
+    selected_seed: Res<SelectedSeed>,
+    weather: Res<Weather>,
+) {
+    let counts = ItemKind::ALL.map(|kind| (kind, inventory.count(kind)));
+    commands.spawn((
+        Name::new("HUD"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Px(10.0),
+            left: Px(10.0),
+            flex_direction: FlexDirection::Column,
+            row_gap: Px(5.0),
+            ..default()
+        },
+        DespawnOnExit(GameState::Gameplay),
+        Children::spawn((
+            Spawn(stamina_bar()),
+            SpawnIter(
+                counts
+                    .into_iter()
+                    .map(|(kind, count)| inventory_counter(kind, count)),
+            ),
+            Spawn((
+                widget::label(selected_seed_text(*selected_seed)),
+                SelectedSeedLabel,
+            )),
+            Spawn((widget::label(weather_text(*weather)), WeatherLabel)),
+        )),
+    ));
+}
+
+/// The row of tools along the bottom of the screen.
+fn spawn_hotbar(mut commands: Commands, selected_tool: Res<SelectedTool>) {
+    let selected = selected_tool.0;
+    commands.spawn((
+        Name::new("Hotbar"),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Px(10.0),
+            width: Percent(100.0),
+            justify_content: JustifyContent::Center,
+            column_gap: Px(8.0),
+            ..default()
+        },
+        Pickable::IGNORE,
+        DespawnOnExit(GameState::Gameplay),
+        Children::spawn(SpawnIter(
+            ActionType::ALL
+                .into_iter()
+                .enumerate()
+                .map(move |(i, tool)| hotbar_slot(i + 1, tool, tool == selected)),
+        )),
+    ));
+}
+
+fn hotbar_slot(number: usize, tool: ActionType, selected: bool) -> impl Bundle {
+    let (background, border) = hotbar_slot_colors(selected);
+    (
+        Name::new(format!("{} Slot", tool.tool_name())),
+        HotbarSlot(tool),
+        Node {
+            padding: UiRect::axes(Px(12.0), Px(6.0)),
+            border: UiRect::all(Px(2.0)),
+            ..default()
+        },
+        BackgroundColor(background),
+        BorderColor::all(border),
+        BorderRadius::all(Px(4.0)),
+        children![widget::label(format!("{number} {}", tool.tool_name()))],
+    )
+}
+
+fn hotbar_slot_colors(selected: bool) -> (Color, Color) {
+    if selected {
+        (SELECTED_SLOT_BACKGROUND, SELECTED_SLOT_BORDER)
+    } else {
+        (HOTBAR_SLOT_BACKGROUND, HOTBAR_SLOT_BORDER)
+    }
+}
+
+fn update_hotbar(
+    selected_tool: Res<SelectedTool>,
+    mut slot_query: Query<(&HotbarSlot, &mut BackgroundColor, &mut BorderColor)>,
+) {
+    if !selected_tool.is_changed() {
+        return;
+    }
+    for (slot, mut background, mut border) in &mut slot_query {
+        let (background_color, border_color) = hotbar_slot_colors(slot.0 == selected_tool.0);
+        background.0 = background_color;
+        *border = BorderColor::all(border_color);
+    }
+}
+
+fn stamina_bar() -> impl Bundle {
+    (
+        Name::new("Stamina Bar"),
+        StaminaBar,
+        Node {
+            width: Px(STAMINA_BAR_WIDTH),
+            height: Px(STAMINA_BAR_HEIGHT),
+            ..default()
+        },
+        BackgroundColor(STAMINA_BAR_BACKGROUND),
+        Visibility::Hidden,
+        children![(
+            Name::new("Fill"),
+            StaminaBarFill,
+            Node {
+                width: Percent(100.0),
+                height: Percent(100.0),
+                ..default()
+            },
+            BackgroundColor(STAMINA_BAR_COLOR),
+        )],
+    )
+}
+
+fn inventory_counter(kind: ItemKind, count: u32) -> impl Bundle {
+    (
+        Name::new(format!("{} Counter", kind.name())),
+        Node {
+            align_items: AlignItems::Center,
+            column_gap: Px(8.0),
+            ..default()
+        },
+        children![
+            (
+                Name::new("Icon"),
+                Node {
+                    width: Px(20.0),
+                    height: Px(20.0),
+                    ..default()
+                },
+                BackgroundColor(kind.color()),
+            ),
+            (
+                widget::label(inventory_count_text(kind, count)),
+                InventoryCountLabel(kind),
+            ),
+        ],
+    )
+}
+
+fn inventory_count_text(kind: ItemKind, count: u32) -> String {
+    format!("{}: {count}", kind.name())
+}
+
+fn update_inventory_labels(
+    inventory: Res<Inventory>,
+    mut label_query: Query<(&mut Text, &InventoryCountLabel)>,
+) {
+    if !inventory.is_changed() {
+        return;
+    }
+    for (mut text, label) in &mut label_query {
+        text.0 = inventory_count_text(label.0, inventory.count(label.0));
+    }
+}
+
+fn selected_seed_text(selected_seed: SelectedSeed) -> String {
+    format!("Planting: {}", selected_seed.0.name())
+}
+
+fn update_selected_seed_label(
+    selected_seed: Res<SelectedSeed>,
+    mut label_query: Query<&mut Text, With<SelectedSeedLabel>>,
+) {
+    if !selected_seed.is_changed() {
+        return;
+    }
+    for mut text in &mut label_query {
+        text.0 = selected_seed_text(*selected_seed);
+    }
+}
+
+fn weather_text(weather: Weather) -> String {
+    format!("Weather: {}", weather.name())
+}
+
+fn update_weather_label(
+    weather: Res<Weather>,
+    mut label_query: Query<&mut Text, With<WeatherLabel>>,
+) {
+    if !weather.is_changed() {
+        return;
+    }
+    for mut text in &mut label_query {
+        text.0 = weather_text(*weather);
+    }
+}
+
+fn update_stamina_bar(
+    stamina_q: Query<&Stamina, With<Player>>,
+    mut bar_q: Query<&mut Visibility, With<StaminaBar>>,
+    mut fill_q: Query<&mut Node, With<StaminaBarFill>>,
+) {
+    let stamina = stamina_q.single().ok();
+    for mut visibility in &mut bar_q {
+        visibility.set_if_neq(if stamina.is_some() {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+    let Some(stamina) = stamina else {
+        return;
+    };
+    for mut node in &mut fill_q {
+        let width = Percent(stamina.fraction() * 100.0);
+        if node.width != width {
+            node.width = width;
+        }
+    }
+}
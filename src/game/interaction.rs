@@ -0,0 +1,125 @@
+//! Things the player can interact with by facing them and pressing [`INTERACT_KEY`], and the
+//! prompt that floats over the one they'd interact with.
+
+use bevy::prelude::*;
+
+use crate::{
+    AppSystems,
+    constants::{TILE_SCALE, TILE_WORLD_SIZE},
+    game::{
+        animation::{Facing, facing_tile_position},
+        chest::OpenChest,
+        dialogue::DialogueState,
+        player::Player,
+    },
+    states::{GameState, GameplayState},
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<Interactable>();
+    app.add_systems(OnEnter(GameState::Gameplay), spawn_interaction_prompt);
+    app.add_systems(
+        Update,
+        update_interaction_prompt
+            .run_if(in_state(GameplayState::Running))
+            .in_set(AppSystems::PostUpdate),
+    );
+}
+
+/// Interacts with whatever the player is facing.
+pub const INTERACT_KEY: KeyCode = KeyCode::Space;
+/// How [`INTERACT_KEY`] is written in the prompt.
+const INTERACT_KEY_LABEL: &str = "Space";
+/// How close something has to be to the tile in front of the player to be interacted with.
+const INTERACT_RANGE: f32 = TILE_WORLD_SIZE;
+/// How far above the interactable the prompt floats, in world units.
+const PROMPT_OFFSET_Y: f32 = TILE_WORLD_SIZE * 1.5;
+/// Z position for rendering the prompt, above everything in the world.
+const PROMPT_Z: f32 = 5.0;
+const PROMPT_FONT_SIZE: f32 = TILE_SCALE * 6.0;
+
+/// Something the player can interact with, like an NPC or a chest.
+#[derive(Component, Debug, Clone, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct Interactable {
+    /// What interacting does, e.g. "Talk", shown in the prompt.
+    pub action: String,
+}
+
+impl Interactable {
+    pub fn new(action: impl Into<String>) -> Self {
+        Self {
+            action: action.into(),
+        }
+    }
+}
+
+/// The closest candidate on or next to the tile in front of `position` when facing `facing`.
+pub fn in_front<'a, T>(
+    position: Vec2,
+    facing: Facing,
+    candidates: impl IntoIterator<Item = (T, &'a GlobalTransform)>,
+) -> Option<T> {
+    let target = facing_tile_position(position, facing.0);
+    candidates
+        .into_iter()
+        .map(|(candidate, transform)| (candidate, transform.translation().xy().distance(target)))
+        .filter(|&(_, distance)| distance <= INTERACT_RANGE)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(candidate, _)| candidate)
+}
+
+/// The floating "Space: Talk" label.
+#[derive(Component)]
+struct InteractionPrompt;
+
+fn spawn_interaction_prompt(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Interaction Prompt"),
+        InteractionPrompt,
+        Text2d::default(),
+        TextFont::from_font_size(PROMPT_FONT_SIZE),
+        TextColor(Color::WHITE),
+        Transform::from_xyz(0.0, 0.0, PROMPT_Z),
+        Visibility::Hidden,
+        DespawnOnExit(GameState::Gameplay),
+    ));
+}
+
+/// Float the prompt over the interactable the player is facing, if any.
+fn update_interaction_prompt(
+    player_q: Query<(&GlobalTransform, &Facing), With<Player>>,
+    interactable_q: Query<(&Interactable, &GlobalTransform)>,
+    mut prompt_q: Query<(&mut Text2d, &mut Transform, &mut Visibility), With<InteractionPrompt>>,
+    dialogue: Res<DialogueState>,
+    open_chest: Res<OpenChest>,
+) {
+    let Ok((mut text, mut transform, mut visibility)) = prompt_q.single_mut() else {
+        return;
+    };
+    let target = player_q
+        .single()
+        .ok()
+        // The prompt would only get in the way of the conversation or the chest's panel.
+        .filter(|_| !dialogue.is_open() && open_chest.0.is_none())
+        .and_then(|(player_transform, facing)| {
+            in_front(
+                player_transform.translation().xy(),
+                *facing,
+                interactable_q
+                    .iter()
+                    .map(|(interactable, transform)| ((interactable, transform), transform)),
+            )
+        });
+    let Some((interactable, target_transform)) = target else {
+        visibility.set_if_neq(Visibility::Hidden);
+        return;
+    };
+    let prompt = format!("{INTERACT_KEY_LABEL}: {}", interactable.action);
+    if text.0 != prompt {
+        text.0 = prompt;
+    }
+    let position = target_transform.translation().xy() + Vec2::Y * PROMPT_OFFSET_Y;
+    transform.translation = position.extend(PROMPT_Z);
+    visibility.set_if_neq(Visibility::Inherited);
+}
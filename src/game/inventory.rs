@@ -0,0 +1,163 @@
+//! The player's inventory and item pickups lying around the world.
+
+use bevy::{camera::primitives::Aabb, platform::collections::HashMap, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AppSystems,
+    constants::TILE_SCALE,
+    game::{
+        collision::{overlaps, world_rect},
+        level::LevelSpawnSystems,
+        notifications::NotifyEvent,
+        player::Player,
+    },
+    states::{DestroyOnEnter, GameState, GameplayState, VisibleInState},
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<Inventory>();
+    app.register_type::<Pickup>();
+    app.init_resource::<Inventory>();
+    app.add_systems(
+        OnEnter(GameState::Gameplay),
+        reset_inventory.in_set(LevelSpawnSystems),
+    );
+    app.add_systems(
+        Update,
+        collect_pickups
+            .run_if(in_state(GameplayState::Running))
+            .in_set(AppSystems::Update),
+    );
+}
+
+/// Z position for rendering pickups, just below the player.
+const PICKUP_Z: f32 = 1.5;
+/// Size of a pickup sprite in world units.
+const PICKUP_SIZE: f32 = TILE_SCALE * 8.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum ItemKind {
+    Wood,
+    Seed,
+    PumpkinSeed,
+    Crop,
+}
+
+impl ItemKind {
+    pub const ALL: [ItemKind; 4] = [
+        ItemKind::Wood,
+        ItemKind::Seed,
+        ItemKind::PumpkinSeed,
+        ItemKind::Crop,
+    ];
+    /// The items that can be planted, in the order Tab cycles through them.
+    pub const SEEDS: [ItemKind; 2] = [ItemKind::Seed, ItemKind::PumpkinSeed];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ItemKind::Wood => "Wood",
+            ItemKind::Seed => "Seed",
+            ItemKind::PumpkinSeed => "Pumpkin Seed",
+            ItemKind::Crop => "Crop",
+        }
+    }
+
+    /// Placeholder color used to draw the item until it has proper art.
+    pub fn color(self) -> Color {
+        match self {
+            ItemKind::Wood => Color::srgb(0.545, 0.353, 0.169),
+            ItemKind::Seed => Color::srgb(0.835, 0.741, 0.412),
+            ItemKind::PumpkinSeed => Color::srgb(0.949, 0.898, 0.737),
+            ItemKind::Crop => Color::srgb(0.910, 0.561, 0.161),
+        }
+    }
+}
+
+/// Item counts carried by the player.
+#[derive(Resource, Debug, Default, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct Inventory {
+    items: HashMap<ItemKind, u32>,
+}
+
+impl Inventory {
+    pub fn count(&self, kind: ItemKind) -> u32 {
+        self.items.get(&kind).copied().unwrap_or(0)
+    }
+
+    pub fn add(&mut self, kind: ItemKind, amount: u32) {
+        *self.items.entry(kind).or_insert(0) += amount;
+    }
+
+    /// Remove `amount` items of `kind` if there are that many, returning whether they were removed.
+    pub fn take(&mut self, kind: ItemKind, amount: u32) -> bool {
+        match self.items.get_mut(&kind) {
+            Some(count) if *count >= amount => {
+                *count -= amount;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// An item lying in the world that is added to the [`Inventory`] when the player walks over it.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct Pickup {
+    pub kind: ItemKind,
+    pub amount: u32,
+}
+
+/// A pickup of `amount` items of `kind` lying at `position`.
+pub fn pickup(kind: ItemKind, amount: u32, position: Vec2) -> impl Bundle {
+    (
+        Name::new(format!("{} Pickup", kind.name())),
+        Pickup { kind, amount },
+        Sprite::from_color(kind.color(), Vec2::splat(PICKUP_SIZE)),
+        Transform::from_translation(position.extend(PICKUP_Z)),
+        VisibleInState(vec![GameState::Gameplay]),
+        DestroyOnEnter(vec![GameState::Title, GameState::GameOver]),
+    )
+}
+
+/// Seeds of each kind the player starts a new game with.
+const STARTING_SEEDS: u32 = 5;
+
+/// A fresh level starts with nothing but a few seeds.
+fn reset_inventory(mut inventory: ResMut<Inventory>) {
+    *inventory = Inventory::default();
+    for seed in ItemKind::SEEDS {
+        inventory.add(seed, STARTING_SEEDS);
+    }
+}
+
+fn collect_pickups(
+    mut commands: Commands,
+    mut inventory: ResMut<Inventory>,
+    player_query: Query<(&GlobalTransform, &Aabb), With<Player>>,
+    pickup_query: Query<(Entity, &Pickup, &GlobalTransform, &Aabb)>,
+    mut notify_events: MessageWriter<NotifyEvent>,
+) {
+    for (player_transform, player_aabb) in &player_query {
+        let player_rect = world_rect(player_transform, player_aabb);
+        for (entity, pickup, transform, aabb) in &pickup_query {
+            if overlaps(player_rect, world_rect(transform, aabb)) {
+                inventory.add(pickup.kind, pickup.amount);
+                info!(
+                    "Picked up {} {} ({} total)",
+                    pickup.amount,
+                    pickup.kind.name(),
+                    inventory.count(pickup.kind)
+                );
+                notify_events.write(NotifyEvent(format!(
+                    "Picked up {} {}",
+                    pickup.amount,
+                    pickup.kind.name()
+                )));
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
@@ -3,22 +3,60 @@
 use bevy::prelude::*;
 
 use crate::{
-    game::player::{PlayerAssets, player},
+    game::{
+        enemy::Enemy,
+        player::{Player, PlayerAssets, player},
+    },
     states::{DestroyOnEnter, GameState, VisibleInState},
 };
 
-/// A system that spawns the main level.
-pub fn spawn_level(
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<Level>();
+
+    // Every path into gameplay goes through here, but coming back from the settings
+    // must keep the running level, so only spawn when there is none.
+    app.configure_sets(
+        OnEnter(GameState::Gameplay),
+        LevelSpawnSystems.run_if(level_missing),
+    );
+    app.add_systems(
+        OnEnter(GameState::Gameplay),
+        (despawn_leftovers, spawn_level).in_set(LevelSpawnSystems),
+    );
+}
+
+/// Systems that build a fresh level when entering [`GameState::Gameplay`] without one.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LevelSpawnSystems;
+
+/// Marks the root entity of the spawned level.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+#[reflect(Component)]
+pub struct Level;
+
+fn level_missing(level_query: Query<(), With<Level>>) -> bool {
+    level_query.is_empty()
+}
+
+/// Clear out any player or enemy that outlived its level, so the new one starts clean.
+fn despawn_leftovers(
     mut commands: Commands,
-    player_assets: Res<PlayerAssets>,
-    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    leftover_query: Query<Entity, Or<(With<Player>, With<Enemy>)>>,
 ) {
+    for entity in &leftover_query {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// A system that spawns the main level.
+pub fn spawn_level(mut commands: Commands, player_assets: Res<PlayerAssets>) {
     commands.spawn((
         Name::new("Level"),
+        Level,
         Transform::default(),
         Visibility::default(),
         VisibleInState(vec![GameState::Gameplay]),
-        DestroyOnEnter(vec![GameState::Title]),
-        children![player(&player_assets, &mut texture_atlas_layouts)],
+        DestroyOnEnter(vec![GameState::Title, GameState::GameOver]),
+        children![player(&player_assets)],
     ));
 }
@@ -0,0 +1,129 @@
+//! A minimap in the corner of the gameplay screen, drawn by a second camera
+//! that looks at the whole farm at once.
+
+use bevy::{
+    camera::{ScalingMode, Viewport, visibility::RenderLayers},
+    prelude::*,
+    window::{PrimaryWindow, WindowResized},
+};
+
+use crate::{
+    AppSystems,
+    constants::*,
+    states::{GameState, GameplayState},
+    world::tiledhelper::MapDimensions,
+};
+
+use super::{enemy::Enemy, player::Player};
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<MinimapCamera>();
+    app.add_systems(OnEnter(GameState::Gameplay), spawn_minimap_camera);
+    app.add_systems(
+        Update,
+        (
+            add_minimap_dots,
+            fit_minimap_camera
+                .run_if(on_message::<WindowResized>.or(resource_changed::<MapDimensions>)),
+        )
+            .run_if(in_state(GameplayState::Running))
+            .in_set(AppSystems::PostUpdate),
+    );
+}
+
+/// Marks the camera rendering the minimap.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct MinimapCamera;
+
+/// Render layer for things only the minimap should draw.
+const MINIMAP_LAYER: usize = 1;
+/// Width of the minimap on screen, in logical pixels. The height follows the map's aspect ratio.
+const MINIMAP_WIDTH: f32 = 216.0;
+const MINIMAP_MARGIN: f32 = 10.0;
+/// Size of the player and enemy dots, in world units.
+const MINIMAP_DOT_SIZE: f32 = TILE_WORLD_SIZE * 1.5;
+const MINIMAP_BACKGROUND: Color = Color::srgb(0.1, 0.1, 0.1);
+const PLAYER_DOT_COLOR: Color = Color::srgb(0.2, 0.6, 1.0);
+const ENEMY_DOT_COLOR: Color = Color::srgb(0.9, 0.2, 0.2);
+
+/// The bottom-right corner of `window`, sized to fit the whole map.
+fn minimap_viewport(window: &Window, map_size: Vec2) -> Viewport {
+    let size = Vec2::new(MINIMAP_WIDTH, MINIMAP_WIDTH * map_size.y / map_size.x);
+    let position = window.size() - size - Vec2::splat(MINIMAP_MARGIN);
+    let scale_factor = window.scale_factor();
+    Viewport {
+        physical_position: (position.max(Vec2::ZERO) * scale_factor).as_uvec2(),
+        physical_size: (size * scale_factor).as_uvec2(),
+        ..default()
+    }
+}
+
+/// Looks at the whole map at once.
+fn minimap_projection(map_size: Vec2) -> Projection {
+    Projection::Orthographic(OrthographicProjection {
+        scaling_mode: ScalingMode::Fixed {
+            width: map_size.x,
+            height: map_size.y,
+        },
+        ..OrthographicProjection::default_2d()
+    })
+}
+
+fn spawn_minimap_camera(
+    mut commands: Commands,
+    window: Single<&Window, With<PrimaryWindow>>,
+    map_dimensions: Res<MapDimensions>,
+) {
+    let map_size = map_dimensions.world_size();
+    commands.spawn((
+        Name::new("Minimap Camera"),
+        MinimapCamera,
+        Camera2d,
+        Camera {
+            // Draw on top of the main camera.
+            order: 1,
+            viewport: Some(minimap_viewport(&window, map_size)),
+            clear_color: ClearColorConfig::Custom(MINIMAP_BACKGROUND),
+            ..default()
+        },
+        minimap_projection(map_size),
+        RenderLayers::from_layers(&[0, MINIMAP_LAYER]),
+        Msaa::Off,
+        DespawnOnExit(GameState::Gameplay),
+    ));
+}
+
+/// Keep the minimap in its corner and showing the whole map, whichever size either is.
+fn fit_minimap_camera(
+    window: Single<&Window, With<PrimaryWindow>>,
+    map_dimensions: Res<MapDimensions>,
+    mut minimap_query: Query<(&mut Camera, &mut Projection), With<MinimapCamera>>,
+) {
+    let map_size = map_dimensions.world_size();
+    for (mut camera, mut projection) in &mut minimap_query {
+        camera.viewport = Some(minimap_viewport(&window, map_size));
+        *projection = minimap_projection(map_size);
+    }
+}
+
+/// Give every newly spawned player and enemy a dot that only the minimap camera renders.
+fn add_minimap_dots(
+    mut commands: Commands,
+    new_query: Query<(Entity, Has<Player>), Or<(Added<Player>, Added<Enemy>)>>,
+) {
+    for (entity, is_player) in &new_query {
+        let color = if is_player {
+            PLAYER_DOT_COLOR
+        } else {
+            ENEMY_DOT_COLOR
+        };
+        commands.entity(entity).with_child((
+            Name::new("Minimap Dot"),
+            // Players and enemies are both drawn at `PLAYER_SCALE`, which the dot inherits.
+            Sprite::from_color(color, Vec2::splat(MINIMAP_DOT_SIZE / PLAYER_SCALE)),
+            Transform::from_xyz(0.0, 0.0, 10.0),
+            RenderLayers::layer(MINIMAP_LAYER),
+        ));
+    }
+}
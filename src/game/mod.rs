@@ -1,23 +1,62 @@
-//! Demo gameplay. All of these modules are only intended for demonstration
-//! purposes and should be replaced with your own game logic.
-//! Feel free to change the logic found here if you feel like tinkering around
-//! to get a feeling for the template.
+//! Gameplay: the player, enemies, actions and everything that runs while
+//! [`GameplayState::Running`](crate::states::GameplayState::Running) is active.
 
 use bevy::prelude::*;
 
-mod animation;
+pub mod animation;
 pub mod camera;
-mod enemy;
+pub mod chest;
+mod collision;
+mod combat;
+pub mod crops;
+mod day_cycle;
+mod dialogue;
+mod effects;
+pub mod enemy;
+mod hud;
+mod interaction;
+pub mod inventory;
 pub mod level;
-mod movement;
-mod player;
+mod minimap;
+pub mod movement;
+pub mod notifications;
+pub mod npc;
+pub mod player;
+mod rng;
+mod rumble;
+pub mod save;
+mod stamina;
+mod weather;
+pub mod y_sort;
 
 pub(super) fn plugin(app: &mut App) {
+    // Plugin tuples top out at 15, so they're added in two batches.
     app.add_plugins((
         animation::plugin,
         movement::plugin,
         player::plugin,
         camera::plugin,
+        chest::plugin,
         enemy::plugin,
+        effects::plugin,
+        rng::plugin,
+        combat::plugin,
+        crops::plugin,
+        inventory::plugin,
+    ));
+    app.add_plugins((
+        hud::plugin,
+        day_cycle::plugin,
+        dialogue::plugin,
+        npc::plugin,
+        notifications::plugin,
+        rumble::plugin,
+        interaction::plugin,
+        level::plugin,
+        minimap::plugin,
+        save::plugin,
+        stamina::plugin,
+        weather::plugin,
+        y_sort::plugin,
     ));
 }
@@ -6,25 +6,56 @@
 //! - Set [`MovementController`] intent based on directional keyboard input.
 //!   This is done in the `player` module, as it is specific to the player
 //!   character.
-//! - Apply movement based on [`MovementController`] intent and maximum speed.
-//! - Wrap the character within the window.
+//! - Accelerate towards the velocity given by the [`MovementController`] intent and
+//!   maximum speed, and apply it unless an [`Obstacle`] tile is in the way.
 //!
-//! Note that the implementation used here is limited for demonstration
-//! purposes. If you want to move the player in a smoother way,
-//! consider using a [fixed timestep](https://github.com/bevyengine/bevy/blob/main/examples/movement/physics_in_fixed_timestep.rs).
+//! Wrapping characters around the map edges lives in the `camera` module.
+//!
+//! Movement is simulated in [`FixedUpdate`] so it behaves the same at any frame rate,
+//! following the [fixed timestep](https://github.com/bevyengine/bevy/blob/main/examples/movement/physics_in_fixed_timestep.rs)
+//! example. Input is still recorded every frame in [`Update`], and the rendered
+//! [`Transform`] is interpolated between the last two simulation steps.
+
+use std::collections::VecDeque;
 
-use bevy::{camera::primitives::Aabb, prelude::*};
+use bevy::{platform::collections::HashSet, prelude::*};
 use bevy_ecs_tilemap::prelude::*;
 
-use crate::{AppSystems, states::GameState, world::tiledhelper::Obstacle};
+use crate::{
+    AppSystems,
+    constants::{
+        CHARACTER_FOOTPRINT_HEIGHT, CHARACTER_FOOTPRINT_WIDTH, INTENT_EPSILON, PLAYER_ACCELERATION,
+        PLAYER_MAX_SPEED, PLAYER_VISIBLE_OFFSET_Y, TILE_WORLD_SIZE,
+    },
+    game::enemy::Culled,
+    states::GameplayState,
+    world::{
+        tiledhelper::{Obstacle, process_loaded_maps},
+        tilemap::{TilemapLayout, WalkabilityGrid},
+    },
+};
 
 pub(super) fn plugin(app: &mut App) {
     app.register_type::<MovementController>();
+    app.register_type::<FixedMovement>();
+    app.init_resource::<ObstacleGrid>();
     app.add_systems(
         Update,
-        (apply_movement,)
-            .run_if(in_state(GameState::Gameplay))
-            .in_set(AppSystems::Update),
+        rebuild_obstacle_grid
+            .after(process_loaded_maps)
+            .in_set(AppSystems::PreUpdate),
+    );
+    app.add_systems(
+        RunFixedMainLoop,
+        (
+            sync_teleported_movement.in_set(RunFixedMainLoopSystems::BeforeFixedMainLoop),
+            interpolate_movement.in_set(RunFixedMainLoopSystems::AfterFixedMainLoop),
+        )
+            .run_if(in_state(GameplayState::Running)),
+    );
+    app.add_systems(
+        FixedUpdate,
+        apply_movement.run_if(in_state(GameplayState::Running)),
     );
 }
 
@@ -33,6 +64,7 @@ pub(super) fn plugin(app: &mut App) {
 /// other players as well.
 #[derive(Component, Reflect)]
 #[reflect(Component)]
+#[require(FixedMovement)]
 pub struct MovementController {
     /// The direction the character wants to move in.
     pub intent: Vec2,
@@ -40,66 +72,416 @@ pub struct MovementController {
     /// Maximum speed in world units per second.
     /// 1 world unit = 1 pixel when using the default 2D camera and no physics engine.
     pub max_speed: f32,
+
+    /// How fast the character speeds up and slows down, in world units per second squared.
+    pub acceleration: f32,
 }
 
 impl Default for MovementController {
     fn default() -> Self {
         Self {
             intent: Vec2::ZERO,
-            // 400 pixels per second is a nice default, but we can still vary this per character.
-            max_speed: 400.0,
+            // Half the player's speed is a nice default, but we can still vary this per character.
+            max_speed: PLAYER_MAX_SPEED / 2.0,
+            // Reaching full speed in a tenth of a second keeps movement snappy.
+            acceleration: PLAYER_ACCELERATION / 2.0,
+        }
+    }
+}
+
+impl MovementController {
+    /// Whether the intent is small enough to treat the character as standing still,
+    /// so gamepad drift or a half-released key doesn't count as movement.
+    pub fn is_still(&self) -> bool {
+        self.intent.length_squared() < INTENT_EPSILON
+    }
+
+    /// The velocity the character accelerates towards, in world units per second. The intent
+    /// is capped to unit length, so moving diagonally is never faster than moving straight,
+    /// whoever set the intent.
+    pub fn target_velocity(&self) -> Vec2 {
+        self.max_speed * self.intent.clamp_length_max(1.0)
+    }
+}
+
+/// A character's position in the fixed timestep simulation.
+#[derive(Component, Debug, Default, Clone, Copy, Reflect)]
+#[reflect(Component)]
+pub struct FixedMovement {
+    previous: Vec2,
+    current: Vec2,
+    /// Velocity in world units per second, easing towards the controller's target velocity.
+    velocity: Vec2,
+    /// The position last written to the [`Transform`], to notice when something else moved it.
+    rendered: Vec2,
+}
+
+impl FixedMovement {
+    /// Position after the latest simulation step.
+    pub fn position(&self) -> Vec2 {
+        self.current
+    }
+}
+
+/// Every [`Obstacle`] tile position of the current map, so movement checks are a
+/// single hash lookup no matter how many layers or movers there are.
+/// Shared by the player and enemy movement so both respect the same terrain.
+#[derive(Resource, Default)]
+pub struct ObstacleGrid {
+    tiles: HashSet<TilePos>,
+    /// How world positions map onto tile positions. All layers of a map share it.
+    layout: Option<TilemapLayout>,
+    /// The same obstacles as a grid to find paths over.
+    walkability: Option<WalkabilityGrid>,
+}
+
+impl ObstacleGrid {
+    /// The `obstacles` of a map laid out as `layout`, if there's a map in.
+    pub fn new(obstacles: HashSet<TilePos>, layout: Option<TilemapLayout>) -> Self {
+        let walkability =
+            layout.map(|layout| WalkabilityGrid::new(layout.map_size(), obstacles.iter().copied()));
+        Self {
+            tiles: obstacles,
+            layout,
+            walkability,
+        }
+    }
+
+    /// How the current map's tiles are laid out, once a map with obstacles is in.
+    pub fn layout(&self) -> Option<TilemapLayout> {
+        self.layout
+    }
+
+    /// Whether `world_pos` lies on an obstacle tile in any layer.
+    pub fn is_obstacle(&self, world_pos: Vec2) -> bool {
+        self.layout
+            .and_then(|layout| layout.tile_pos(world_pos))
+            .is_some_and(|tile_pos| self.is_obstacle_tile(&tile_pos))
+    }
+
+    /// Whether `tile_pos` is an obstacle in any layer.
+    pub fn is_obstacle_tile(&self, tile_pos: &TilePos) -> bool {
+        self.tiles.contains(tile_pos)
+    }
+
+    /// Whether moving a character at `position` by `delta` would run any corner of its
+    /// `footprint` (see [`character_footprint`]) into an obstacle.
+    pub fn is_move_blocked(&self, position: Vec2, footprint: Rect, delta: Vec2) -> bool {
+        footprint_corners(position + delta, footprint)
+            .into_iter()
+            .any(|corner| self.is_obstacle(corner))
+    }
+
+    /// A route around the obstacles for a character with `footprint` at `position` to the
+    /// tile under `target`, as the positions to walk through. The tiles are the ones under
+    /// the footprint's center, and each position lines that center up with a tile center, so
+    /// the footprint doesn't clip the obstacles alongside. The tile the character already
+    /// stands on is left out. `None` without a map or if the tile can't be reached.
+    pub fn route(&self, position: Vec2, footprint: Rect, target: Vec2) -> Option<Waypoints> {
+        let (layout, walkability) = (self.layout?, self.walkability.as_ref()?);
+        let start = layout.tile_pos(position + footprint.center())?;
+        let path = walkability.astar(start, layout.tile_pos(target)?)?;
+        let waypoints = path.iter().skip(1);
+        Some(Waypoints(
+            waypoints
+                .map(|tile| layout.tile_center(tile) - footprint.center())
+                .collect(),
+        ))
+    }
+}
+
+/// How close a character has to get to a waypoint to head for the next one. Heading off from
+/// short of a turn cuts its corner, so this stays small enough that the footprint doesn't
+/// clip the obstacle on the inside of the turn.
+const WAYPOINT_RADIUS: f32 = TILE_WORLD_SIZE / 5.0;
+
+/// The positions left to walk through on an [`ObstacleGrid::route`], next one first.
+#[derive(Debug, Clone, Default, PartialEq, Reflect)]
+pub struct Waypoints(pub VecDeque<Vec2>);
+
+impl Waypoints {
+    /// Drop the waypoints reached at `position` and return the one to head for next. A
+    /// character moving `step` per fixed step could step right over a waypoint and swing
+    /// back and forth around it, so anything within half a step also counts as reached.
+    pub fn next(&mut self, position: Vec2, step: f32) -> Option<Vec2> {
+        let radius = WAYPOINT_RADIUS.max(step / 2.0);
+        while self
+            .0
+            .front()
+            .is_some_and(|waypoint| waypoint.distance(position) < radius)
+        {
+            self.0.pop_front();
+        }
+        self.0.front().copied()
+    }
+}
+
+/// The box a character drawn at `scale` collides with, relative to its position. It sits on
+/// the visible character rather than the center of its sprite frame, and scales with the
+/// sprite but stays much smaller than it, so characters fit through one-tile gaps.
+pub fn character_footprint(scale: Vec2) -> Rect {
+    Rect::from_center_size(
+        Vec2::new(0.0, PLAYER_VISIBLE_OFFSET_Y) * scale,
+        Vec2::new(CHARACTER_FOOTPRINT_WIDTH, CHARACTER_FOOTPRINT_HEIGHT) * scale,
+    )
+}
+
+/// The world positions of the corners of `footprint` at `position`, the points
+/// [`ObstacleGrid::is_move_blocked`] checks.
+pub fn footprint_corners(position: Vec2, footprint: Rect) -> [Vec2; 4] {
+    let Rect { min, max } = footprint;
+    [min, vec2(max.x, min.y), vec2(min.x, max.y), max].map(|corner| position + corner)
+}
+
+/// Rebuild the [`ObstacleGrid`] whenever obstacle tiles come or go, i.e. when a map is
+/// (re)loaded or switched.
+pub(super) fn rebuild_obstacle_grid(
+    mut grid: ResMut<ObstacleGrid>,
+    added_q: Query<(), Added<Obstacle>>,
+    mut removed: RemovedComponents<Obstacle>,
+    obstacle_q: Query<(&TilePos, &TilemapId), With<Obstacle>>,
+    tilemap_q: Query<(
+        &TilemapSize,
+        &TilemapGridSize,
+        &TilemapTileSize,
+        &TilemapType,
+        &TilemapAnchor,
+        &Transform,
+    )>,
+) {
+    // Read every removal so they don't count again next frame.
+    if removed.read().count() == 0 && added_q.is_empty() {
+        return;
+    }
+    let tiles = obstacle_q.iter().map(|(tile_pos, _)| *tile_pos).collect();
+    // Layers are spawned this frame, before their `GlobalTransform` is propagated,
+    // but they have no parent so their `Transform` is already the global one.
+    let layout = obstacle_q
+        .iter()
+        .find_map(|(_, tilemap_id)| tilemap_q.get(tilemap_id.0).ok())
+        .map(
+            |(map_size, grid_size, tile_size, map_type, anchor, transform)| {
+                TilemapLayout::new(
+                    *map_size,
+                    *grid_size,
+                    *tile_size,
+                    *map_type,
+                    *anchor,
+                    transform.compute_affine(),
+                )
+            },
+        );
+    *grid = ObstacleGrid::new(tiles, layout);
+}
+
+/// Adopt positions set directly on the [`Transform`] (spawning, loading a save, warps or
+/// wrapping at the map edge) as a teleport, without interpolating towards them.
+fn sync_teleported_movement(mut movement_query: Query<(&Transform, &mut FixedMovement)>) {
+    for (transform, mut movement) in &mut movement_query {
+        let position = transform.translation.xy();
+        if position != movement.rendered {
+            *movement = FixedMovement {
+                previous: position,
+                current: position,
+                rendered: position,
+                ..default()
+            };
         }
     }
 }
 
 fn apply_movement(
     time: Res<Time>,
-    mut movement_query: Query<(&MovementController, &mut Transform, &Aabb)>,
-    tilemap_q: Query<
-        (
-            &TilemapSize,
-            &TilemapGridSize,
-            &TilemapTileSize,
-            &TilemapType,
-            &TileStorage,
-            &Transform,
-            &TilemapAnchor,
-        ),
-        Without<MovementController>,
-    >,
-    obstacle_q: Query<&Obstacle>,
+    mut movement_query: Query<(
+        &MovementController,
+        &mut FixedMovement,
+        &Transform,
+        Has<Culled>,
+    )>,
+    obstacles: Res<ObstacleGrid>,
 ) {
-    for (controller, mut transform, aabb) in &mut movement_query {
-        let velocity = controller.max_speed * controller.intent;
-        let delta_movement = velocity.extend(0.0) * time.delta_secs();
-        let future_position =
-            transform.translation + delta_movement + Vec3::from(aabb.half_extents);
-
-        for (map_size, grid_size, tile_size, map_type, tile_storage, map_transform, anchor) in
-            tilemap_q.iter()
-        {
-            let future_in_map_pos: Vec2 = {
-                let future_pos = Vec4::from((future_position, 1.0));
-                let future_in_map_pos = map_transform.to_matrix().inverse() * future_pos;
-                future_in_map_pos.xy()
+    for (controller, mut movement, transform, is_culled) in &mut movement_query {
+        movement.previous = movement.current;
+        if is_culled {
+            continue;
+        }
+        let max_delta_velocity = controller.acceleration * time.delta_secs();
+        movement.velocity = movement
+            .velocity
+            .move_towards(controller.target_velocity(), max_delta_velocity);
+        if movement.velocity == Vec2::ZERO {
+            continue;
+        }
+        let delta_movement = movement.velocity * time.delta_secs();
+        let footprint = character_footprint(transform.scale.xy());
+        if obstacles.is_move_blocked(movement.current, footprint, delta_movement) {
+            // Bumping into something stops the character dead.
+            movement.velocity = Vec2::ZERO;
+            continue;
+        }
+        movement.current += delta_movement;
+    }
+}
+
+/// Place each character between its last two simulation steps, by how far the
+/// frame has progressed towards the next one.
+fn interpolate_movement(
+    fixed_time: Res<Time<Fixed>>,
+    mut movement_query: Query<(&mut Transform, &mut FixedMovement)>,
+) {
+    let alpha = fixed_time.overstep_fraction();
+    for (mut transform, mut movement) in &mut movement_query {
+        let position = movement.previous.lerp(movement.current, alpha);
+        transform.translation = position.extend(transform.translation.z);
+        movement.rendered = position;
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::time::Duration;
+
+    use bevy::{ecs::system::RunSystemOnce, math::Affine3A};
+
+    use super::*;
+    use crate::constants::{PLAYER_SCALE, TILE_SCALE, TILE_SIZE};
+
+    /// Fixed steps a second, as in Bevy's default fixed timestep.
+    pub(crate) const STEPS_PER_SEC: f32 = 64.0;
+
+    /// A 7×3 map centered on the origin with `obstacles`, for a game whose
+    /// [`TILE_SCALE`] is `tile_scale`.
+    pub(crate) fn grid(
+        tile_scale: f32,
+        obstacles: impl IntoIterator<Item = TilePos>,
+    ) -> ObstacleGrid {
+        let tile = TILE_SIZE as f32;
+        let layout = TilemapLayout::new(
+            TilemapSize { x: 7, y: 3 },
+            TilemapGridSize { x: tile, y: tile },
+            TilemapTileSize { x: tile, y: tile },
+            TilemapType::Square,
+            TilemapAnchor::Center,
+            Affine3A::from_scale(Vec3::new(tile_scale, tile_scale, 1.0)),
+        );
+        ObstacleGrid::new(obstacles.into_iter().collect(), Some(layout))
+    }
+
+    /// Walk `path` from `position` in fixed steps of `step`, asserting that `footprint` never
+    /// runs into an obstacle on `grid`, until every waypoint is reached.
+    pub(crate) fn walk_route(
+        grid: &ObstacleGrid,
+        mut position: Vec2,
+        footprint: Rect,
+        mut path: Waypoints,
+        step: f32,
+    ) {
+        for _ in 0..10_000 {
+            let Some(waypoint) = path.next(position, step) else {
+                return;
             };
-            if let Some(future_tile_pos) = TilePos::from_world_pos(
-                &future_in_map_pos,
-                map_size,
-                grid_size,
-                tile_size,
-                map_type,
-                anchor,
-            ) && let Some(tile_entity) = tile_storage.get(&future_tile_pos)
-            {
-                if obstacle_q.get(tile_entity).is_ok() {
-                    println!("Collision detected at tile position: {:?}", future_tile_pos);
-                }
-                //return;
-            }
+            let delta = (waypoint - position).normalize_or_zero() * step;
+            assert!(
+                !grid.is_move_blocked(position, footprint, delta),
+                "Stuck at {position} on the way to {waypoint}"
+            );
+            position += delta;
+        }
+        panic!("The route was never walked to its end");
+    }
+
+    /// The character footprint with [`TILE_SCALE`] set to `tile_scale`.
+    fn footprint(tile_scale: f32) -> Rect {
+        character_footprint(Vec2::splat(PLAYER_SCALE / TILE_SCALE * tile_scale))
+    }
+
+    /// Fixed steps it takes a character starting on the middle tile to walk into a wall two
+    /// tiles to its right at full speed, with [`TILE_SCALE`] set to `tile_scale`.
+    fn steps_to_wall(tile_scale: f32) -> usize {
+        let grid = grid(tile_scale, (0..3).map(|y| TilePos { x: 5, y }));
+        let footprint = footprint(tile_scale);
+        let step = Vec2::X * PLAYER_MAX_SPEED / TILE_SCALE * tile_scale / STEPS_PER_SEC;
+        let mut position = Vec2::ZERO;
+        let mut steps = 0;
+        while !grid.is_move_blocked(position, footprint, step) {
+            position += step;
+            steps += 1;
+            assert!(steps < 1000, "Never reached the wall");
+        }
+        steps
+    }
+
+    #[test]
+    fn collision_and_speed_follow_the_tile_scale() {
+        let expected = steps_to_wall(TILE_SCALE);
+        assert!(expected > 0);
+        for tile_scale in [1.0, 2.0, 4.5, 6.0] {
+            assert_eq!(
+                steps_to_wall(tile_scale),
+                expected,
+                "TILE_SCALE {tile_scale}"
+            );
         }
-        if controller.intent.length_squared() > 0.0 {
-            transform.translation += delta_movement;
+    }
+
+    #[test]
+    fn characters_fit_through_one_tile_gaps() {
+        for tile_scale in [1.0, 2.0, TILE_SCALE, 6.0] {
+            // Obstacles along the top and bottom rows leave a corridor one tile tall.
+            let walls = (0..7).flat_map(|x| [TilePos { x, y: 0 }, TilePos { x, y: 2 }]);
+            let grid = grid(tile_scale, walls);
+            let footprint = footprint(tile_scale);
+            // With the footprint lined up with the corridor, both of its ends are free.
+            let start = Vec2::new(-3.0, 0.0) * TILE_SIZE as f32 * tile_scale - footprint.center();
+            let delta = Vec2::X * 6.0 * TILE_SIZE as f32 * tile_scale;
+            assert!(
+                !grid.is_move_blocked(start, footprint, Vec2::ZERO)
+                    && !grid.is_move_blocked(start, footprint, delta),
+                "TILE_SCALE {tile_scale}"
+            );
+            // Stepping a whole tile up or down runs into a wall.
+            let tile_up = Vec2::Y * TILE_SIZE as f32 * tile_scale;
+            assert!(grid.is_move_blocked(start, footprint, tile_up));
+            assert!(grid.is_move_blocked(start, footprint, -tile_up));
         }
     }
+
+    /// How far a character with `intent` moves in a fixed step once it's up to speed.
+    fn step_length(intent: Vec2) -> f32 {
+        let mut world = World::new();
+        world.init_resource::<ObstacleGrid>();
+        let mut time = Time::<()>::default();
+        time.advance_by(Duration::from_secs_f32(1.0 / STEPS_PER_SEC));
+        world.insert_resource(time);
+        let character = world
+            .spawn((
+                MovementController {
+                    intent,
+                    ..default()
+                },
+                Transform::default(),
+            ))
+            .id();
+        // A second is plenty to reach full speed.
+        for _ in 0..STEPS_PER_SEC as usize {
+            world
+                .run_system_once(apply_movement)
+                .expect("Movement should run");
+        }
+        let movement = world
+            .get::<FixedMovement>(character)
+            .expect("The character should have moved");
+        movement.current.distance(movement.previous)
+    }
+
+    #[test]
+    fn diagonal_input_moves_as_fast_as_cardinal_input() {
+        let cardinal = step_length(Vec2::X);
+        let diagonal = step_length(Vec2::new(1.0, 1.0));
+        assert!(cardinal > 0.0);
+        assert!(
+            (diagonal - cardinal).abs() < 1e-3,
+            "diagonal {diagonal}, cardinal {cardinal}"
+        );
+    }
 }
@@ -0,0 +1,136 @@
+//! Toasts: short messages stacked in the corner of the screen that fade out on their own,
+//! for things worth telling the player like picking something up or running out of seeds.
+
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, ui::Val::*};
+
+use crate::{
+    AppSystems,
+    states::{GameState, GameplayState},
+    theme::{palette::*, widget},
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<Notifications>();
+    app.register_type::<Toast>();
+    app.init_resource::<Notifications>();
+    app.add_message::<NotifyEvent>();
+    app.add_systems(OnEnter(GameState::Gameplay), spawn_toast_stack);
+    app.add_systems(OnEnter(GameState::Title), clear_notifications);
+    app.add_systems(OnEnter(GameState::GameOver), clear_notifications);
+    app.add_systems(
+        Update,
+        (
+            tick_toasts.in_set(AppSystems::TickTimers),
+            (queue_notifications, show_toasts)
+                .chain()
+                .in_set(AppSystems::PostUpdate),
+        )
+            .run_if(in_state(GameplayState::Running)),
+    );
+}
+
+/// Most toasts on screen at once. Further messages wait their turn.
+const MAX_TOASTS: usize = 4;
+const TOAST_SECS: f32 = 3.0;
+/// How long before it disappears a toast starts fading out.
+const TOAST_FADE_SECS: f32 = 0.5;
+
+/// Show a message to the player as a toast.
+#[derive(Message, Debug, Clone)]
+pub struct NotifyEvent(pub String);
+
+/// Messages waiting for room in the toast stack, oldest first.
+#[derive(Resource, Debug, Default, Reflect)]
+#[reflect(Resource)]
+pub struct Notifications {
+    pending: VecDeque<String>,
+}
+
+/// A toast on screen, despawned once the timer finishes.
+#[derive(Component, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct Toast(pub Timer);
+
+impl Default for Toast {
+    fn default() -> Self {
+        Self(Timer::from_seconds(TOAST_SECS, TimerMode::Once))
+    }
+}
+
+/// The column in the top right corner that toasts are stacked in, oldest on top.
+#[derive(Component)]
+struct ToastStack;
+
+fn spawn_toast_stack(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Toast Stack"),
+        ToastStack,
+        Node {
+            position_type: PositionType::Absolute,
+            top: Px(10.0),
+            right: Px(10.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::FlexEnd,
+            row_gap: Px(6.0),
+            ..default()
+        },
+        Pickable::IGNORE,
+        DespawnOnExit(GameState::Gameplay),
+    ));
+}
+
+/// Messages still waiting when the session ends aren't news in the next one.
+fn clear_notifications(mut notifications: ResMut<Notifications>) {
+    notifications.pending.clear();
+}
+
+fn queue_notifications(
+    mut notify_events: MessageReader<NotifyEvent>,
+    mut notifications: ResMut<Notifications>,
+) {
+    notifications
+        .pending
+        .extend(notify_events.read().map(|event| event.0.clone()));
+}
+
+/// Move waiting messages into the stack while there's room.
+fn show_toasts(
+    mut commands: Commands,
+    mut notifications: ResMut<Notifications>,
+    stack: Single<Entity, With<ToastStack>>,
+    toast_q: Query<(), With<Toast>>,
+) {
+    let room = MAX_TOASTS.saturating_sub(toast_q.iter().count());
+    for _ in 0..room {
+        let Some(message) = notifications.pending.pop_front() else {
+            break;
+        };
+        commands
+            .entity(*stack)
+            .with_child((widget::toast(message), Toast::default()));
+    }
+}
+
+fn tick_toasts(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut toast_q: Query<(Entity, &mut Toast, &mut BackgroundColor, &Children)>,
+    mut text_q: Query<&mut TextColor>,
+) {
+    for (entity, mut toast, mut background, children) in &mut toast_q {
+        toast.0.tick(time.delta());
+        if toast.0.is_finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        let opacity = (toast.0.remaining_secs() / TOAST_FADE_SECS).min(1.0);
+        background.0 = TOAST_BACKGROUND.with_alpha(TOAST_BACKGROUND.alpha() * opacity);
+        for child in children {
+            if let Ok(mut text_color) = text_q.get_mut(*child) {
+                text_color.0 = TOAST_TEXT.with_alpha(opacity);
+            }
+        }
+    }
+}
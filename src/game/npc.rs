@@ -0,0 +1,167 @@
+//! Friendly characters that wander around the map and can be talked to.
+//!
+//! An NPC is a point object on one of the map's object layers with a `dialogue` property
+//! naming what it says. The object's name is the NPC's name.
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    AppSystems,
+    constants::*,
+    game::{
+        animation::Facing,
+        dialogue::{DialogueState, advance_dialogue, dialogue_open, dialogue_pages},
+        enemy::RoamTimer,
+        interaction::{INTERACT_KEY, Interactable, in_front},
+        movement::MovementController,
+        player::{Player, PlayerAssets},
+        rng::GameRng,
+        y_sort::YSort,
+    },
+    states::{DestroyOnEnter, GameState, GameplayState, VisibleInState},
+    world::tiledhelper::{TiledMap, TiledMapHandle, process_loaded_maps},
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<Npc>();
+    app.add_systems(
+        Update,
+        (
+            (respawn_edited_npcs, spawn_map_npcs)
+                .chain()
+                .after(process_loaded_maps)
+                .in_set(AppSystems::PreUpdate),
+            talk_to_npcs
+                .run_if(not(dialogue_open))
+                .after(advance_dialogue)
+                .in_set(AppSystems::RecordInput),
+        )
+            .run_if(in_state(GameplayState::Running)),
+    );
+}
+
+/// NPCs stroll at a fraction of [`PLAYER_MAX_SPEED`].
+const NPC_SPEED_MULTIPLIER: f32 = 1.0 / 14.0;
+const NPC_ROAM_INTERVAL_SECS: std::ops::RangeInclusive<f32> = 2.0..=4.0;
+/// Tint that tells NPCs apart from the player, who shares their sprite sheet.
+const NPC_COLOR: Color = Color::srgb(0.75, 0.85, 1.0);
+
+/// A friendly character.
+#[derive(Component, Debug, Clone, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct Npc {
+    pub name: String,
+    /// What the NPC says when talked to.
+    pub dialogue_id: String,
+}
+
+/// Marks a map whose NPCs have been spawned.
+#[derive(Component)]
+struct NpcsSpawned;
+
+pub fn npc(
+    player_assets: &PlayerAssets,
+    npc: Npc,
+    pos: Vec2,
+    roam_timer: RoamTimer,
+) -> impl Bundle {
+    (
+        Name::new(format!("NPC {}", npc.name)),
+        npc,
+        Sprite {
+            image: player_assets.player.clone(),
+            texture_atlas: Some(TextureAtlas {
+                layout: player_assets.layout.clone(),
+                index: 0,
+            }),
+            color: NPC_COLOR,
+            ..default()
+        },
+        Transform::from_translation(pos.extend(PLAYER_Z))
+            .with_scale(Vec2::splat(PLAYER_SCALE).extend(1.0)),
+        MovementController {
+            max_speed: PLAYER_MAX_SPEED * NPC_SPEED_MULTIPLIER,
+            ..default()
+        },
+        roam_timer,
+        Interactable::new("Talk"),
+        YSort,
+        VisibleInState(vec![GameState::Gameplay]),
+        DestroyOnEnter(vec![GameState::Title, GameState::GameOver]),
+    )
+}
+
+/// Respawn the NPCs of a map edited while the game runs, so they follow the edit.
+fn respawn_edited_npcs(
+    mut commands: Commands,
+    mut map_events: MessageReader<AssetEvent<TiledMap>>,
+    map_q: Query<(Entity, &TiledMapHandle), With<NpcsSpawned>>,
+    npc_q: Query<Entity, With<Npc>>,
+) {
+    let modified: Vec<_> = map_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+    for (map_entity, handle) in &map_q {
+        if !modified.contains(&handle.0.id()) {
+            continue;
+        }
+        for entity in &npc_q {
+            commands.entity(entity).despawn();
+        }
+        commands.entity(map_entity).remove::<NpcsSpawned>();
+    }
+}
+
+/// Spawn the NPCs of a map once it has loaded.
+fn spawn_map_npcs(
+    mut commands: Commands,
+    maps: Res<Assets<TiledMap>>,
+    map_q: Query<(Entity, &TiledMapHandle), Without<NpcsSpawned>>,
+    player_assets: Res<PlayerAssets>,
+    mut rng: ResMut<GameRng>,
+) {
+    for (map_entity, handle) in &map_q {
+        let Some(tiled_map) = maps.get(&handle.0) else {
+            continue;
+        };
+        for object in tiled_map.objects() {
+            let Some(tiled::PropertyValue::StringValue(dialogue_id)) =
+                object.properties.get("dialogue")
+            else {
+                continue;
+            };
+            let npc_data = Npc {
+                name: object.name.clone(),
+                dialogue_id: dialogue_id.clone(),
+            };
+            let position = tiled_map.to_world(Vec2::new(object.x, object.y));
+            let roam_timer = RoamTimer::from_seconds(rng.0.random_range(NPC_ROAM_INTERVAL_SECS));
+            commands.spawn(npc(&player_assets, npc_data, position, roam_timer));
+        }
+        commands.entity(map_entity).insert(NpcsSpawned);
+    }
+}
+
+/// Start a conversation with the NPC the player is facing.
+pub(super) fn talk_to_npcs(
+    input: Res<ButtonInput<KeyCode>>,
+    player_q: Query<(&GlobalTransform, &Facing), With<Player>>,
+    npc_q: Query<(&Npc, &GlobalTransform)>,
+    mut dialogue: ResMut<DialogueState>,
+) {
+    if !input.just_pressed(INTERACT_KEY) {
+        return;
+    }
+    let Ok((player_transform, facing)) = player_q.single() else {
+        return;
+    };
+    let Some(npc) = in_front(player_transform.translation().xy(), *facing, npc_q) else {
+        return;
+    };
+    dialogue.start(npc.name.clone(), dialogue_pages(&npc.dialogue_id));
+}
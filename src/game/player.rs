@@ -1,6 +1,7 @@
 //! Player-specific behavior.
 
 use bevy::{
+    audio::Volume,
     image::{ImageLoaderSettings, ImageSampler},
     prelude::*,
     sprite::Anchor,
@@ -9,20 +10,44 @@ use bevy::{
 use crate::{
     AppSystems,
     asset_tracking::LoadResource,
+    audio::{SoundEffect, sound_effect},
     constants::{GRID_SIZE_X, GRID_SIZE_Y},
-    game::{animation::PlayerAnimation, movement::MovementController},
-    states::GameState,
+    game::{
+        animation::PlayerAnimation,
+        movement::{
+            FixedMovement, MovementController, ObstacleGrid, Waypoints, character_footprint,
+        },
+    },
+    states::GameplayState,
+    world::tiledhelper::TileType,
 };
 
-use crate::constants::{PLAYER_MAX_SPEED, PLAYER_SCALE, PLAYER_Z};
+use crate::constants::{
+    PLAYER_ACCELERATION, PLAYER_MAX_HEALTH, PLAYER_MAX_SPEED, PLAYER_MAX_STAMINA, PLAYER_SCALE,
+    PLAYER_STAMINA_REGEN, PLAYER_Z,
+};
 
 use super::{
-    animation::{ActionType, PlayerActionState},
-    camera::WithinBounds,
+    animation::{ActionConfig, ActionType, Facing, PlayerActionState},
+    camera::{CursorPos, WithinBounds},
+    chest::chest_open,
+    combat::Health,
+    crops::SelectedSeed,
+    dialogue::dialogue_open,
+    inventory::ItemKind,
+    stamina::Stamina,
+    y_sort::YSort,
 };
 
 pub(super) fn plugin(app: &mut App) {
     app.register_type::<Player>();
+    app.register_type::<SelectedTool>();
+    app.init_resource::<SelectedTool>();
+    app.register_type::<SprintConfig>();
+    app.init_resource::<SprintConfig>();
+    app.register_type::<ClickToMove>();
+    app.init_resource::<ClickToMove>();
+    app.register_type::<ClickPath>();
 
     app.register_type::<PlayerAssets>();
     app.load_resource::<PlayerAssets>();
@@ -30,23 +55,27 @@ pub(super) fn plugin(app: &mut App) {
     // Record directional input as movement controls.
     app.add_systems(
         Update,
-        (record_player_directional_input, record_player_actions_input)
+        (
+            record_player_directional_input,
+            record_move_click.run_if(resource_equals(ClickToMove(true))),
+            follow_click_path,
+            record_sprint_input,
+            record_tool_selection,
+            record_player_actions_input,
+            record_seed_selection,
+        )
             .chain()
-            .run_if(in_state(GameState::Gameplay))
+            .run_if(
+                in_state(GameplayState::Running)
+                    .and(not(dialogue_open))
+                    .and(not(chest_open)),
+            )
             .in_set(AppSystems::RecordInput),
     );
 }
 
 /// The player character.
-pub fn player(
-    player_assets: &PlayerAssets,
-    texture_atlas_layouts: &mut Assets<TextureAtlasLayout>,
-) -> impl Bundle {
-    // A texture atlas is a way to split a single image into a grid of related images.
-    // You can learn more in this example: https://github.com/bevyengine/bevy/blob/latest/examples/2d/texture_atlas.rs
-    let layout =
-        TextureAtlasLayout::from_grid(UVec2::new(GRID_SIZE_X, GRID_SIZE_Y), 16, 6, None, None);
-    let texture_atlas_layout = texture_atlas_layouts.add(layout);
+pub fn player(player_assets: &PlayerAssets) -> impl Bundle {
     let player_animation = PlayerAnimation::new();
 
     (
@@ -55,7 +84,7 @@ pub fn player(
         Sprite {
             image: player_assets.player.clone(),
             texture_atlas: Some(TextureAtlas {
-                layout: texture_atlas_layout,
+                layout: player_assets.layout.clone(),
                 index: player_animation.get_atlas_index(),
             }),
             ..default()
@@ -65,11 +94,16 @@ pub fn player(
             .with_scale(Vec2::splat(PLAYER_SCALE).extend(1.0)),
         MovementController {
             max_speed: PLAYER_MAX_SPEED,
+            acceleration: PLAYER_ACCELERATION,
             ..default()
         },
         WithinBounds,
+        Health::new(PLAYER_MAX_HEALTH),
+        Stamina::new(PLAYER_MAX_STAMINA, PLAYER_STAMINA_REGEN),
         player_animation,
+        Facing::default(),
         PlayerActionState::default(),
+        YSort,
     )
 }
 
@@ -103,42 +137,263 @@ fn record_player_directional_input(
     }
 }
 
+/// Whether clicking a tile walks the player there, as an alternative to the keyboard.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub struct ClickToMove(pub bool);
+
+impl Default for ClickToMove {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+/// Clicked to walk to the tile under the cursor.
+const MOVE_BUTTON: MouseButton = MouseButton::Left;
+/// The positions the player is walking through after a click.
+#[derive(Component, Debug, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct ClickPath(Waypoints);
+
+/// Plan a walk around the obstacles to the clicked tile, from where the player is in the
+/// simulation that checks their moves against the obstacles.
+fn record_move_click(
+    mut commands: Commands,
+    mouse: Res<ButtonInput<MouseButton>>,
+    cursor_pos: Res<CursorPos>,
+    grid: Res<ObstacleGrid>,
+    player_assets: Res<PlayerAssets>,
+    player_query: Query<(Entity, &Transform, &FixedMovement), With<Player>>,
+) {
+    if !mouse.just_pressed(MOVE_BUTTON) {
+        return;
+    }
+    for (entity, transform, movement) in &player_query {
+        let footprint = character_footprint(transform.scale.xy());
+        let Some(path) = grid.route(movement.position(), footprint, cursor_pos.0) else {
+            commands.spawn(sound_effect(player_assets.denied.clone()));
+            continue;
+        };
+        commands.entity(entity).insert(ClickPath(path));
+    }
+}
+
+/// Steer the player along their [`ClickPath`]. Any directional input takes over again.
+fn follow_click_path(
+    mut commands: Commands,
+    fixed_time: Res<Time<Fixed>>,
+    mut player_query: Query<
+        (
+            Entity,
+            &FixedMovement,
+            &mut MovementController,
+            &mut ClickPath,
+        ),
+        With<Player>,
+    >,
+) {
+    for (entity, movement, mut controller, mut path) in &mut player_query {
+        if !controller.is_still() {
+            commands.entity(entity).remove::<ClickPath>();
+            continue;
+        }
+        let position = movement.position();
+        let step = controller.max_speed * fixed_time.timestep().as_secs_f32();
+        let Some(waypoint) = path.0.next(position, step) else {
+            commands.entity(entity).remove::<ClickPath>();
+            continue;
+        };
+        controller.intent = (waypoint - position).normalize_or_zero();
+    }
+}
+
+/// Held to run.
+const SPRINT_KEYS: [KeyCode; 2] = [KeyCode::ShiftLeft, KeyCode::ShiftRight];
+
+/// How running works.
+#[derive(Resource, Debug, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct SprintConfig {
+    /// How many times faster than [`PLAYER_MAX_SPEED`] the player runs.
+    pub speed_multiplier: f32,
+    /// [`Stamina`] spent every second of running. The player can't run on an empty bar.
+    pub stamina_per_sec: f32,
+}
+
+impl Default for SprintConfig {
+    fn default() -> Self {
+        Self {
+            speed_multiplier: 1.6,
+            stamina_per_sec: 20.0,
+        }
+    }
+}
+
+/// Let the player run while a sprint key is held and they have the stamina for it.
+fn record_sprint_input(
+    time: Res<Time>,
+    input: Res<ButtonInput<KeyCode>>,
+    sprint_config: Res<SprintConfig>,
+    mut player_query: Query<(&mut MovementController, Option<&mut Stamina>), With<Player>>,
+) {
+    let held = input.any_pressed(SPRINT_KEYS);
+    for (mut controller, stamina) in &mut player_query {
+        let cost = sprint_config.stamina_per_sec * time.delta_secs();
+        let sprinting = held
+            && !controller.is_still()
+            && stamina.is_none_or(|mut stamina| stamina.try_spend(cost));
+        controller.max_speed = if sprinting {
+            PLAYER_MAX_SPEED * sprint_config.speed_multiplier
+        } else {
+            PLAYER_MAX_SPEED
+        };
+    }
+}
+
 fn record_player_actions_input(
+    mut commands: Commands,
     input: Res<ButtonInput<KeyCode>>,
-    mut player_query: Query<(&mut PlayerActionState, &MovementController)>,
+    selected_tool: Res<SelectedTool>,
+    action_config: Res<ActionConfig>,
+    player_assets: Res<PlayerAssets>,
+    mut player_query: Query<(
+        &mut PlayerActionState,
+        &MovementController,
+        Option<&mut Stamina>,
+    )>,
 ) {
-    let (mut action_state, controller) = player_query.single_mut().expect("Player should exist!");
+    let Ok((mut action_state, controller, stamina)) = player_query.single_mut() else {
+        return;
+    };
+
+    // Moving cancels whatever was queued up
+    if !controller.is_still() {
+        action_state.queued_action = None;
+        return;
+    }
+
+    if !input.just_pressed(USE_TOOL_KEY) {
+        return;
+    }
+    let action = selected_tool.0;
 
     if action_state.current_action.is_none() {
-        // Only allow starting actions when not moving
-        if controller.intent == Vec2::ZERO {
-            if input.just_pressed(KeyCode::KeyE) {
-                action_state.current_action = Some(ActionType::Watering);
-                action_state.action_progress = 0.0;
-            } else if input.just_pressed(KeyCode::KeyQ) {
-                action_state.current_action = Some(ActionType::Hoeing);
-                action_state.action_progress = 0.0;
-            } else if input.just_pressed(KeyCode::KeyF) {
-                action_state.current_action = Some(ActionType::Chopping);
-                action_state.action_progress = 0.0;
-            }
+        let cost = action_config.stamina_cost(action);
+        if !stamina.is_none_or(|mut stamina| stamina.try_spend(cost)) {
+            commands.spawn(sound_effect(player_assets.denied.clone()));
+            return;
+        }
+        action_state.current_action = Some(action);
+        action_state.action_progress = 0.0;
+    } else {
+        // Remember the latest request so it starts right after the current action
+        action_state.queued_action = Some(action);
+    }
+}
+
+/// Performs the [`SelectedTool`]'s action.
+const USE_TOOL_KEY: KeyCode = KeyCode::KeyE;
+/// Number keys selecting a tool from the hotbar, in the order of [`ActionType::ALL`].
+const TOOL_KEYS: [KeyCode; ActionType::ALL.len()] =
+    [KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3];
+/// Switches to the next seed in [`ItemKind::SEEDS`].
+const NEXT_SEED_KEY: KeyCode = KeyCode::Tab;
+
+/// The hotbar tool used by [`USE_TOOL_KEY`].
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub struct SelectedTool(pub ActionType);
+
+impl Default for SelectedTool {
+    fn default() -> Self {
+        Self(ActionType::ALL[0])
+    }
+}
+
+fn record_tool_selection(
+    input: Res<ButtonInput<KeyCode>>,
+    mut selected_tool: ResMut<SelectedTool>,
+) {
+    for (key, tool) in TOOL_KEYS.into_iter().zip(ActionType::ALL) {
+        if input.just_pressed(key) {
+            selected_tool.set_if_neq(SelectedTool(tool));
         }
     }
 }
 
+fn record_seed_selection(
+    input: Res<ButtonInput<KeyCode>>,
+    mut selected_seed: ResMut<SelectedSeed>,
+) {
+    if !input.just_pressed(NEXT_SEED_KEY) {
+        return;
+    }
+    let index = ItemKind::SEEDS
+        .iter()
+        .position(|&seed| seed == selected_seed.0)
+        .map_or(0, |index| (index + 1) % ItemKind::SEEDS.len());
+    selected_seed.0 = ItemKind::SEEDS[index];
+}
+
 #[derive(Resource, Asset, Clone, Reflect)]
 #[reflect(Resource)]
 pub struct PlayerAssets {
     #[dependency]
-    player: Handle<Image>,
+    pub player: Handle<Image>,
+    /// How the player sheet splits into frames. Built once and shared by every spawn.
+    pub layout: Handle<TextureAtlasLayout>,
+    /// Step samples, played as [`step_sound_effect`] makes them sound on the ground.
     #[dependency]
     pub steps: Vec<Handle<AudioSource>>,
+    /// Played when the player tries to act without enough stamina.
+    #[dependency]
+    pub denied: Handle<AudioSource>,
+}
+
+/// A step sample as it sounds on `tile_type`. The same samples play on every surface, but
+/// soft ground like grass, dirt and sand plays them slower and quieter, which lowers their
+/// pitch into a dull thud, and hard ground like rock plays them faster, so they click.
+/// Water and unknown ground play them as they are.
+pub fn step_sound_effect(handle: Handle<AudioSource>, tile_type: Option<&TileType>) -> impl Bundle {
+    let (speed, volume) = match tile_type {
+        Some(TileType::Grass | TileType::Dirt | TileType::Sand) => (0.75, 0.5),
+        Some(TileType::Rock) => (1.35, 1.0),
+        Some(TileType::Water) | None => (1.0, 1.0),
+    };
+    (
+        AudioPlayer(handle),
+        PlaybackSettings::DESPAWN
+            .with_speed(speed)
+            .with_volume(Volume::Linear(volume)),
+        SoundEffect,
+    )
+}
+
+#[cfg(test)]
+impl PlayerAssets {
+    /// Default handles, for spawning a player without loading anything.
+    pub fn placeholder() -> Self {
+        Self {
+            player: default(),
+            layout: default(),
+            steps: default(),
+            denied: default(),
+        }
+    }
 }
 
 impl FromWorld for PlayerAssets {
     fn from_world(world: &mut World) -> Self {
+        // A texture atlas is a way to split a single image into a grid of related images.
+        // You can learn more in this example: https://github.com/bevyengine/bevy/blob/latest/examples/2d/texture_atlas.rs
+        let layout =
+            TextureAtlasLayout::from_grid(UVec2::new(GRID_SIZE_X, GRID_SIZE_Y), 16, 6, None, None);
+        let layout = world
+            .resource_mut::<Assets<TextureAtlasLayout>>()
+            .add(layout);
         let assets = world.resource::<AssetServer>();
         Self {
+            layout,
             player: assets.load_with_settings(
                 "images/character.png",
                 |settings: &mut ImageLoaderSettings| {
@@ -152,6 +407,44 @@ impl FromWorld for PlayerAssets {
                 assets.load("audio/sound_effects/step3.ogg"),
                 assets.load("audio/sound_effects/step4.ogg"),
             ],
+            denied: assets.load("audio/sound_effects/button_press.ogg"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs_tilemap::prelude::*;
+
+    use super::*;
+    use crate::{
+        constants::TILE_SCALE,
+        game::movement::tests::{STEPS_PER_SEC, grid, walk_route},
+    };
+
+    #[test]
+    fn click_paths_run_along_obstacles() {
+        // The path runs right above a row of obstacles, the one the footprint reaches into.
+        let grid = grid(TILE_SCALE, (0..7).map(|x| TilePos { x, y: 0 }));
+        let layout = grid.layout().expect("The grid should have a map");
+        let footprint = character_footprint(Vec2::splat(PLAYER_SCALE));
+        let position = layout.tile_center(&TilePos { x: 0, y: 1 }) - footprint.center();
+        let goal = layout.tile_center(&TilePos { x: 6, y: 1 });
+        let path = grid
+            .route(position, footprint, goal)
+            .expect("The goal should be reachable");
+        assert_eq!(path.0.len(), 6);
+
+        // Walking, and running, whose steps are longer than the waypoint radius.
+        let sprint_speed = PLAYER_MAX_SPEED * SprintConfig::default().speed_multiplier;
+        for speed in [PLAYER_MAX_SPEED, sprint_speed] {
+            walk_route(
+                &grid,
+                position,
+                footprint,
+                path.clone(),
+                speed / STEPS_PER_SEC,
+            );
         }
     }
 }
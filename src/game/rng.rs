@@ -0,0 +1,59 @@
+//! A seeded random number generator shared by gameplay systems, so that
+//! randomized behavior can be reproduced for debugging.
+
+use bevy::prelude::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::{game::level::LevelSpawnSystems, states::GameState};
+
+/// Seed used when [`SEED_ENV_VAR`] isn't set.
+const DEFAULT_SEED: u64 = 0xF15F;
+/// Environment variable that overrides [`DEFAULT_SEED`], e.g. `FISH_SEED=42`.
+const SEED_ENV_VAR: &str = "FISH_SEED";
+
+pub(super) fn plugin(app: &mut App) {
+    let seed = startup_seed();
+    app.insert_resource(GameSeed(seed));
+    app.insert_resource(GameRng::new(seed));
+    app.add_systems(
+        OnEnter(GameState::Gameplay),
+        reseed_for_new_level.in_set(LevelSpawnSystems),
+    );
+}
+
+/// The seed picked at startup.
+#[derive(Resource, Debug, Clone, Copy)]
+struct GameSeed(u64);
+
+/// The game's random number generator. Gameplay code should draw from this
+/// instead of `rand::random` or `rand::rng` so the same seed always produces
+/// the same behavior.
+#[derive(Resource)]
+pub struct GameRng(pub ChaCha8Rng);
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self(ChaCha8Rng::seed_from_u64(seed))
+    }
+
+    /// Restart the random sequence from `seed`.
+    pub fn reseed(&mut self, seed: u64) {
+        self.0 = ChaCha8Rng::seed_from_u64(seed);
+    }
+}
+
+fn startup_seed() -> u64 {
+    let seed = std::env::var(SEED_ENV_VAR)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_SEED);
+    info!("Seeding game RNG with {seed}");
+    seed
+}
+
+/// Start every fresh level from the startup seed, so a new game plays out the same way
+/// each time, not just the first one after launching.
+fn reseed_for_new_level(seed: Res<GameSeed>, mut rng: ResMut<GameRng>) {
+    rng.reseed(seed.0);
+}
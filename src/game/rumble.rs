@@ -0,0 +1,65 @@
+//! Controller rumble on impacts, following the same hits that shake the screen.
+
+use std::time::Duration;
+
+use bevy::{
+    input::gamepad::{GamepadRumbleIntensity, GamepadRumbleRequest},
+    prelude::*,
+};
+
+use crate::{AppSystems, game::camera::ScreenShake, states::GameplayState};
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<RumbleSettings>();
+    app.init_resource::<RumbleSettings>();
+    app.add_systems(
+        Update,
+        rumble_on_impacts
+            .run_if(in_state(GameplayState::Running))
+            .in_set(AppSystems::PostUpdate),
+    );
+}
+
+/// How hard connected gamepads rumble on impacts.
+#[derive(Resource, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Resource)]
+pub struct RumbleSettings {
+    /// Motor strength per unit of [`ScreenShake`] trauma, capped at full strength.
+    /// Zero turns rumble off.
+    pub intensity: f32,
+    pub duration_secs: f32,
+}
+
+impl Default for RumbleSettings {
+    fn default() -> Self {
+        Self {
+            intensity: 1.0,
+            duration_secs: 0.15,
+        }
+    }
+}
+
+fn rumble_on_impacts(
+    mut screen_shake: ResMut<ScreenShake>,
+    settings: Res<RumbleSettings>,
+    gamepad_q: Query<Entity, With<Gamepad>>,
+    mut rumble_requests: MessageWriter<GamepadRumbleRequest>,
+) {
+    // Always take the impacts, so ones from before a gamepad was connected don't pile up.
+    let impacts = screen_shake.take_impacts();
+    if impacts <= 0.0 || settings.intensity <= 0.0 {
+        return;
+    }
+    let strength = (impacts * settings.intensity).min(1.0);
+    for gamepad in &gamepad_q {
+        rumble_requests.write(GamepadRumbleRequest::Add {
+            gamepad,
+            // Converting a negative duration, set through reflection, would panic.
+            duration: Duration::from_secs_f32(settings.duration_secs.max(0.0)),
+            intensity: GamepadRumbleIntensity {
+                strong_motor: strength,
+                weak_motor: strength,
+            },
+        });
+    }
+}
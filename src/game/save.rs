@@ -0,0 +1,652 @@
+//! Saving the running game and restoring it later.
+//!
+//! Press F5 during gameplay (or use the pause menu) to save, and F9 to load.
+//! Saves are written as RON to a file next to the executable, or to
+//! LocalStorage on the web.
+
+use bevy::{ecs::system::SystemParam, input::common_conditions::input_just_pressed, prelude::*};
+use bevy_ecs_tilemap::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    AppSystems,
+    game::{
+        chest::{Chest, ChestContents, Storage},
+        combat::Health,
+        crops::{Crop, Farmland, Plot, SpawnedPlots},
+        day_cycle::GameTime,
+        enemy::{Enemy, EnemyAssets, EnemyKind, PendingEnemySpawn, enemy, random_roam_timer},
+        inventory::{Inventory, ItemKind, Pickup, pickup},
+        level::Level,
+        npc::Npc,
+        player::{ClickPath, Player},
+        rng::GameRng,
+    },
+    states::{GameState, GameplayState},
+    world::{
+        tiledhelper::{ChoppedTiles, TiledMapHandle, process_loaded_maps},
+        tilemap::CurrentMap,
+    },
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (
+            save_game.run_if(input_just_pressed(KeyCode::F5)),
+            load_game.run_if(input_just_pressed(KeyCode::F9)),
+        )
+            .run_if(in_state(GameplayState::Running))
+            .in_set(AppSystems::RecordInput),
+    );
+    app.add_systems(
+        Update,
+        apply_pending_load
+            .run_if(resource_exists::<PendingLoad>.and(in_state(GameplayState::Running)))
+            .in_set(AppSystems::PreUpdate)
+            // Chopped tiles and plots have to be known before the fresh map's tiles are processed.
+            .before(process_loaded_maps),
+    );
+}
+
+#[cfg(not(target_family = "wasm"))]
+const SAVE_PATH: &str = "savegame.ron";
+#[cfg(target_family = "wasm")]
+const SAVE_KEY: &str = "fish.savegame";
+
+/// Everything needed to restore a game in progress.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SaveData {
+    /// The [`CurrentMap`] the game was saved on.
+    map: String,
+    player: PlayerSave,
+    inventory: Vec<(ItemKind, u32)>,
+    enemies: Vec<EnemySave>,
+    pickups: Vec<PickupSave>,
+    /// Tile positions of the tiles chopped down so far, on any map.
+    chopped_tiles: Vec<ChoppedSave>,
+    /// Tilled tiles and what grows on them, on any map.
+    farmland: Vec<FarmSave>,
+    /// What's in every chest the player has used, on any map.
+    #[serde(default)]
+    chests: Vec<ChestSave>,
+    game_time: GameTimeSave,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct PlayerSave {
+    position: [f32; 2],
+    health: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct EnemySave {
+    kind: EnemyKind,
+    position: [f32; 2],
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct PickupSave {
+    kind: ItemKind,
+    amount: u32,
+    position: [f32; 2],
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct ChoppedSave {
+    map: String,
+    tiles: Vec<[u32; 2]>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct FarmSave {
+    map: String,
+    plots: Vec<([u32; 2], Plot)>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct ChestSave {
+    map: String,
+    id: u32,
+    items: Vec<(ItemKind, u32)>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct GameTimeSave {
+    seconds_of_day: f32,
+    day: u32,
+}
+
+#[derive(Debug, Error)]
+pub enum SaveError {
+    #[error("Could not access the save file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not serialize the save: {0}")]
+    Serialize(#[from] ron::Error),
+    #[error("Could not parse the save: {0}")]
+    Deserialize(#[from] ron::error::SpannedError),
+    #[cfg(target_family = "wasm")]
+    #[error("Could not access LocalStorage")]
+    Storage,
+    #[error("There is no save to load")]
+    Missing,
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn write_save(contents: &str) -> Result<(), SaveError> {
+    std::fs::write(SAVE_PATH, contents)?;
+    Ok(())
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn read_save() -> Result<String, SaveError> {
+    match std::fs::read_to_string(SAVE_PATH) {
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Err(SaveError::Missing),
+        result => Ok(result?),
+    }
+}
+
+#[cfg(target_family = "wasm")]
+fn local_storage() -> Result<web_sys::Storage, SaveError> {
+    web_sys::window()
+        .and_then(|window| window.local_storage().ok().flatten())
+        .ok_or(SaveError::Storage)
+}
+
+#[cfg(target_family = "wasm")]
+fn write_save(contents: &str) -> Result<(), SaveError> {
+    local_storage()?
+        .set_item(SAVE_KEY, contents)
+        .map_err(|_| SaveError::Storage)
+}
+
+#[cfg(target_family = "wasm")]
+fn read_save() -> Result<String, SaveError> {
+    local_storage()?
+        .get_item(SAVE_KEY)
+        .map_err(|_| SaveError::Storage)?
+        .ok_or(SaveError::Missing)
+}
+
+/// Whether there's a save to continue from.
+pub fn save_exists() -> bool {
+    read_save().is_ok()
+}
+
+/// Read access to the parts of the world that end up in a [`SaveData`].
+#[derive(SystemParam)]
+pub struct GameSnapshot<'w, 's> {
+    player_q: Query<'w, 's, (&'static Transform, &'static Health), With<Player>>,
+    enemy_q: Query<'w, 's, (&'static Transform, &'static EnemyKind), With<Enemy>>,
+    pickup_q: Query<'w, 's, (&'static Transform, &'static Pickup)>,
+    inventory: Res<'w, Inventory>,
+    chopped_tiles: Res<'w, ChoppedTiles>,
+    chest_contents: Res<'w, ChestContents>,
+    farmland: Res<'w, Farmland>,
+    plots: SpawnedPlots<'w, 's>,
+    game_time: Res<'w, GameTime>,
+    current_map: Res<'w, CurrentMap>,
+}
+
+impl GameSnapshot<'_, '_> {
+    fn capture(&self) -> Option<SaveData> {
+        let (player_transform, health) = self.player_q.single().ok()?;
+        let mut farmland = self.farmland.clone();
+        farmland.keep(&self.current_map.0, self.plots.plots());
+        Some(SaveData {
+            map: self.current_map.0.clone(),
+            player: PlayerSave {
+                position: player_transform.translation.xy().to_array(),
+                health: health.current,
+            },
+            inventory: ItemKind::ALL
+                .iter()
+                .map(|&kind| (kind, self.inventory.count(kind)))
+                .filter(|&(_, count)| count > 0)
+                .collect(),
+            enemies: self
+                .enemy_q
+                .iter()
+                .map(|(transform, &kind)| EnemySave {
+                    kind,
+                    position: transform.translation.xy().to_array(),
+                })
+                .collect(),
+            pickups: self
+                .pickup_q
+                .iter()
+                .map(|(transform, pickup)| PickupSave {
+                    kind: pickup.kind,
+                    amount: pickup.amount,
+                    position: transform.translation.xy().to_array(),
+                })
+                .collect(),
+            chopped_tiles: self
+                .chopped_tiles
+                .0
+                .iter()
+                .map(|(map, tiles)| ChoppedSave {
+                    map: map.clone(),
+                    tiles: tiles.iter().map(|pos| pos.to_array()).collect(),
+                })
+                .collect(),
+            farmland: farmland
+                .0
+                .into_iter()
+                .map(|(map, plots)| FarmSave {
+                    map,
+                    plots: plots
+                        .into_iter()
+                        .map(|(tile, plot)| (tile.to_array(), plot))
+                        .collect(),
+                })
+                .collect(),
+            chests: self
+                .chest_contents
+                .0
+                .iter()
+                .map(|((map, id), storage)| ChestSave {
+                    map: map.clone(),
+                    id: *id,
+                    items: ItemKind::ALL
+                        .iter()
+                        .map(|&kind| (kind, storage.count(kind)))
+                        .filter(|&(_, count)| count > 0)
+                        .collect(),
+                })
+                .collect(),
+            game_time: GameTimeSave {
+                seconds_of_day: self.game_time.seconds_of_day,
+                day: self.game_time.day,
+            },
+        })
+    }
+
+    /// Serialize the current game and write it out.
+    pub fn save(&self) {
+        let Some(data) = self.capture() else {
+            warn!("Nothing to save: there is no player");
+            return;
+        };
+        let result = ron::ser::to_string_pretty(&data, ron::ser::PrettyConfig::default())
+            .map_err(SaveError::from)
+            .and_then(|contents| write_save(&contents));
+        match result {
+            Ok(()) => info!("Game saved"),
+            Err(error) => error!("{error}"),
+        }
+    }
+}
+
+/// Tears down the running level so a saved one can take its place.
+#[derive(SystemParam)]
+pub struct LoadRequest<'w, 's> {
+    commands: Commands<'w, 's>,
+    world_q: Query<
+        'w,
+        's,
+        Entity,
+        Or<(
+            With<Level>,
+            With<Enemy>,
+            With<Pickup>,
+            With<Crop>,
+            With<Npc>,
+            With<Chest>,
+            With<TiledMapHandle>,
+            With<TileStorage>,
+            With<TilePos>,
+        )>,
+    >,
+    click_path_q: Query<'w, 's, Entity, With<ClickPath>>,
+    current_map: ResMut<'w, CurrentMap>,
+    next_screen: ResMut<'w, NextState<GameState>>,
+}
+
+impl LoadRequest<'_, '_> {
+    /// Read the save and rebuild the level from it. The level is torn down right away and
+    /// respawned on the way back into [`GameState::Gameplay`] through the loading screen,
+    /// then [`apply_pending_load`] restores the saved state.
+    pub fn load(&mut self) {
+        if let Some(data) = read_save_data() {
+            self.load_data(data);
+        }
+    }
+
+    fn load_data(&mut self, data: SaveData) {
+        self.queue_data(data);
+        // Setting `Gameplay` while already in it wouldn't run `OnEnter`, so nothing would
+        // respawn the level. Leaving it for the loading screen makes it a real transition.
+        self.next_screen.set(GameState::Loading);
+    }
+
+    /// Read the save and get it ready to be restored the next time the level is spawned,
+    /// leaving it to the caller to get there. Returns whether there was a save to read.
+    pub fn queue(&mut self) -> bool {
+        read_save_data().map(|data| self.queue_data(data)).is_some()
+    }
+
+    fn queue_data(&mut self, data: SaveData) {
+        // Whoever was walking somewhere in the old level stops, before it is torn down.
+        for entity in &self.click_path_q {
+            self.commands.entity(entity).remove::<ClickPath>();
+        }
+        for entity in &self.world_q {
+            self.commands.entity(entity).despawn();
+        }
+        // The level respawns the current map, so point it at the saved one first.
+        self.current_map.0 = data.map.clone();
+        self.commands.insert_resource(PendingLoad(data));
+    }
+}
+
+/// The save, if there is one and it can be read.
+fn read_save_data() -> Option<SaveData> {
+    match read_save().and_then(|contents| Ok(ron::from_str(&contents)?)) {
+        Ok(data) => Some(data),
+        Err(error) => {
+            error!("{error}");
+            None
+        }
+    }
+}
+
+/// A loaded save waiting for the fresh level to be spawned.
+#[derive(Resource)]
+struct PendingLoad(SaveData);
+
+fn save_game(snapshot: GameSnapshot) {
+    snapshot.save();
+}
+
+fn load_game(mut load_request: LoadRequest) {
+    load_request.load();
+}
+
+fn apply_pending_load(
+    mut commands: Commands,
+    pending_load: Res<PendingLoad>,
+    mut player_q: Query<(&mut Transform, &mut Health), With<Player>>,
+    enemy_q: Query<Entity, With<Enemy>>,
+    mut inventory: ResMut<Inventory>,
+    mut chopped_tiles: ResMut<ChoppedTiles>,
+    mut chest_contents: ResMut<ChestContents>,
+    mut farmland: ResMut<Farmland>,
+    mut game_time: ResMut<GameTime>,
+    mut rng: ResMut<GameRng>,
+    enemy_assets: Res<EnemyAssets>,
+) {
+    // Wait for the new level's player to exist.
+    let Ok((mut player_transform, mut health)) = player_q.single_mut() else {
+        return;
+    };
+    let data = &pending_load.0;
+
+    let position = Vec2::from_array(data.player.position);
+    player_transform.translation = position.extend(player_transform.translation.z);
+    health.current = data.player.health.min(health.max);
+
+    *inventory = Inventory::default();
+    for &(kind, amount) in &data.inventory {
+        inventory.add(kind, amount);
+    }
+
+    *chopped_tiles = ChoppedTiles::default();
+    for saved in &data.chopped_tiles {
+        for &[x, y] in &saved.tiles {
+            chopped_tiles.insert(&saved.map, TilePos { x, y });
+        }
+    }
+
+    // Tiles are tilled and planted from these as their map spawns them.
+    farmland.0 = data
+        .farmland
+        .iter()
+        .map(|saved| {
+            let plots = saved
+                .plots
+                .iter()
+                .map(|(tile, plot)| (UVec2::from_array(*tile), plot.clone()))
+                .collect();
+            (saved.map.clone(), plots)
+        })
+        .collect();
+
+    // Chests are filled from these as their map spawns them.
+    chest_contents.0 = data
+        .chests
+        .iter()
+        .map(|saved| {
+            let mut storage = Storage::default();
+            for &(kind, amount) in &saved.items {
+                storage.add(kind, amount);
+            }
+            ((saved.map.clone(), saved.id), storage)
+        })
+        .collect();
+
+    game_time.seconds_of_day = data.game_time.seconds_of_day;
+    game_time.day = data.game_time.day;
+
+    // Replace the freshly rolled enemies with the saved ones, and don't roll any more
+    // once the map is in.
+    for entity in &enemy_q {
+        commands.entity(entity).despawn();
+    }
+    commands.remove_resource::<PendingEnemySpawn>();
+    for (i, saved) in data.enemies.iter().enumerate() {
+        let roam_timer = random_roam_timer(saved.kind, &mut rng);
+        commands.spawn((
+            Name::new(format!("{} Enemy {i}", saved.kind.name())),
+            enemy(
+                &enemy_assets,
+                &Vec2::from_array(saved.position),
+                saved.kind,
+                roam_timer,
+            ),
+        ));
+    }
+    for saved in &data.pickups {
+        commands.spawn(pickup(
+            saved.kind,
+            saved.amount,
+            Vec2::from_array(saved.position),
+        ));
+    }
+
+    commands.remove_resource::<PendingLoad>();
+    info!("Game loaded");
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::system::RunSystemOnce, state::app::StatesPlugin};
+
+    use super::*;
+    use crate::{
+        asset_tracking::ResourceHandles,
+        game::{
+            animation,
+            camera::CursorPos,
+            chest::OpenChest,
+            crops::{self, SelectedSeed, Tilled},
+            dialogue::DialogueState,
+            level,
+            movement::ObstacleGrid,
+            player::{self, PlayerAssets},
+        },
+        states::AppState,
+        world::tiledhelper::TileType,
+    };
+
+    fn save_data() -> SaveData {
+        SaveData {
+            map: CurrentMap::default().0,
+            player: PlayerSave {
+                position: [32.0, -16.0],
+                health: 1.0,
+            },
+            inventory: Vec::new(),
+            enemies: Vec::new(),
+            pickups: Vec::new(),
+            chopped_tiles: Vec::new(),
+            farmland: Vec::new(),
+            chests: Vec::new(),
+            game_time: GameTimeSave {
+                seconds_of_day: 0.0,
+                day: 1,
+            },
+        }
+    }
+
+    fn app_in_gameplay() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin, AssetPlugin::default()));
+        app.configure_sets(
+            Update,
+            (
+                AppSystems::TickTimers,
+                AppSystems::RecordInput,
+                AppSystems::PreUpdate,
+                AppSystems::Update,
+                AppSystems::PostUpdate,
+            )
+                .chain(),
+        );
+        app.init_asset::<Image>();
+        app.init_asset::<AudioSource>();
+        app.init_asset::<TextureAtlasLayout>();
+        app.init_resource::<ResourceHandles>();
+        app.add_plugins((level::plugin, player::plugin, animation::plugin));
+        app.init_state::<GameState>();
+        app.add_computed_state::<AppState>();
+        app.add_sub_state::<GameplayState>();
+        app.init_resource::<CurrentMap>();
+        app.init_resource::<ButtonInput<KeyCode>>();
+        app.init_resource::<ButtonInput<MouseButton>>();
+        app.init_resource::<CursorPos>();
+        app.init_resource::<ObstacleGrid>();
+        app.init_resource::<SelectedSeed>();
+        app.init_resource::<DialogueState>();
+        app.init_resource::<OpenChest>();
+        app.insert_resource(GameRng::new(0));
+        app.insert_resource(PlayerAssets::placeholder());
+        // Stands in for the loading screen, which heads on once the assets are in.
+        app.add_systems(
+            OnEnter(GameState::Loading),
+            |mut next_screen: ResMut<NextState<GameState>>| {
+                next_screen.set(GameState::Gameplay);
+            },
+        );
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Gameplay);
+        app.update();
+        app
+    }
+
+    fn count<F: bevy::ecs::query::QueryFilter>(app: &mut App) -> usize {
+        app.world_mut()
+            .query_filtered::<(), F>()
+            .iter(app.world())
+            .count()
+    }
+
+    #[test]
+    fn loading_during_gameplay_respawns_the_level() {
+        let mut app = app_in_gameplay();
+        assert_eq!(count::<With<Level>>(&mut app), 1);
+
+        // Load where F9 does, so the player's systems later in the frame run without a player.
+        app.add_systems(
+            Update,
+            (|mut load_request: LoadRequest| {
+                load_request.load_data(save_data());
+            })
+            .run_if(run_once)
+            .in_set(AppSystems::RecordInput),
+        );
+        app.update();
+        assert_eq!(count::<With<Level>>(&mut app), 0);
+
+        // Into the loading screen, then back into gameplay.
+        app.update();
+        app.update();
+        assert_eq!(
+            app.world().resource::<State<GameState>>().get(),
+            &GameState::Gameplay
+        );
+        assert_eq!(count::<With<Level>>(&mut app), 1);
+        assert_eq!(count::<With<Player>>(&mut app), 1);
+        assert!(app.world().contains_resource::<PendingLoad>());
+    }
+
+    /// A one-tile grass layer at the origin, as a freshly loaded map would spawn it.
+    fn spawn_grass(world: &mut World) -> Entity {
+        let tilemap = world
+            .spawn((
+                TilemapSize { x: 1, y: 1 },
+                TilemapGridSize { x: 16.0, y: 16.0 },
+                TilemapTileSize { x: 16.0, y: 16.0 },
+                TilemapType::Square,
+                TilemapAnchor::Center,
+                Transform::default(),
+            ))
+            .id();
+        world
+            .spawn((
+                TilePos { x: 0, y: 0 },
+                TilemapId(tilemap),
+                TileType::Grass,
+                TileColor::default(),
+            ))
+            .id()
+    }
+
+    #[test]
+    fn planted_crops_survive_saving_and_loading() {
+        let mut world = World::new();
+        world.init_resource::<Inventory>();
+        world.init_resource::<ChoppedTiles>();
+        world.init_resource::<ChestContents>();
+        world.init_resource::<Farmland>();
+        world.init_resource::<GameTime>();
+        world.init_resource::<CurrentMap>();
+        world.insert_resource(GameRng::new(0));
+        world.insert_resource(EnemyAssets::placeholder());
+        world.spawn((Player, Transform::default(), Health::new(1.0)));
+
+        let tile = spawn_grass(&mut world);
+        world.entity_mut(tile).insert(Tilled);
+        let mut crop = Crop::new(tile, ItemKind::PumpkinSeed);
+        crop.stage = 2;
+        world.spawn(crop);
+
+        let data = world
+            .run_system_once(|snapshot: GameSnapshot| snapshot.capture())
+            .expect("The snapshot should run")
+            .expect("There should be a player to save");
+        let contents = ron::to_string(&data).expect("The save should serialize");
+        let data: SaveData = ron::from_str(&contents).expect("The save should parse");
+
+        // Tear the level down, then load the save into a freshly spawned one.
+        world.clear_entities();
+        world.spawn((Player, Transform::default(), Health::new(1.0)));
+        world.insert_resource(PendingLoad(data));
+        world
+            .run_system_once(apply_pending_load)
+            .expect("The load should apply");
+        let tile = spawn_grass(&mut world);
+        world
+            .run_system_once(crops::restore_plots)
+            .expect("The plots should be restored");
+
+        assert!(world.entity(tile).contains::<Tilled>());
+        let crops: Vec<Crop> = world.query::<&Crop>().iter(&world).cloned().collect();
+        assert_eq!(crops.len(), 1);
+        assert_eq!(crops[0].tile, tile);
+        assert_eq!(crops[0].seed, ItemKind::PumpkinSeed);
+        assert_eq!(crops[0].stage, 2);
+    }
+}
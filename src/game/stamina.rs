@@ -0,0 +1,61 @@
+//! Stamina: the energy the player spends on tool actions and slowly gets back.
+
+use bevy::prelude::*;
+
+use crate::{AppSystems, states::GameplayState};
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<Stamina>();
+    app.add_systems(
+        Update,
+        regenerate_stamina
+            .run_if(in_state(GameplayState::Running))
+            .in_set(AppSystems::TickTimers),
+    );
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct Stamina {
+    pub current: f32,
+    pub max: f32,
+    /// Stamina recovered every second, up to `max`.
+    pub regen_per_sec: f32,
+}
+
+impl Stamina {
+    pub fn new(max: f32, regen_per_sec: f32) -> Self {
+        Self {
+            current: max,
+            max,
+            regen_per_sec,
+        }
+    }
+
+    /// Spend `cost` if there is enough stamina left, returning whether it was spent.
+    pub fn try_spend(&mut self, cost: f32) -> bool {
+        if self.current < cost {
+            return false;
+        }
+        self.current -= cost;
+        true
+    }
+
+    /// How full the stamina bar is, from 0 to 1.
+    pub fn fraction(&self) -> f32 {
+        if self.max <= 0.0 {
+            return 0.0;
+        }
+        (self.current / self.max).clamp(0.0, 1.0)
+    }
+}
+
+fn regenerate_stamina(time: Res<Time>, mut stamina_q: Query<&mut Stamina>) {
+    for mut stamina in &mut stamina_q {
+        if stamina.current >= stamina.max {
+            continue;
+        }
+        stamina.current =
+            (stamina.current + stamina.regen_per_sec * time.delta_secs()).min(stamina.max);
+    }
+}
@@ -0,0 +1,182 @@
+//! Weather that changes every so often. While it rains, drops fall across the screen and
+//! the soil under every crop stays watered.
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use rand::Rng;
+
+use crate::{
+    AppSystems,
+    constants::TILE_SCALE,
+    game::{
+        crops::{Crop, Watered, WateringConfig, water_tile},
+        effects::{Lifetime, Particle},
+        level::LevelSpawnSystems,
+        minimap::MinimapCamera,
+        rng::GameRng,
+    },
+    states::{DestroyOnEnter, GameState, GameplayState, VisibleInState},
+    world::tiledhelper::HighlightedTile,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<Weather>();
+    app.register_type::<WeatherTimers>();
+    app.init_resource::<Weather>();
+    app.init_resource::<WeatherTimers>();
+    app.add_systems(
+        OnEnter(GameState::Gameplay),
+        reset_weather.in_set(LevelSpawnSystems),
+    );
+    app.add_systems(
+        Update,
+        (
+            change_weather.in_set(AppSystems::TickTimers),
+            (spawn_raindrops, water_crops_in_rain)
+                .run_if(resource_equals(Weather::Rain))
+                .in_set(AppSystems::Update),
+        )
+            .run_if(in_state(GameplayState::Running)),
+    );
+}
+
+/// Seconds between weather changes.
+const WEATHER_CHANGE_SECS: f32 = 90.0;
+/// Chance that the weather turns to rain at each change.
+const RAIN_CHANCE: f64 = 0.3;
+/// Raindrops spawned every second while it rains.
+const RAINDROPS_PER_SEC: f32 = 150.0;
+/// Z position for rendering raindrops, above everything else in the world.
+const RAINDROP_Z: f32 = 4.0;
+/// One map pixel wide and four tall.
+const RAINDROP_SIZE: Vec2 = Vec2::new(TILE_SCALE, TILE_SCALE * 4.0);
+const RAINDROP_COLOR: Color = Color::srgba(0.7, 0.8, 1.0, 0.6);
+const RAINDROP_VELOCITY: Vec2 = Vec2::new(TILE_SCALE * -20.0, TILE_SCALE * -234.0);
+const RAINDROP_LIFETIME_SECS: f32 = 0.5;
+
+/// The current weather.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+#[reflect(Resource)]
+pub enum Weather {
+    #[default]
+    Clear,
+    Rain,
+}
+
+impl Weather {
+    pub fn name(self) -> &'static str {
+        match self {
+            Weather::Clear => "Clear",
+            Weather::Rain => "Rain",
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Resource)]
+struct WeatherTimers {
+    /// Time until the weather may change.
+    change: Timer,
+    /// Spacing between raindrops.
+    raindrop: Timer,
+}
+
+impl Default for WeatherTimers {
+    fn default() -> Self {
+        Self {
+            change: Timer::from_seconds(WEATHER_CHANGE_SECS, TimerMode::Repeating),
+            raindrop: Timer::from_seconds(1.0 / RAINDROPS_PER_SEC, TimerMode::Repeating),
+        }
+    }
+}
+
+/// Every fresh level starts with clear skies.
+fn reset_weather(mut weather: ResMut<Weather>, mut timers: ResMut<WeatherTimers>) {
+    *weather = Weather::default();
+    *timers = WeatherTimers::default();
+}
+
+fn change_weather(
+    time: Res<Time>,
+    mut timers: ResMut<WeatherTimers>,
+    mut weather: ResMut<Weather>,
+    mut rng: ResMut<GameRng>,
+) {
+    timers.change.tick(time.delta());
+    if !timers.change.just_finished() {
+        return;
+    }
+    let next = if rng.0.random_bool(RAIN_CHANCE) {
+        Weather::Rain
+    } else {
+        Weather::Clear
+    };
+    weather.set_if_neq(next);
+}
+
+fn raindrop(position: Vec2) -> impl Bundle {
+    (
+        Name::new("Raindrop"),
+        Particle {
+            velocity: RAINDROP_VELOCITY,
+        },
+        Lifetime(Timer::from_seconds(RAINDROP_LIFETIME_SECS, TimerMode::Once)),
+        Sprite::from_color(RAINDROP_COLOR, RAINDROP_SIZE),
+        Transform::from_translation(position.extend(RAINDROP_Z)),
+        VisibleInState(vec![GameState::Gameplay]),
+        DestroyOnEnter(vec![GameState::Title, GameState::GameOver]),
+    )
+}
+
+/// Drop rain over the part of the world the camera sees.
+fn spawn_raindrops(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut timers: ResMut<WeatherTimers>,
+    camera_q: Single<(&Transform, &Projection), (With<Camera2d>, Without<MinimapCamera>)>,
+    mut rng: ResMut<GameRng>,
+) {
+    timers.raindrop.tick(time.delta());
+    let (camera_transform, projection) = *camera_q;
+    let Projection::Orthographic(projection) = projection else {
+        return;
+    };
+    let area = projection.area;
+    let center = camera_transform.translation.xy();
+    for _ in 0..timers.raindrop.times_finished_this_tick() {
+        // Start a little above the view, since drops fall for a while before fading.
+        let offset = Vec2::new(
+            rng.0.random_range(area.min.x..=area.max.x),
+            rng.0.random_range(area.min.y..=area.max.y) + area.height() * 0.25,
+        );
+        commands.spawn(raindrop(center + offset));
+    }
+}
+
+/// Keep the soil under every crop wet for as long as it rains.
+fn water_crops_in_rain(
+    mut commands: Commands,
+    crop_q: Query<&Crop>,
+    mut tile_q: Query<(
+        Option<&mut Watered>,
+        &mut TileColor,
+        Option<&mut HighlightedTile>,
+    )>,
+    watering_config: Res<WateringConfig>,
+) {
+    for crop in &crop_q {
+        let Ok((watered, mut tile_color, highlight)) = tile_q.get_mut(crop.tile) else {
+            continue;
+        };
+        match watered {
+            Some(mut watered) => watered.0.reset(),
+            None => water_tile(
+                &mut commands,
+                crop.tile,
+                &mut tile_color,
+                highlight.map(Mut::into_inner),
+                &watering_config,
+            ),
+        }
+    }
+}
@@ -0,0 +1,35 @@
+//! Y-sorting: sprites lower on screen are drawn in front of those higher up, so characters
+//! and objects on the map overlap each other the way they would seen from above at an angle.
+
+use bevy::prelude::*;
+
+use crate::{AppSystems, constants::PLAYER_Z, states::GameplayState};
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<YSort>();
+    app.add_systems(
+        Update,
+        apply_y_sort
+            .run_if(in_state(GameplayState::Running))
+            .in_set(AppSystems::PostUpdate),
+    );
+}
+
+/// How much Z changes per world unit of Y. Small enough that a map's worth of Y stays well
+/// within a unit of [`PLAYER_Z`], between the ground items below and the effects above.
+const Y_SORT_SCALE: f32 = 1e-4;
+
+/// Draws the sprite in front of or behind the other y-sorted sprites by its Y position,
+/// instead of at a fixed Z.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+#[reflect(Component)]
+pub struct YSort;
+
+fn apply_y_sort(mut sorted_q: Query<&mut Transform, With<YSort>>) {
+    for mut transform in &mut sorted_q {
+        let z = PLAYER_Z - transform.translation.y * Y_SORT_SCALE;
+        if transform.translation.z != z {
+            transform.translation.z = z;
+        }
+    }
+}
@@ -10,11 +10,7 @@ mod theme;
 mod utils;
 mod world;
 
-use bevy::{
-    asset::AssetMetaCheck,
-    audio::{AudioPlugin, Volume},
-    prelude::*,
-};
+use bevy::{asset::AssetMetaCheck, prelude::*};
 
 pub struct AppPlugin;
 
@@ -53,18 +49,13 @@ impl Plugin for AppPlugin {
                     }
                     .into(),
                     ..default()
-                })
-                .set(AudioPlugin {
-                    global_volume: GlobalVolume {
-                        volume: Volume::Linear(0.3),
-                    },
-                    ..default()
                 }),
         );
 
         // Add other plugins.
         app.add_plugins((
             asset_tracking::plugin,
+            audio::plugin,
             game::plugin,
             #[cfg(feature = "dev")]
             dev_tools::plugin,
@@ -94,6 +85,8 @@ fn spawn_camera(mut commands: Commands) {
     commands.spawn((
         Name::new("Camera"),
         Camera2d,
+        // Keep the UI on this camera when others (like the minimap) are added.
+        IsDefaultUiCamera,
         SpritePickingCamera,
         Msaa::Off,
     ));
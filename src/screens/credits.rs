@@ -1,16 +1,42 @@
 //! A credits screen that can be accessed from the title screen.
+//!
+//! The credits themselves live in `assets/credits.ron`, so they can be updated
+//! without touching any code. They roll up the screen like film credits and return
+//! to the title screen once they're over, or right away when Escape is pressed.
 
-use bevy::{ecs::spawn::SpawnIter, prelude::*, ui::Val::*};
+use bevy::{
+    asset::{AssetLoader, LoadContext, io::Reader},
+    ecs::spawn::SpawnIter,
+    input::common_conditions::input_just_pressed,
+    prelude::*,
+    ui::Val::*,
+    window::PrimaryWindow,
+};
+use serde::Deserialize;
+use thiserror::Error;
 
 use crate::{
     asset_tracking::LoadResource,
     audio::music,
-    states::{GameState, PreviousState},
+    states::{GameState, ScreenStack},
     theme::prelude::*,
 };
 
 pub(super) fn plugin(app: &mut App) {
+    app.init_asset::<CreditsList>();
+    app.register_asset_loader(CreditsLoader);
+    app.register_type::<CreditsAssets>();
+    app.load_resource::<CreditsAssets>();
+    app.register_type::<CreditsRoll>();
     app.add_systems(OnEnter(GameState::Credits), spawn_credits_screen);
+    app.add_systems(
+        Update,
+        (
+            roll_credits,
+            return_to_title.run_if(input_just_pressed(KeyCode::Escape)),
+        )
+            .run_if(in_state(GameState::Credits)),
+    );
 
     app.register_type::<CreditsMusic>();
     app.load_resource::<CreditsMusic>();
@@ -18,40 +44,111 @@ pub(super) fn plugin(app: &mut App) {
     app.add_systems(OnExit(GameState::Credits), stop_credits_music);
 }
 
-fn spawn_credits_screen(mut commands: Commands) {
+/// The people and assets to credit, as listed in `credits.ron`.
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)]
+struct CreditsList {
+    /// Contributor and role pairs.
+    created_by: Vec<[String; 2]>,
+    /// Asset and license pairs.
+    assets: Vec<[String; 2]>,
+}
+
+#[derive(Default)]
+struct CreditsLoader;
+
+#[derive(Debug, Error)]
+enum CreditsLoaderError {
+    #[error("Could not read the credits: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse the credits: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for CreditsLoader {
+    type Asset = CreditsList;
+    type Settings = ();
+    type Error = CreditsLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        // Only claim the credits, so other RON assets can have loaders of their own.
+        &["credits.ron"]
+    }
+}
+
+#[derive(Resource, Asset, Clone, Reflect)]
+#[reflect(Resource)]
+struct CreditsAssets {
+    #[dependency]
+    list: Handle<CreditsList>,
+}
+
+impl FromWorld for CreditsAssets {
+    fn from_world(world: &mut World) -> Self {
+        let assets = world.resource::<AssetServer>();
+        Self {
+            list: assets.load("credits.ron"),
+        }
+    }
+}
+
+/// Speed the credits roll up the screen, in logical pixels per second.
+const CREDITS_ROLL_SPEED: f32 = 60.0;
+
+/// The credits rolling up the screen, with the offset of their top edge from the top of the window.
+#[derive(Component, Debug, Reflect)]
+#[reflect(Component)]
+struct CreditsRoll {
+    top: f32,
+}
+
+fn spawn_credits_screen(
+    mut commands: Commands,
+    credits_assets: Res<CreditsAssets>,
+    credits_lists: Res<Assets<CreditsList>>,
+    window: Single<&Window, With<PrimaryWindow>>,
+) {
+    // Read the list every time the screen opens, so edits show up after hot reloading.
+    let (created_by, assets) = credits_lists
+        .get(&credits_assets.list)
+        .map(|list| (list.created_by.clone(), list.assets.clone()))
+        .unwrap_or_default();
+    // Start just below the bottom of the window.
+    let top = window.height();
     commands.spawn((
-        widget::ui_root("Credits Screen"),
+        Name::new("Credits Screen"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Px(top),
+            width: Percent(100.0),
+            align_items: AlignItems::Center,
+            flex_direction: FlexDirection::Column,
+            row_gap: Px(20.0),
+            ..default()
+        },
+        CreditsRoll { top },
         DespawnOnExit(GameState::Credits),
         children![
             widget::header("Created by"),
-            created_by(),
+            grid(created_by),
             widget::header("Assets"),
-            assets(),
+            grid(assets),
             widget::button("Back", enter_title_screen),
         ],
     ));
 }
 
-fn created_by() -> impl Bundle {
-    grid(vec![
-        ["Joe Shmoe", "Implemented alligator wrestling AI"],
-        ["Jane Doe", "Made the music for the alien invasion"],
-    ])
-}
-
-fn assets() -> impl Bundle {
-    grid(vec![
-        ["Ducky sprite", "CC0 by Caz Creates Games"],
-        ["Button SFX", "CC0 by Jaszunio15"],
-        ["Music", "CC BY 3.0 by Kevin MacLeod"],
-        [
-            "Bevy logo",
-            "All rights reserved by the Bevy Foundation, permission granted for splash screen use when unmodified",
-        ],
-    ])
-}
-
-fn grid(content: Vec<[&'static str; 2]>) -> impl Bundle {
+fn grid(content: Vec<[String; 2]>) -> impl Bundle {
     (
         Name::new("Grid"),
         Node {
@@ -79,13 +176,37 @@ fn grid(content: Vec<[&'static str; 2]>) -> impl Bundle {
     )
 }
 
+/// Move the credits up, and head back to the title screen once they've left the window.
+fn roll_credits(
+    time: Res<Time>,
+    roll: Single<(&mut Node, &mut CreditsRoll, &ComputedNode)>,
+    next_screen: ResMut<NextState<GameState>>,
+    screen_stack: ResMut<ScreenStack>,
+) {
+    let (mut node, mut roll, computed) = roll.into_inner();
+    roll.top -= CREDITS_ROLL_SPEED * time.delta_secs();
+    node.top = Px(roll.top);
+
+    let height = computed.size().y * computed.inverse_scale_factor();
+    if height > 0.0 && roll.top < -height {
+        return_to_title(next_screen, screen_stack);
+    }
+}
+
 fn enter_title_screen(
     _: On<Pointer<Click>>,
+    next_screen: ResMut<NextState<GameState>>,
+    screen_stack: ResMut<ScreenStack>,
+) {
+    return_to_title(next_screen, screen_stack);
+}
+
+/// Go back to the screen the credits were opened from, which is the title screen.
+fn return_to_title(
     mut next_screen: ResMut<NextState<GameState>>,
-    mut previous_state: ResMut<PreviousState>,
+    mut screen_stack: ResMut<ScreenStack>,
 ) {
-    previous_state.0 = GameState::Credits;
-    next_screen.set(GameState::Title);
+    next_screen.set(screen_stack.pop().unwrap_or(GameState::Title));
 }
 
 #[derive(Resource, Asset, Clone, Reflect)]
@@ -0,0 +1,31 @@
+//! The screen shown when the player runs out of health.
+
+use bevy::prelude::*;
+
+use crate::{states::GameState, theme::prelude::*};
+
+use super::transition::Transition;
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(OnEnter(GameState::GameOver), spawn_game_over_screen);
+}
+
+fn spawn_game_over_screen(mut commands: Commands) {
+    commands.spawn((
+        widget::ui_root("Game Over Screen"),
+        DespawnOnExit(GameState::GameOver),
+        children![
+            widget::header("Game Over"),
+            widget::button("Retry", retry_gameplay),
+            widget::button("Title", enter_title_screen),
+        ],
+    ));
+}
+
+fn retry_gameplay(_: On<Pointer<Click>>, mut transition: ResMut<Transition>) {
+    transition.to(GameState::Gameplay);
+}
+
+fn enter_title_screen(_: On<Pointer<Click>>, mut transition: ResMut<Transition>) {
+    transition.to(GameState::Title);
+}
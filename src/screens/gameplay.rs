@@ -4,30 +4,33 @@ use bevy::{input::common_conditions::input_just_pressed, prelude::*};
 
 use crate::{
     asset_tracking::LoadResource,
-    audio::music,
-    game::level::spawn_level,
-    states::{GameState, PreviousState},
+    audio::{ambience, music},
+    game::chest::chest_open,
+    states::{AppState, GameState, GameplayState},
 };
 
 pub(super) fn plugin(app: &mut App) {
-    app.add_systems(
-        OnTransition::<GameState> {
-            exited: GameState::Title,
-            entered: GameState::Gameplay,
-        },
-        spawn_level,
-    );
-
     app.register_type::<GameplayMusic>();
     app.load_resource::<GameplayMusic>();
+    // Keep the track going through the pause menu (where it gets ducked instead),
+    // and only stop it once gameplay is actually over.
     app.add_systems(OnEnter(GameState::Gameplay), start_gameplay_music);
-    app.add_systems(OnExit(GameState::Gameplay), stop_gameplay_music);
+    app.add_systems(OnEnter(GameState::Title), stop_gameplay_music);
+    app.add_systems(OnEnter(GameState::GameOver), stop_gameplay_music);
+
+    app.register_type::<GameplayAmbience>();
+    app.load_resource::<GameplayAmbience>();
+    // Unlike the music, the ambience goes quiet while the game is paused.
+    app.add_systems(OnEnter(GameplayState::Running), start_gameplay_ambience);
+    app.add_systems(OnExit(GameplayState::Running), stop_gameplay_ambience);
 
     app.add_systems(
         Update,
         pause_or_continue_gameplay.run_if(
-            (in_state(GameState::Gameplay).or(in_state(GameState::Pausing)))
-                .and(input_just_pressed(KeyCode::Escape)),
+            in_state(AppState::InGame)
+                .and(input_just_pressed(KeyCode::Escape))
+                // Escape closes an open chest instead.
+                .and(not(chest_open)),
         ),
     );
 }
@@ -51,6 +54,9 @@ impl FromWorld for GameplayMusic {
 }
 
 fn start_gameplay_music(mut commands: Commands, mut gameplay_music: ResMut<GameplayMusic>) {
+    if gameplay_music.entity.is_some() {
+        return;
+    }
     let handle = gameplay_music.handle.clone();
     gameplay_music.entity = Some(commands.spawn(music(handle)).id());
 }
@@ -61,15 +67,47 @@ fn stop_gameplay_music(mut commands: Commands, mut gameplay_music: ResMut<Gamepl
     }
 }
 
-fn pause_or_continue_gameplay(
-    current_screen: Res<State<GameState>>,
-    mut next_screen: ResMut<NextState<GameState>>,
-    mut previous_state: ResMut<PreviousState>,
+#[derive(Resource, Asset, Clone, Reflect)]
+#[reflect(Resource)]
+struct GameplayAmbience {
+    #[dependency]
+    handle: Handle<AudioSource>,
+    entity: Option<Entity>,
+}
+
+impl FromWorld for GameplayAmbience {
+    fn from_world(world: &mut World) -> Self {
+        let assets = world.resource::<AssetServer>();
+        Self {
+            handle: assets.load("audio/ambience/farm.ogg"),
+            entity: None,
+        }
+    }
+}
+
+fn start_gameplay_ambience(
+    mut commands: Commands,
+    mut gameplay_ambience: ResMut<GameplayAmbience>,
 ) {
-    previous_state.0 = current_screen.get().clone();
-    if current_screen.get() == &GameState::Pausing {
-        next_screen.set(GameState::Gameplay);
+    if gameplay_ambience.entity.is_some() {
         return;
     }
-    next_screen.set(GameState::Pausing);
+    let handle = gameplay_ambience.handle.clone();
+    gameplay_ambience.entity = Some(commands.spawn(ambience(handle)).id());
+}
+
+fn stop_gameplay_ambience(mut commands: Commands, mut gameplay_ambience: ResMut<GameplayAmbience>) {
+    if let Some(entity) = gameplay_ambience.entity.take() {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn pause_or_continue_gameplay(
+    gameplay_state: Res<State<GameplayState>>,
+    mut next_gameplay_state: ResMut<NextState<GameplayState>>,
+) {
+    next_gameplay_state.set(match gameplay_state.get() {
+        GameplayState::Running => GameplayState::Paused,
+        GameplayState::Paused => GameplayState::Running,
+    });
 }
@@ -1,17 +1,21 @@
 //! A loading screen during which game assets are loaded.
 //! This reduces stuttering, especially for audio on WASM.
 
-use bevy::prelude::*;
+use bevy::{prelude::*, ui::Val::*};
 
-use crate::{
-    asset_tracking::ResourceHandles,
-    states::{GameState, PreviousState},
-    theme::prelude::*,
-};
+use crate::{AppSystems, asset_tracking::ResourceHandles, states::GameState, theme::prelude::*};
+
+use super::transition::Transition;
 
 pub(super) fn plugin(app: &mut App) {
     app.add_systems(OnEnter(GameState::Loading), spawn_loading_screen);
 
+    app.add_systems(
+        Update,
+        update_loading_progress
+            .run_if(in_state(GameState::Loading))
+            .in_set(AppSystems::PostUpdate),
+    );
     app.add_systems(
         Update,
         enter_gameplay_screen.run_if(in_state(GameState::Loading).and(all_assets_loaded)),
@@ -22,16 +26,22 @@ fn spawn_loading_screen(mut commands: Commands) {
     commands.spawn((
         widget::ui_root("Loading Screen"),
         DespawnOnExit(GameState::Loading),
-        children![widget::label("Loading...")],
+        children![widget::label("Loading..."), widget::progress_bar()],
     ));
 }
 
-fn enter_gameplay_screen(
-    mut next_screen: ResMut<NextState<GameState>>,
-    mut previous_state: ResMut<PreviousState>,
+fn update_loading_progress(
+    resource_handles: Res<ResourceHandles>,
+    mut fill_query: Query<&mut Node, With<widget::ProgressBarFill>>,
 ) {
-    previous_state.0 = GameState::Loading;
-    next_screen.set(GameState::Gameplay);
+    let percent = resource_handles.progress() * 100.0;
+    for mut node in &mut fill_query {
+        node.width = Percent(percent);
+    }
+}
+
+fn enter_gameplay_screen(mut transition: ResMut<Transition>) {
+    transition.to(GameState::Gameplay);
 }
 
 fn all_assets_loaded(resource_handles: Res<ResourceHandles>) -> bool {
@@ -1,27 +1,33 @@
 //! The game's main screen states and transitions between them.
 
 mod credits;
+mod game_over;
 mod gameplay;
 mod loading;
 mod pause;
 mod settings;
 mod splash;
 mod title;
+mod transition;
 
 use bevy::prelude::*;
 
-use crate::states::GameState;
+use crate::states::{AppState, GameState, GameplayState};
 
 pub(super) fn plugin(app: &mut App) {
     app.init_state::<GameState>();
+    app.add_computed_state::<AppState>();
+    app.add_sub_state::<GameplayState>();
 
     app.add_plugins((
         credits::plugin,
+        game_over::plugin,
         gameplay::plugin,
         loading::plugin,
         settings::plugin,
         splash::plugin,
         title::plugin,
         pause::plugin,
+        transition::plugin,
     ));
 }
@@ -1,23 +1,79 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, window::WindowFocused};
 
 use crate::{
-    states::{GameState, PreviousState},
-    theme::widget,
+    audio::MusicDuck,
+    game::save::{GameSnapshot, LoadRequest},
+    states::{GameState, GameplayState},
+    theme::{palette::MODAL_BACKDROP, widget},
 };
 
 use super::title::enter_settings_screen;
 #[cfg(not(target_family = "wasm"))]
 use super::title::exit_app;
+use super::transition::Transition;
 pub(super) fn plugin(app: &mut App) {
-    app.add_systems(OnEnter(GameState::Pausing), spawn_pausing_screen);
+    app.add_systems(OnEnter(GameplayState::Paused), spawn_pausing_screen);
+
+    // Freeze virtual time while paused. Settings can be opened from the pause menu,
+    // so only resume once we actually leave the menus.
+    app.add_systems(OnEnter(GameplayState::Paused), pause_time);
+    app.add_systems(OnEnter(GameplayState::Running), unpause_time);
+    app.add_systems(OnEnter(GameState::Title), unpause_time);
+
+    // Quieten the music while paused, keeping it down in the settings too.
+    app.add_systems(OnEnter(GameplayState::Paused), duck_music);
+    app.add_systems(OnEnter(GameplayState::Running), restore_music);
+    app.add_systems(OnEnter(GameState::Title), restore_music);
+    app.add_systems(OnEnter(GameState::GameOver), restore_music);
+
+    app.register_type::<PauseOnFocusLoss>();
+    app.init_resource::<PauseOnFocusLoss>();
+    app.add_systems(
+        Update,
+        pause_on_focus_loss.run_if(
+            in_state(GameplayState::Running)
+                .and(resource_equals(PauseOnFocusLoss(true)))
+                .and(on_message::<WindowFocused>),
+        ),
+    );
+}
+
+/// Whether switching away from the game window opens the pause menu.
+/// Can be turned off in the settings, e.g. by speedrunners.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub struct PauseOnFocusLoss(pub bool);
+
+impl Default for PauseOnFocusLoss {
+    fn default() -> Self {
+        Self(true)
+    }
 }
+
+/// Pause when the window loses focus. Regaining focus leaves the pause menu up.
+fn pause_on_focus_loss(
+    mut focus_events: MessageReader<WindowFocused>,
+    mut next_gameplay_state: ResMut<NextState<GameplayState>>,
+) {
+    if focus_events.read().any(|event| !event.focused) {
+        next_gameplay_state.set(GameplayState::Paused);
+    }
+}
+
+/// Factor applied to the music volume while the pause menu is open.
+const PAUSED_MUSIC_VOLUME: f32 = 0.3;
+
 fn spawn_pausing_screen(mut commands: Commands) {
     commands.spawn((
         widget::ui_root("Pausing Screen"),
-        DespawnOnExit(GameState::Pausing),
+        // The game stays on screen behind the menu, so dim it to keep the buttons readable.
+        BackgroundColor(MODAL_BACKDROP),
+        DespawnOnExit(GameplayState::Paused),
         #[cfg(not(target_family = "wasm"))]
         children![
             widget::button("Continue", continue_to_gameplay_screen),
+            widget::button("Save", save_from_menu),
+            widget::button("Load", load_from_menu),
             widget::button("Settings", enter_settings_screen),
             widget::button("Title", enter_title_screen),
             widget::button("Exit", exit_app),
@@ -25,6 +81,8 @@ fn spawn_pausing_screen(mut commands: Commands) {
         #[cfg(target_family = "wasm")]
         children![
             widget::button("Continue", continue_to_gameplay_screen),
+            widget::button("Save", save_from_menu),
+            widget::button("Load", load_from_menu),
             widget::button("Settings", enter_settings_screen),
             widget::button("Title", enter_title_screen),
         ],
@@ -33,18 +91,46 @@ fn spawn_pausing_screen(mut commands: Commands) {
 
 fn continue_to_gameplay_screen(
     _: On<Pointer<Click>>,
-    mut next_screen: ResMut<NextState<GameState>>,
-    mut previous_state: ResMut<PreviousState>,
+    mut next_gameplay_state: ResMut<NextState<GameplayState>>,
 ) {
-    previous_state.0 = GameState::Pausing;
-    next_screen.set(GameState::Gameplay);
+    next_gameplay_state.set(GameplayState::Running);
 }
 
-fn enter_title_screen(
-    _: On<Pointer<Click>>,
-    mut next_screen: ResMut<NextState<GameState>>,
-    mut previous_state: ResMut<PreviousState>,
-) {
-    previous_state.0 = GameState::Pausing;
-    next_screen.set(GameState::Title);
+fn save_from_menu(_: On<Pointer<Click>>, snapshot: GameSnapshot) {
+    snapshot.save();
+}
+
+fn load_from_menu(_: On<Pointer<Click>>, mut load_request: LoadRequest) {
+    load_request.load();
+}
+
+/// Ask whether to abandon the session, and head to the title screen if the player confirms.
+fn enter_title_screen(_: On<Pointer<Click>>, mut commands: Commands) {
+    commands.spawn((
+        widget::confirmation(
+            "Return to title? Unsaved progress will be lost.",
+            confirm_title_screen,
+        ),
+        DespawnOnExit(GameplayState::Paused),
+    ));
+}
+
+fn confirm_title_screen(_: On<Pointer<Click>>, mut transition: ResMut<Transition>) {
+    transition.to(GameState::Title);
+}
+
+fn pause_time(mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+}
+
+fn unpause_time(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
+}
+
+fn duck_music(mut music_duck: ResMut<MusicDuck>) {
+    music_duck.0 = PAUSED_MUSIC_VOLUME;
+}
+
+fn restore_music(mut music_duck: ResMut<MusicDuck>) {
+    *music_duck = MusicDuck::default();
 }
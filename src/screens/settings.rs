@@ -2,20 +2,57 @@
 //! We can add all manner of settings and accessibility options here.
 //! For 3D, we'd also place the camera sensitivity and FOV here.
 
-use bevy::{audio::Volume, prelude::*, ui::Val::*};
+#[cfg(not(target_family = "wasm"))]
+use bevy::window::{MonitorSelection, PrimaryWindow, WindowMode};
+use bevy::{prelude::*, ui::Val::*};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    states::{GameState, PreviousState},
+    audio::{self, VolumeChannel, VolumeSettings},
+    game::camera::ZoomSettings,
+    states::{GameState, GameplayState, ScreenStack},
     theme::prelude::*,
 };
 
+use super::pause::PauseOnFocusLoss;
+
 pub(super) fn plugin(app: &mut App) {
     app.add_systems(OnEnter(GameState::Settings), spawn_settings_screen);
 
-    app.register_type::<GlobalVolumeLabel>();
+    app.register_type::<PersistentSettings>();
+    app.insert_resource(PersistentSettings::load());
+    app.add_systems(Startup, restore_mute);
+    app.add_systems(Update, (apply_display_settings, persist_mute));
+
+    #[cfg(not(target_family = "wasm"))]
+    {
+        app.register_type::<FullscreenLabel>();
+        app.add_systems(
+            OnEnter(GameState::Settings),
+            spawn_fullscreen_setting.after(spawn_settings_screen),
+        );
+        app.add_systems(
+            Update,
+            update_fullscreen_label.run_if(in_state(GameState::Settings)),
+        );
+    }
+
+    app.register_type::<VolumeLabel>();
+    app.register_type::<ZoomSpeedLabel>();
+    app.register_type::<PauseOnFocusLossLabel>();
+    app.register_type::<UiScaleLabel>();
+    app.register_type::<MuteLabel>();
     app.add_systems(
         Update,
-        (update_volume_label, update_game_speed_label).run_if(in_state(GameState::Settings)),
+        (
+            update_volume_labels,
+            update_game_speed_label,
+            update_zoom_speed_label,
+            update_pause_on_focus_loss_label,
+            update_ui_scale_label,
+            update_mute_label,
+        )
+            .run_if(in_state(GameState::Settings)),
     );
 }
 
@@ -27,6 +64,7 @@ fn spawn_settings_screen(mut commands: Commands) {
             widget::header("Settings"),
             (
                 Name::new("Settings Grid"),
+                SettingsGrid,
                 Node {
                     display: Display::Grid,
                     row_gap: Px(10.0),
@@ -36,13 +74,37 @@ fn spawn_settings_screen(mut commands: Commands) {
                 },
                 children![
                     (
-                        widget::label("Audio Volume"),
+                        widget::label("Master Volume"),
+                        Node {
+                            justify_self: JustifySelf::End,
+                            ..default()
+                        }
+                    ),
+                    volume_widget(VolumeChannel::Master),
+                    (
+                        widget::label("Music Volume"),
+                        Node {
+                            justify_self: JustifySelf::End,
+                            ..default()
+                        }
+                    ),
+                    volume_widget(VolumeChannel::Music),
+                    (
+                        widget::label("SFX Volume"),
                         Node {
                             justify_self: JustifySelf::End,
                             ..default()
                         }
                     ),
-                    volume_widget(),
+                    volume_widget(VolumeChannel::SoundEffects),
+                    (
+                        widget::label("Mute (M)"),
+                        Node {
+                            justify_self: JustifySelf::End,
+                            ..default()
+                        }
+                    ),
+                    mute_widget(),
                     (
                         widget::label("Game Speed"),
                         Node {
@@ -51,6 +113,30 @@ fn spawn_settings_screen(mut commands: Commands) {
                         }
                     ),
                     game_speed_widget(),
+                    (
+                        widget::label("Zoom Sensitivity"),
+                        Node {
+                            justify_self: JustifySelf::End,
+                            ..default()
+                        }
+                    ),
+                    zoom_speed_widget(),
+                    (
+                        widget::label("Pause When Unfocused"),
+                        Node {
+                            justify_self: JustifySelf::End,
+                            ..default()
+                        }
+                    ),
+                    pause_on_focus_loss_widget(),
+                    (
+                        widget::label("UI Scale"),
+                        Node {
+                            justify_self: JustifySelf::End,
+                            ..default()
+                        }
+                    ),
+                    ui_scale_widget(),
                 ],
             ),
             widget::button("Back", enter_last_screen),
@@ -58,27 +144,53 @@ fn spawn_settings_screen(mut commands: Commands) {
     ));
 }
 
-fn volume_widget() -> impl Bundle {
+/// The grid of setting rows, each a label followed by its control.
+#[derive(Component)]
+struct SettingsGrid;
+
+fn volume_widget(channel: VolumeChannel) -> impl Bundle {
     (
         Node {
             justify_self: JustifySelf::Start,
             ..default()
         },
         children![
-            widget::button_small("-", lower_volume),
+            widget::button_small(
+                "-",
+                move |_: On<Pointer<Click>>, mut volume_settings: ResMut<VolumeSettings>| {
+                    let volume = volume_settings.get_mut(channel);
+                    *volume = (*volume - 0.1).max(MIN_VOLUME);
+                }
+            ),
             (
                 Node {
                     padding: UiRect::horizontal(Px(10.0)),
                     justify_content: JustifyContent::Center,
                     ..default()
                 },
-                children![(widget::label(""), GlobalVolumeLabel)],
+                children![(widget::label(""), VolumeLabel(channel))],
+            ),
+            widget::button_small(
+                "+",
+                move |_: On<Pointer<Click>>, mut volume_settings: ResMut<VolumeSettings>| {
+                    let volume = volume_settings.get_mut(channel);
+                    *volume = (*volume + 0.1).min(MAX_VOLUME);
+                }
             ),
-            widget::button_small("+", raise_volume),
         ],
     )
 }
 
+fn mute_widget() -> impl Bundle {
+    (
+        Node {
+            justify_self: JustifySelf::Start,
+            ..default()
+        },
+        children![widget::button_toggle(MuteLabel, toggle_mute)],
+    )
+}
+
 fn game_speed_widget() -> impl Bundle {
     (
         Node {
@@ -100,19 +212,78 @@ fn game_speed_widget() -> impl Bundle {
     )
 }
 
+fn zoom_speed_widget() -> impl Bundle {
+    (
+        Node {
+            justify_self: JustifySelf::Start,
+            ..default()
+        },
+        children![
+            widget::button_small("-", lower_zoom_speed),
+            (
+                Node {
+                    padding: UiRect::horizontal(Px(10.0)),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                children![(widget::label(""), ZoomSpeedLabel)],
+            ),
+            widget::button_small("+", raise_zoom_speed),
+        ],
+    )
+}
+
+fn pause_on_focus_loss_widget() -> impl Bundle {
+    (
+        Node {
+            justify_self: JustifySelf::Start,
+            ..default()
+        },
+        children![
+            widget::button_small("<", toggle_pause_on_focus_loss),
+            (
+                Node {
+                    padding: UiRect::horizontal(Px(10.0)),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                children![(widget::label(""), PauseOnFocusLossLabel)],
+            ),
+            widget::button_small(">", toggle_pause_on_focus_loss),
+        ],
+    )
+}
+
 const MIN_VOLUME: f32 = 0.0;
 const MAX_VOLUME: f32 = 3.0;
 const MAX_GAME_SPEED: f32 = 3.0;
 const MIN_GAME_SPEED: f32 = 0.2;
+const MIN_ZOOM_SPEED: f32 = 0.02;
+const MAX_ZOOM_SPEED: f32 = 0.3;
 
-fn lower_volume(_: On<Pointer<Click>>, mut global_volume: ResMut<GlobalVolume>) {
-    let new_factor = global_volume.volume.to_linear() - 0.1;
-    global_volume.volume = Volume::Linear(new_factor.max(MIN_VOLUME));
+fn ui_scale_widget() -> impl Bundle {
+    (
+        Node {
+            justify_self: JustifySelf::Start,
+            ..default()
+        },
+        children![
+            widget::button_small("-", lower_ui_scale),
+            (
+                Node {
+                    padding: UiRect::horizontal(Px(10.0)),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                children![(widget::label(""), UiScaleLabel)],
+            ),
+            widget::button_small("+", raise_ui_scale),
+        ],
+    )
 }
 
-fn raise_volume(_: On<Pointer<Click>>, mut global_volume: ResMut<GlobalVolume>) {
-    let new_factor = global_volume.volume.to_linear() + 0.1;
-    global_volume.volume = Volume::Linear(new_factor.min(MAX_VOLUME));
+fn toggle_mute(_: On<Pointer<Click>>, volume_settings: ResMut<VolumeSettings>) {
+    audio::toggle_mute(volume_settings);
 }
 
 fn lower_game_speed(_: On<Pointer<Click>>, mut time: ResMut<Time<Virtual>>) {
@@ -124,22 +295,59 @@ fn raise_game_speed(_: On<Pointer<Click>>, mut time: ResMut<Time<Virtual>>) {
     let new_speed = time.relative_speed() + 0.1;
     time.set_relative_speed(new_speed.min(MAX_GAME_SPEED));
 }
+
+fn lower_zoom_speed(_: On<Pointer<Click>>, mut zoom_settings: ResMut<ZoomSettings>) {
+    let new_speed = zoom_settings.speed - 0.02;
+    zoom_settings.speed = new_speed.max(MIN_ZOOM_SPEED);
+}
+
+fn raise_zoom_speed(_: On<Pointer<Click>>, mut zoom_settings: ResMut<ZoomSettings>) {
+    let new_speed = zoom_settings.speed + 0.02;
+    zoom_settings.speed = new_speed.min(MAX_ZOOM_SPEED);
+}
+fn toggle_pause_on_focus_loss(
+    _: On<Pointer<Click>>,
+    mut pause_on_focus_loss: ResMut<PauseOnFocusLoss>,
+) {
+    pause_on_focus_loss.0 = !pause_on_focus_loss.0;
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct VolumeLabel(VolumeChannel);
+
 #[derive(Component, Reflect)]
 #[reflect(Component)]
-struct GlobalVolumeLabel;
+struct MuteLabel;
 
 #[derive(Component, Reflect)]
 #[reflect(Component)]
 struct GlobalGameSpeedLabel;
 
-fn update_volume_label(
-    mut label: Single<&mut Text, With<GlobalVolumeLabel>>,
-    global_volume: Res<GlobalVolume>,
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct ZoomSpeedLabel;
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct PauseOnFocusLossLabel;
+
+fn update_volume_labels(
+    mut label_q: Query<(&mut Text, &VolumeLabel)>,
+    volume_settings: Res<VolumeSettings>,
 ) {
-    let factor = global_volume.volume.to_linear();
-    let percent = (factor * 100.0).round();
-    let text = format!("{percent}%");
-    label.0 = text;
+    for (mut label, VolumeLabel(channel)) in &mut label_q {
+        let percent = (volume_settings.get(*channel) * 100.0).round();
+        label.0 = format!("{percent}%");
+    }
+}
+
+fn update_mute_label(
+    mut label: Single<&mut Text, With<MuteLabel>>,
+    volume_settings: Res<VolumeSettings>,
+) {
+    let text = if volume_settings.muted { "On" } else { "Off" };
+    label.0 = text.to_string();
 }
 
 fn update_game_speed_label(
@@ -151,11 +359,250 @@ fn update_game_speed_label(
     label.0 = text;
 }
 
+fn update_zoom_speed_label(
+    mut label: Single<&mut Text, With<ZoomSpeedLabel>>,
+    zoom_settings: Res<ZoomSettings>,
+) {
+    let text = format!("{:.2}", zoom_settings.speed);
+    label.0 = text;
+}
+
+fn update_pause_on_focus_loss_label(
+    mut label: Single<&mut Text, With<PauseOnFocusLossLabel>>,
+    pause_on_focus_loss: Res<PauseOnFocusLoss>,
+) {
+    let text = if pause_on_focus_loss.0 { "On" } else { "Off" };
+    label.0 = text.to_string();
+}
+
 fn enter_last_screen(
     _: On<Pointer<Click>>,
     mut next_screen: ResMut<NextState<GameState>>,
-    mut previous_state: ResMut<PreviousState>,
+    mut next_gameplay_state: ResMut<NextState<GameplayState>>,
+    mut screen_stack: ResMut<ScreenStack>,
+) {
+    let last_screen = screen_stack.pop().unwrap_or(GameState::Title);
+    // Mid-game the settings are only reachable from the pause menu, so that's where to go
+    // back to rather than straight into the running game.
+    if last_screen == GameState::Gameplay {
+        next_gameplay_state.set(GameplayState::Paused);
+    }
+    next_screen.set(last_screen);
+}
+
+#[cfg(not(target_family = "wasm"))]
+const PERSISTENT_SETTINGS_PATH: &str = "settings.ron";
+#[cfg(target_family = "wasm")]
+const PERSISTENT_SETTINGS_KEY: &str = "fish.settings";
+
+/// UI scales the player can pick from, for reading the menus on small and large screens.
+const UI_SCALES: [f32; 4] = [1.0, 1.25, 1.5, 2.0];
+
+/// Settings kept apart from the save so they survive a restart and a new game.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Resource)]
+#[serde(default)]
+struct PersistentSettings {
+    /// Ignored on the web, where the browser owns the window.
+    fullscreen: bool,
+    ui_scale: f32,
+    /// Mirrors [`VolumeSettings::muted`], which the mute hotkey flips from any screen.
+    muted: bool,
+}
+
+impl Default for PersistentSettings {
+    fn default() -> Self {
+        Self {
+            fullscreen: false,
+            ui_scale: UI_SCALES[0],
+            muted: false,
+        }
+    }
+}
+
+impl PersistentSettings {
+    fn load() -> Self {
+        let Some(contents) = read_persistent_settings() else {
+            return Self::default();
+        };
+        ron::from_str(&contents).unwrap_or_else(|error| {
+            warn!("Could not parse the settings: {error}");
+            Self::default()
+        })
+    }
+
+    fn save(&self) {
+        match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(contents) => write_persistent_settings(&contents),
+            Err(error) => warn!("Could not serialize the settings: {error}"),
+        }
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn window_mode(&self) -> WindowMode {
+        if self.fullscreen {
+            WindowMode::BorderlessFullscreen(MonitorSelection::Current)
+        } else {
+            WindowMode::Windowed
+        }
+    }
+
+    /// Move to the next smaller (`step < 0`) or larger (`step > 0`) of the [`UI_SCALES`].
+    fn step_ui_scale(&mut self, step: isize) {
+        let current = UI_SCALES
+            .iter()
+            .position(|&scale| scale >= self.ui_scale)
+            .unwrap_or(UI_SCALES.len() - 1);
+        let next = (current as isize + step).clamp(0, UI_SCALES.len() as isize - 1);
+        self.ui_scale = UI_SCALES[next as usize];
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn read_persistent_settings() -> Option<String> {
+    match std::fs::read_to_string(PERSISTENT_SETTINGS_PATH) {
+        Ok(contents) => Some(contents),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => None,
+        Err(error) => {
+            warn!("Could not read {PERSISTENT_SETTINGS_PATH}: {error}");
+            None
+        }
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn write_persistent_settings(contents: &str) {
+    if let Err(error) = std::fs::write(PERSISTENT_SETTINGS_PATH, contents) {
+        warn!("Could not save {PERSISTENT_SETTINGS_PATH}: {error}");
+    }
+}
+
+#[cfg(target_family = "wasm")]
+fn read_persistent_settings() -> Option<String> {
+    web_sys::window()?
+        .local_storage()
+        .ok()??
+        .get_item(PERSISTENT_SETTINGS_KEY)
+        .ok()?
+}
+
+#[cfg(target_family = "wasm")]
+fn write_persistent_settings(contents: &str) {
+    let saved = web_sys::window()
+        .and_then(|window| window.local_storage().ok().flatten())
+        .is_some_and(|storage| storage.set_item(PERSISTENT_SETTINGS_KEY, contents).is_ok());
+    if !saved {
+        warn!("Could not save the settings to LocalStorage");
+    }
+}
+
+/// Apply the display settings whenever they change, including once they're loaded.
+fn apply_display_settings(
+    persistent_settings: Res<PersistentSettings>,
+    mut ui_scale: ResMut<UiScale>,
+    #[cfg(not(target_family = "wasm"))] mut window: Single<&mut Window, With<PrimaryWindow>>,
+) {
+    if !persistent_settings.is_changed() {
+        return;
+    }
+    ui_scale.0 = persistent_settings.ui_scale;
+    #[cfg(not(target_family = "wasm"))]
+    {
+        window.mode = persistent_settings.window_mode();
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct FullscreenLabel;
+
+#[cfg(not(target_family = "wasm"))]
+fn spawn_fullscreen_setting(mut commands: Commands, grid: Single<Entity, With<SettingsGrid>>) {
+    commands.entity(*grid).with_children(|grid| {
+        grid.spawn((
+            widget::label("Fullscreen"),
+            Node {
+                justify_self: JustifySelf::End,
+                ..default()
+            },
+        ));
+        grid.spawn((
+            Node {
+                justify_self: JustifySelf::Start,
+                ..default()
+            },
+            children![
+                widget::button_small("<", toggle_fullscreen),
+                (
+                    Node {
+                        padding: UiRect::horizontal(Px(10.0)),
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    children![(widget::label(""), FullscreenLabel)],
+                ),
+                widget::button_small(">", toggle_fullscreen),
+            ],
+        ));
+    });
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn toggle_fullscreen(_: On<Pointer<Click>>, mut persistent_settings: ResMut<PersistentSettings>) {
+    persistent_settings.fullscreen = !persistent_settings.fullscreen;
+    persistent_settings.save();
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn update_fullscreen_label(
+    mut label: Single<&mut Text, With<FullscreenLabel>>,
+    persistent_settings: Res<PersistentSettings>,
+) {
+    let text = if persistent_settings.fullscreen {
+        "On"
+    } else {
+        "Off"
+    };
+    label.0 = text.to_string();
+}
+
+fn lower_ui_scale(_: On<Pointer<Click>>, mut persistent_settings: ResMut<PersistentSettings>) {
+    persistent_settings.step_ui_scale(-1);
+    persistent_settings.save();
+}
+
+fn raise_ui_scale(_: On<Pointer<Click>>, mut persistent_settings: ResMut<PersistentSettings>) {
+    persistent_settings.step_ui_scale(1);
+    persistent_settings.save();
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct UiScaleLabel;
+
+fn update_ui_scale_label(
+    mut label: Single<&mut Text, With<UiScaleLabel>>,
+    persistent_settings: Res<PersistentSettings>,
+) {
+    label.0 = format!("{}x", persistent_settings.ui_scale);
+}
+
+fn restore_mute(
+    persistent_settings: Res<PersistentSettings>,
+    mut volume_settings: ResMut<VolumeSettings>,
+) {
+    volume_settings.muted = persistent_settings.muted;
+}
+
+/// Save the mute state whenever it's toggled, from the settings screen or the hotkey.
+fn persist_mute(
+    volume_settings: Res<VolumeSettings>,
+    mut persistent_settings: ResMut<PersistentSettings>,
 ) {
-    next_screen.set(previous_state.0.clone());
-    previous_state.0 = GameState::Settings;
+    if persistent_settings.muted == volume_settings.muted {
+        return;
+    }
+    persistent_settings.muted = volume_settings.muted;
+    persistent_settings.save();
 }
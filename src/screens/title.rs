@@ -1,46 +1,73 @@
 //! The title screen that appears when the game starts.
 
-use bevy::prelude::*;
+use bevy::{ecs::spawn::SpawnWith, prelude::*};
 
 use crate::{
-    asset_tracking::ResourceHandles,
-    states::{GameState, PreviousState},
+    asset_tracking::{LoadResource, ResourceHandles},
+    audio::music,
+    game::save::{LoadRequest, save_exists},
+    states::{GameState, ScreenStack},
     theme::prelude::*,
 };
 
+use super::transition::Transition;
+
 pub(super) fn plugin(app: &mut App) {
     app.add_systems(OnEnter(GameState::Title), spawn_title_screen);
+
+    app.register_type::<TitleMusic>();
+    app.load_resource::<TitleMusic>();
+    app.add_systems(OnEnter(GameState::Title), start_title_music);
+    app.add_systems(OnExit(GameState::Title), stop_title_music);
 }
 
 fn spawn_title_screen(mut commands: Commands) {
+    // Only offer to continue when there's something to continue from.
+    let can_continue = save_exists();
     commands.spawn((
         widget::ui_root("Title Screen"),
         DespawnOnExit(GameState::Title),
-        #[cfg(not(target_family = "wasm"))]
-        children![
-            widget::button("Play", enter_loading_or_gameplay_screen),
-            widget::button("Settings", enter_settings_screen),
-            widget::button("Credits", enter_credits_screen),
-            widget::button("Exit", exit_app),
-        ],
-        #[cfg(target_family = "wasm")]
-        children![
-            widget::button("Play", enter_loading_or_gameplay_screen),
-            widget::button("Settings", enter_settings_screen),
-            widget::button("Credits", enter_credits_screen),
-        ],
+        Children::spawn(SpawnWith(move |parent: &mut ChildSpawner| {
+            if can_continue {
+                parent.spawn(widget::button("Continue", continue_saved_game));
+            } else {
+                parent.spawn(widget::button_disabled("Continue"));
+            }
+            parent.spawn(widget::button("Play", enter_loading_or_gameplay_screen));
+            parent.spawn(widget::button("Settings", enter_settings_screen));
+            parent.spawn(widget::button("Credits", enter_credits_screen));
+            #[cfg(not(target_family = "wasm"))]
+            parent.spawn(widget::button("Exit", exit_app));
+        })),
     ));
 }
 
+/// Pick up the last save where the player left off.
+fn continue_saved_game(
+    _: On<Pointer<Click>>,
+    mut load_request: LoadRequest,
+    resource_handles: Res<ResourceHandles>,
+    mut transition: ResMut<Transition>,
+) {
+    if load_request.queue() {
+        enter_gameplay(&resource_handles, &mut transition);
+    }
+}
+
 fn enter_loading_or_gameplay_screen(
     _: On<Pointer<Click>>,
     resource_handles: Res<ResourceHandles>,
-    mut next_screen: ResMut<NextState<GameState>>,
+    mut transition: ResMut<Transition>,
 ) {
+    enter_gameplay(&resource_handles, &mut transition);
+}
+
+/// Head into the game, through the loading screen if the assets aren't ready yet.
+fn enter_gameplay(resource_handles: &ResourceHandles, transition: &mut Transition) {
     if resource_handles.is_all_done() {
-        next_screen.set(GameState::Gameplay);
+        transition.to(GameState::Gameplay);
     } else {
-        next_screen.set(GameState::Loading);
+        transition.to(GameState::Loading);
     }
 }
 
@@ -48,21 +75,63 @@ pub fn enter_settings_screen(
     _: On<Pointer<Click>>,
     mut next_screen: ResMut<NextState<GameState>>,
     current_state: Res<State<GameState>>,
-    mut previous_state: ResMut<PreviousState>,
+    mut screen_stack: ResMut<ScreenStack>,
 ) {
-    previous_state.0 = current_state.get().clone();
+    screen_stack.push(current_state.get().clone());
     next_screen.set(GameState::Settings);
 }
 
 fn enter_credits_screen(
     _: On<Pointer<Click>>,
     mut next_screen: ResMut<NextState<GameState>>,
-    mut previous_state: ResMut<PreviousState>,
+    mut screen_stack: ResMut<ScreenStack>,
 ) {
-    previous_state.0 = GameState::Title;
+    screen_stack.push(GameState::Title);
     next_screen.set(GameState::Credits);
 }
+/// Ask whether to quit, and quit if the player confirms.
 #[cfg(not(target_family = "wasm"))]
-pub fn exit_app(_: On<Pointer<Click>>, mut app_exit: MessageWriter<AppExit>) {
+pub fn exit_app(
+    _: On<Pointer<Click>>,
+    mut commands: Commands,
+    current_state: Res<State<GameState>>,
+) {
+    commands.spawn((
+        widget::confirmation("Quit the game?", quit),
+        DespawnOnExit(current_state.get().clone()),
+    ));
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn quit(_: On<Pointer<Click>>, mut app_exit: MessageWriter<AppExit>) {
     app_exit.write(AppExit::Success);
 }
+
+#[derive(Resource, Asset, Clone, Reflect)]
+#[reflect(Resource)]
+struct TitleMusic {
+    #[dependency]
+    handle: Handle<AudioSource>,
+    entity: Option<Entity>,
+}
+
+impl FromWorld for TitleMusic {
+    fn from_world(world: &mut World) -> Self {
+        let assets = world.resource::<AssetServer>();
+        Self {
+            handle: assets.load("audio/music/Monkeys Spinning Monkeys.ogg"),
+            entity: None,
+        }
+    }
+}
+
+fn start_title_music(mut commands: Commands, mut title_music: ResMut<TitleMusic>) {
+    let handle = title_music.handle.clone();
+    title_music.entity = Some(commands.spawn(music(handle)).id());
+}
+
+fn stop_title_music(mut commands: Commands, mut title_music: ResMut<TitleMusic>) {
+    if let Some(entity) = title_music.entity.take() {
+        commands.entity(entity).despawn();
+    }
+}
@@ -0,0 +1,103 @@
+//! Fading to black and back in around a screen change, instead of cutting straight to it.
+
+use bevy::prelude::*;
+
+use crate::states::GameState;
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<Transition>();
+    app.init_resource::<Transition>();
+    app.add_systems(Startup, spawn_transition_overlay);
+    app.add_systems(Update, update_transition);
+}
+
+/// How long the screen takes to fade out, and then back in.
+const TRANSITION_FADE_SECS: f32 = 0.3;
+
+/// Requests a screen change that fades out, switches to the new [`GameState`] once the
+/// screen is black, and fades back in.
+#[derive(Resource, Debug, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct Transition {
+    phase: TransitionPhase,
+    timer: Timer,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Reflect)]
+enum TransitionPhase {
+    Idle,
+    FadingOut(GameState),
+    FadingIn,
+}
+
+impl Default for Transition {
+    fn default() -> Self {
+        Self {
+            phase: TransitionPhase::Idle,
+            timer: Timer::from_seconds(TRANSITION_FADE_SECS, TimerMode::Once),
+        }
+    }
+}
+
+impl Transition {
+    /// Fade over to `state`. Ignored while another transition is still fading out.
+    pub fn to(&mut self, state: GameState) {
+        if matches!(self.phase, TransitionPhase::FadingOut(_)) {
+            return;
+        }
+        self.phase = TransitionPhase::FadingOut(state);
+        self.timer.reset();
+    }
+}
+
+/// The full-screen node that fades to black. Lives for the whole app.
+#[derive(Component)]
+struct TransitionOverlay;
+
+fn spawn_transition_overlay(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Transition Overlay"),
+        TransitionOverlay,
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            ..default()
+        },
+        BackgroundColor(Color::BLACK.with_alpha(0.0)),
+        // Just below the warp fade, which only ever runs during gameplay.
+        GlobalZIndex(i32::MAX - 1),
+        Pickable::IGNORE,
+    ));
+}
+
+fn update_transition(
+    // Real time, since the pause screen stops virtual time.
+    time: Res<Time<Real>>,
+    mut transition: ResMut<Transition>,
+    mut next_screen: ResMut<NextState<GameState>>,
+    mut overlay_q: Query<&mut BackgroundColor, With<TransitionOverlay>>,
+) {
+    if transition.phase == TransitionPhase::Idle {
+        return;
+    }
+    transition.timer.tick(time.delta());
+    let progress = transition.timer.fraction();
+    let alpha = match transition.phase {
+        TransitionPhase::FadingOut(_) => progress,
+        _ => 1.0 - progress,
+    };
+    for mut background in &mut overlay_q {
+        background.0 = Color::BLACK.with_alpha(alpha);
+    }
+
+    if !transition.timer.is_finished() {
+        return;
+    }
+    let phase = std::mem::replace(&mut transition.phase, TransitionPhase::Idle);
+    if let TransitionPhase::FadingOut(state) = phase {
+        next_screen.set(state);
+        transition.phase = TransitionPhase::FadingIn;
+        transition.timer.reset();
+    }
+}
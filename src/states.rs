@@ -3,8 +3,10 @@ use bevy::prelude::*;
 use crate::AppSystems;
 pub(super) fn plugin(app: &mut App) {
     app.register_type::<VisibleInState>()
+        .register_type::<HiddenByState>()
         .register_type::<DestroyOnEnter>()
-        .init_resource::<PreviousState>()
+        .register_type::<ScreenStack>()
+        .init_resource::<ScreenStack>()
         .add_systems(
             Update,
             (visible_in_state, destroy_on_enter)
@@ -12,16 +14,24 @@ pub(super) fn plugin(app: &mut App) {
                 .in_set(AppSystems::PreUpdate),
         );
 }
+/// Despawns the entity, along with all of its descendants, when entering one of the listed states.
 #[derive(Component, Clone, Reflect)]
 pub struct DestroyOnEnter(pub Vec<GameState>);
 
-#[derive(Resource, Clone, Reflect)]
-pub struct PreviousState(pub GameState);
+/// The screens to go back to, most recent last. Menus push the screen they were opened
+/// from and pop it when closed, so going back from nested menus retraces the way in.
+#[derive(Resource, Debug, Default, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct ScreenStack(Vec<GameState>);
 
-impl FromWorld for PreviousState {
-    fn from_world(world: &mut World) -> Self {
-        let state = world.resource::<State<GameState>>();
-        Self(state.get().clone())
+impl ScreenStack {
+    pub fn push(&mut self, state: GameState) {
+        self.0.push(state);
+    }
+
+    /// The screen to go back to, if any.
+    pub fn pop(&mut self) -> Option<GameState> {
+        self.0.pop()
     }
 }
 
@@ -32,23 +42,43 @@ fn destroy_on_enter(
 ) {
     for (entity, destroy_on) in query.iter() {
         if destroy_on.0.contains(&*current_state) {
-            commands.entity(entity).despawn();
+            // Despawning is recursive, so a tagged child may already be gone with its parent.
+            commands.entity(entity).try_despawn();
         }
     }
 }
 
+/// Hides the entity outside of the listed states. Only the hiding is owned by this:
+/// whatever visibility the entity had before is put back once it is shown again.
 #[derive(Component, Clone, Reflect)]
 pub struct VisibleInState(pub Vec<GameState>);
 
+/// The visibility an entity had before [`VisibleInState`] hid it.
+#[derive(Component, Clone, Reflect)]
+struct HiddenByState(Visibility);
+
 fn visible_in_state(
-    mut query: Query<(&mut Visibility, &VisibleInState)>,
+    mut commands: Commands,
+    mut query: Query<(
+        Entity,
+        &mut Visibility,
+        &VisibleInState,
+        Option<&HiddenByState>,
+    )>,
     current_state: Res<State<GameState>>,
 ) {
-    for (mut visibility, state) in query.iter_mut() {
-        if state.0.contains(&*current_state) {
-            *visibility = Visibility::Visible;
-        } else {
-            *visibility = Visibility::Hidden;
+    for (entity, mut visibility, state, hidden_by_state) in query.iter_mut() {
+        let visible = state.0.contains(&*current_state);
+        match (visible, hidden_by_state) {
+            (true, Some(hidden_by_state)) => {
+                *visibility = hidden_by_state.0;
+                commands.entity(entity).remove::<HiddenByState>();
+            }
+            (false, None) => {
+                commands.entity(entity).insert(HiddenByState(*visibility));
+                *visibility = Visibility::Hidden;
+            }
+            _ => {}
         }
     }
 }
@@ -62,6 +92,61 @@ pub enum GameState {
     Credits,
     Settings,
     Loading,
-    Pausing,
     Gameplay,
+    GameOver,
+}
+
+/// Whether the player is in a game or somewhere in the menus around it, computed from
+/// [`GameState`]. For systems that only care which side of that line they're on.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum AppState {
+    Menu,
+    InGame,
+}
+
+impl ComputedStates for AppState {
+    type SourceStates = GameState;
+
+    fn compute(screen: GameState) -> Option<Self> {
+        Some(match screen {
+            GameState::Gameplay => AppState::InGame,
+            _ => AppState::Menu,
+        })
+    }
+}
+
+/// Whether the game in progress is running or sitting in the pause menu. Only exists
+/// during [`AppState::InGame`], and starts out running each time a game is entered.
+#[derive(SubStates, Debug, Hash, PartialEq, Eq, Clone, Copy, Default, Reflect)]
+#[source(AppState = AppState::InGame)]
+pub enum GameplayState {
+    #[default]
+    Running,
+    Paused,
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    #[test]
+    fn destroy_on_enter_despawns_the_whole_hierarchy() {
+        let mut world = World::new();
+        world.insert_resource(State::new(GameState::Title));
+        let tag = || DestroyOnEnter(vec![GameState::Title]);
+        let parent = world.spawn(tag()).id();
+        // A tagged child, and an untagged grandchild below it.
+        let child = world.spawn((tag(), ChildOf(parent))).id();
+        let grandchild = world.spawn(ChildOf(child)).id();
+        let survivor = world.spawn(DestroyOnEnter(vec![GameState::GameOver])).id();
+
+        world.run_system_once(destroy_on_enter).unwrap();
+
+        for entity in [parent, child, grandchild] {
+            assert!(world.get_entity(entity).is_err(), "{entity} survived");
+        }
+        assert!(world.get_entity(survivor).is_ok());
+    }
 }
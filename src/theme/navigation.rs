@@ -0,0 +1,175 @@
+//! Driving menus without a mouse: arrow keys, WASD or a gamepad d-pad move the focus
+//! between buttons, and Enter, Space or the gamepad's south button presses the focused one.
+
+use std::time::Duration;
+
+use bevy::{
+    camera::NormalizedRenderTarget,
+    picking::{
+        backend::HitData,
+        pointer::{Location, PointerId},
+    },
+    prelude::*,
+    ui::UiGlobalTransform,
+    window::{PrimaryWindow, WindowRef},
+};
+
+use crate::theme::{palette::FOCUS_OUTLINE, widget::Modal};
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<FocusedButton>();
+    app.init_resource::<FocusedButton>();
+    app.add_systems(Update, (navigate_buttons, highlight_focused_button).chain());
+}
+
+/// The button that keyboard and gamepad input acts on, if any.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub struct FocusedButton(pub Option<Entity>);
+
+/// Marks the button currently drawn with the focus outline.
+#[derive(Component)]
+struct FocusOutline;
+
+const PREVIOUS_KEYS: [KeyCode; 4] = [
+    KeyCode::ArrowUp,
+    KeyCode::ArrowLeft,
+    KeyCode::KeyW,
+    KeyCode::KeyA,
+];
+const NEXT_KEYS: [KeyCode; 4] = [
+    KeyCode::ArrowDown,
+    KeyCode::ArrowRight,
+    KeyCode::KeyS,
+    KeyCode::KeyD,
+];
+const ACTIVATE_KEYS: [KeyCode; 3] = [KeyCode::Enter, KeyCode::NumpadEnter, KeyCode::Space];
+const PREVIOUS_GAMEPAD_BUTTONS: [GamepadButton; 2] =
+    [GamepadButton::DPadUp, GamepadButton::DPadLeft];
+const NEXT_GAMEPAD_BUTTONS: [GamepadButton; 2] =
+    [GamepadButton::DPadDown, GamepadButton::DPadRight];
+
+/// Move the focus through the visible buttons in reading order, and click the focused one.
+fn navigate_buttons(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    mut focused: ResMut<FocusedButton>,
+    button_q: Query<
+        (
+            Entity,
+            &UiGlobalTransform,
+            &ComputedNode,
+            &InheritedVisibility,
+        ),
+        With<Button>,
+    >,
+    modal_q: Query<Entity, With<Modal>>,
+    parent_q: Query<&ChildOf>,
+    window_q: Query<Entity, With<PrimaryWindow>>,
+    camera_q: Query<Entity, With<IsDefaultUiCamera>>,
+) {
+    let pressed = |keys: &[KeyCode], buttons: &[GamepadButton]| {
+        keyboard.any_just_pressed(keys.iter().copied())
+            || gamepads
+                .iter()
+                .any(|gamepad| gamepad.any_just_pressed(buttons.iter().copied()))
+    };
+    let step: isize = if pressed(&PREVIOUS_KEYS, &PREVIOUS_GAMEPAD_BUTTONS) {
+        -1
+    } else if pressed(&NEXT_KEYS, &NEXT_GAMEPAD_BUTTONS) {
+        1
+    } else {
+        0
+    };
+    let activate = pressed(&ACTIVATE_KEYS, &[GamepadButton::South]);
+    if step == 0 && !activate {
+        return;
+    }
+
+    // Buttons behind an open modal can't be reached until it closes.
+    let modal = modal_q.iter().last();
+    let reachable = |button: Entity| {
+        modal.is_none_or(|modal| {
+            parent_q
+                .iter_ancestors(button)
+                .any(|ancestor| ancestor == modal)
+        })
+    };
+    // Top to bottom, then left to right, like the buttons read on screen.
+    let mut buttons: Vec<_> = button_q
+        .iter()
+        .filter(|&(entity, _, node, visibility)| {
+            visibility.get() && !node.is_empty() && reachable(entity)
+        })
+        .map(|(entity, transform, node, _)| (entity, transform.translation, node))
+        .collect();
+    buttons.sort_by(|(_, a, _), (_, b, _)| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)));
+    if buttons.is_empty() {
+        focused.0 = None;
+        return;
+    }
+
+    let current = focused
+        .0
+        .and_then(|entity| buttons.iter().position(|&(button, ..)| button == entity));
+    let Some(index) = current else {
+        // The first press after a screen change only picks a button to start from.
+        focused.0 = Some(buttons[0].0);
+        return;
+    };
+    if step != 0 {
+        let next = (index as isize + step).rem_euclid(buttons.len() as isize) as usize;
+        focused.0 = Some(buttons[next].0);
+        return;
+    }
+
+    // Click the button like the mouse would, so its observer runs as usual.
+    let (entity, position, node) = buttons[index];
+    let (Ok(window), Ok(camera)) = (window_q.single(), camera_q.single()) else {
+        return;
+    };
+    let Some(window) = WindowRef::Primary.normalize(Some(window)) else {
+        return;
+    };
+    let location = Location {
+        target: NormalizedRenderTarget::Window(window),
+        position: position * node.inverse_scale_factor(),
+    };
+    let click = Click {
+        button: PointerButton::Primary,
+        hit: HitData::new(camera, 0.0, None, None),
+        duration: Duration::ZERO,
+    };
+    commands.trigger(Pointer::new(PointerId::Mouse, location, click, entity));
+}
+
+/// Outline the focused button, and only that one.
+fn highlight_focused_button(
+    mut commands: Commands,
+    focused: Res<FocusedButton>,
+    outlined_q: Query<Entity, With<FocusOutline>>,
+) {
+    for entity in &outlined_q {
+        if focused.0 != Some(entity) {
+            commands.entity(entity).remove::<(FocusOutline, Outline)>();
+        }
+    }
+    let Some(entity) = focused.0 else {
+        return;
+    };
+    if outlined_q.contains(entity) {
+        return;
+    }
+    // The button may have been despawned along with its screen.
+    if let Ok(mut button) = commands.get_entity(entity) {
+        button.try_insert((
+            FocusOutline,
+            Outline {
+                width: Val::Px(4.0),
+                offset: Val::Px(2.0),
+                color: FOCUS_OUTLINE,
+            },
+        ));
+    }
+}
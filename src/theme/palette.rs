@@ -14,3 +14,23 @@ pub const BUTTON_BACKGROUND: Color = Color::srgb(0.275, 0.400, 0.750);
 pub const BUTTON_HOVERED_BACKGROUND: Color = Color::srgb(0.384, 0.600, 0.820);
 /// #3d4999
 pub const BUTTON_PRESSED_BACKGROUND: Color = Color::srgb(0.239, 0.286, 0.600);
+/// #a0a0a0
+pub const BUTTON_DISABLED_TEXT: Color = Color::srgb(0.627, 0.627, 0.627);
+/// #5a5f73
+pub const BUTTON_DISABLED_BACKGROUND: Color = Color::srgb(0.353, 0.373, 0.451);
+
+/// #fcfbcc
+pub const FOCUS_OUTLINE: Color = Color::srgb(0.988, 0.984, 0.800);
+
+/// #fcfbcc
+pub const TOAST_TEXT: Color = Color::srgb(0.988, 0.984, 0.800);
+/// #1e2338 at 85% opacity
+pub const TOAST_BACKGROUND: Color = Color::srgba(0.118, 0.137, 0.220, 0.85);
+
+/// Black at 70% opacity
+pub const MODAL_BACKDROP: Color = Color::srgba(0.0, 0.0, 0.0, 0.7);
+
+/// #3d4999
+pub const PROGRESS_BAR_BACKGROUND: Color = Color::srgb(0.239, 0.286, 0.600);
+/// #6299d1
+pub const PROGRESS_BAR_FILL: Color = Color::srgb(0.384, 0.600, 0.820);
@@ -47,6 +47,102 @@ pub fn label(text: impl Into<String>) -> impl Bundle {
     )
 }
 
+/// A short message on a dark rounded backing, for toasts.
+pub fn toast(text: impl Into<String>) -> impl Bundle {
+    (
+        Name::new("Toast"),
+        Node {
+            padding: UiRect::axes(Px(12.0), Px(6.0)),
+            ..default()
+        },
+        BorderRadius::all(Px(6.0)),
+        BackgroundColor(TOAST_BACKGROUND),
+        children![(
+            Name::new("Toast Text"),
+            Text(text.into()),
+            TextFont::from_font_size(20.0),
+            TextColor(TOAST_TEXT),
+        )],
+    )
+}
+
+/// The filled part of a [`progress_bar`]. Set its [`Node::width`] to a percentage to show progress.
+#[derive(Component, Debug, Clone, Copy, Default, Reflect)]
+#[reflect(Component)]
+pub struct ProgressBarFill;
+
+/// A horizontal bar that starts empty and fills up through its [`ProgressBarFill`] child.
+pub fn progress_bar() -> impl Bundle {
+    (
+        Name::new("Progress Bar"),
+        Node {
+            width: Px(400.0),
+            height: Px(24.0),
+            ..default()
+        },
+        BackgroundColor(PROGRESS_BAR_BACKGROUND),
+        BorderRadius::MAX,
+        children![(
+            Name::new("Progress Bar Fill"),
+            ProgressBarFill,
+            Node {
+                width: Percent(0.0),
+                height: Percent(100.0),
+                ..default()
+            },
+            BackgroundColor(PROGRESS_BAR_FILL),
+            BorderRadius::MAX,
+        )],
+    )
+}
+
+/// Marks a modal dialog. While one is open, menu navigation stays inside it.
+#[derive(Component, Debug, Clone, Copy, Default, Reflect)]
+#[reflect(Component)]
+pub struct Modal;
+
+/// A modal dialog asking `question`, which runs `on_confirm` if the player answers yes.
+/// Either answer closes it, and the backdrop blocks clicks on whatever is behind it.
+pub fn confirmation<E, B, M, I>(question: impl Into<String>, on_confirm: I) -> impl Bundle
+where
+    E: Event + EntityEvent,
+    B: Bundle,
+    I: IntoObserverSystem<E, B, M>,
+{
+    let question = question.into();
+    let on_confirm = IntoObserverSystem::into_system(on_confirm);
+    (
+        Name::new("Confirmation"),
+        Modal,
+        Node {
+            position_type: PositionType::Absolute,
+            width: Percent(100.0),
+            height: Percent(100.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            flex_direction: FlexDirection::Column,
+            row_gap: Px(20.0),
+            ..default()
+        },
+        BackgroundColor(MODAL_BACKDROP),
+        GlobalZIndex(MODAL_Z_INDEX),
+        Children::spawn(SpawnWith(|parent: &mut ChildSpawner| {
+            let dialog = parent.target_entity();
+            let close = move |_: On<Pointer<Click>>, mut commands: Commands| {
+                commands.entity(dialog).try_despawn();
+            };
+            parent.spawn(header(question));
+            parent.spawn(button("Yes", on_confirm)).observe(close);
+            parent
+                .spawn(button("No", |_: On<Pointer<Click>>| {}))
+                .observe(close);
+        })),
+    )
+}
+
+/// Draws modals above the screens they're opened from.
+const MODAL_Z_INDEX: i32 = 100;
+
 /// A large rounded button with text and an action defined as an [`Observer`].
 pub fn button<E, B, M, I>(text: impl Into<String>, action: I) -> impl Bundle
 where
@@ -67,6 +163,34 @@ where
             },
             BorderRadius::MAX,
         ),
+        (),
+    )
+}
+
+/// A greyed out, large rounded button for an action that isn't available right now.
+/// It can't be hovered, focused or clicked.
+pub fn button_disabled(text: impl Into<String>) -> impl Bundle {
+    (
+        Name::new("Button"),
+        Node::default(),
+        children![(
+            Name::new("Button Inner"),
+            Node {
+                width: Px(300.0),
+                height: Px(80.0),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            BorderRadius::MAX,
+            BackgroundColor(BUTTON_DISABLED_BACKGROUND),
+            children![(
+                Name::new("Button Text"),
+                Text(text.into()),
+                TextFont::from_font_size(40.0),
+                TextColor(BUTTON_DISABLED_TEXT),
+            )],
+        )],
     )
 }
 
@@ -87,14 +211,39 @@ where
             justify_content: JustifyContent::Center,
             ..default()
         },
+        (),
+    )
+}
+
+/// A button that flips an on/off setting. It starts out without text, and `text_marker` tags
+/// its text so a system can show the setting's state in it.
+pub fn button_toggle<E, B, M, I>(text_marker: impl Component, action: I) -> impl Bundle
+where
+    E: Event + EntityEvent,
+    B: Bundle,
+    I: IntoObserverSystem<E, B, M>,
+{
+    button_base(
+        "",
+        action,
+        Node {
+            width: Px(90.0),
+            height: Px(30.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        text_marker,
     )
 }
 
-/// A simple button with text and an action defined as an [`Observer`]. The button's layout is provided by `button_bundle`.
+/// A simple button with text and an action defined as an [`Observer`]. The button's layout is provided by `button_bundle`,
+/// and `text_bundle` is added to its text.
 fn button_base<E, B, M, I>(
     text: impl Into<String>,
     action: I,
     button_bundle: impl Bundle,
+    text_bundle: impl Bundle,
 ) -> impl Bundle
 where
     E: Event + EntityEvent,
@@ -122,6 +271,7 @@ where
                         Text(text),
                         TextFont::from_font_size(40.0),
                         TextColor(BUTTON_TEXT),
+                        text_bundle,
                     )],
                 ))
                 .insert(button_bundle)
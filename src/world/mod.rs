@@ -1,5 +1,6 @@
 pub mod tiledhelper;
 pub mod tilemap;
+mod warp;
 use bevy::prelude::*;
 
 pub(super) fn plugin(app: &mut App) {
@@ -7,5 +8,6 @@ pub(super) fn plugin(app: &mut App) {
         tilemap::plugin,
         bevy_ecs_tilemap::TilemapPlugin,
         tiledhelper::TiledPlugin,
+        warp::plugin,
     ));
 }
@@ -10,29 +10,39 @@
 //
 // Functional limitations:
 //   * When the 'atlas' feature is enabled tilesets using a collection of images will be skipped.
-//   * Only finite tile layers are loaded. Infinite tile layers and object layers will be skipped.
+//   * Only finite tile layers are loaded. Infinite tile layers will be skipped, and object layers
+//     are only read through `TiledMap::objects` (e.g. for entry points and warps).
 
 use std::io::Cursor;
 use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 
-use bevy::input::ButtonState;
-use bevy::input::mouse::MouseButtonInput;
-use bevy::log::{info, warn};
+use bevy::log::{debug, info, warn};
 use bevy::prelude::*;
 use bevy::reflect::Reflect;
 use bevy::{
-    asset::{AssetLoader, AssetPath, io::Reader},
-    platform::collections::HashMap,
+    asset::{
+        AssetLoadFailedEvent, AssetLoader, AssetPath, LoadContext, RenderAssetUsages, io::Reader,
+    },
+    image::{ImageLoaderSettings, ImageSampler},
+    platform::collections::{HashMap, HashSet},
     reflect::TypePath,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
 };
 use bevy_ecs_tilemap::prelude::*;
 use thiserror::Error;
 
 use crate::AppSystems;
-use crate::constants::TILE_SCALE;
-use crate::game::camera::CursorPos;
-use crate::states::{DestroyOnEnter, GameState, VisibleInState};
+use crate::constants::{TILE_SCALE, TILE_SIZE};
+use crate::game::animation::{ActionType, Facing, TileInteraction, facing_tile_position};
+use crate::game::camera::ScreenShake;
+use crate::game::inventory::{ItemKind, pickup};
+use crate::game::level::LevelSpawnSystems;
+use crate::game::player::Player;
+use crate::game::y_sort::YSort;
+use crate::states::{DestroyOnEnter, GameState, GameplayState, VisibleInState};
+use crate::world::tilemap::{CurrentMap, TileQuery};
 
 #[derive(Default)]
 pub struct TiledPlugin;
@@ -42,15 +52,40 @@ impl Plugin for TiledPlugin {
         app.init_asset::<TiledMap>()
             .register_asset_loader(TiledLoader)
             .register_type::<TileType>()
+            .register_type::<ChoppedTiles>()
+            .register_type::<TileAnimation>()
+            .register_type::<TallTile>()
+            .register_type::<MapDimensions>()
+            .init_resource::<ChoppedTiles>()
+            .init_resource::<MapDimensions>()
+            .add_systems(
+                OnEnter(GameState::Gameplay),
+                reset_chopped_tiles.in_set(LevelSpawnSystems),
+            )
             .add_systems(
                 Update,
                 (
                     process_loaded_maps,
-                    (handle_mouse_highlight, apply_highlight_effect)
-                        .run_if(on_message::<MouseButtonInput>.and(in_state(GameState::Gameplay))),
+                    remove_chopped_tiles,
+                    (highlight_facing_tile, apply_highlight_effect)
+                        .chain()
+                        .run_if(in_state(GameplayState::Running)),
                 )
                     .chain()
                     .in_set(AppSystems::PreUpdate),
+            )
+            .add_systems(
+                Update,
+                (chop_tiles, animate_tiles)
+                    .run_if(in_state(GameplayState::Running))
+                    .in_set(AppSystems::PostUpdate),
+            )
+            .add_systems(Update, replace_missing_tileset_images)
+            .add_systems(
+                Update,
+                sync_tall_tile_sprites
+                    .after(animate_tiles)
+                    .in_set(AppSystems::PostUpdate),
             );
     }
 }
@@ -67,8 +102,148 @@ pub enum TileType {
 #[derive(Component, Debug, Clone, Copy, Reflect)]
 pub struct Obstacle;
 
-#[derive(Component)]
-struct HighlightedTile;
+/// A tile (e.g. a tree or bush) that the player can remove by chopping it.
+#[derive(Component, Debug, Clone, Copy, Reflect)]
+pub struct Choppable;
+
+/// A tile that characters can walk behind, like a tree. Its layer is drawn as a whole, so
+/// the tile is hidden there and drawn as its own y-sorted sprite instead. It stays in the
+/// layer's [`TileStorage`], so it is still found, chopped and highlighted like any other tile.
+#[derive(Component, Debug, Clone, Copy, Reflect)]
+#[reflect(Component)]
+pub struct TallTile;
+
+/// A Tiled tile animation that [`AnimatedTile`] can't express, because its frames
+/// aren't consecutive in the tileset or don't all last as long. Cycled on the CPU instead.
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct TileAnimation {
+    /// Texture index and duration of each frame, as authored in Tiled.
+    frames: Vec<(u32, Duration)>,
+    current: usize,
+    elapsed: Duration,
+}
+
+/// Animate a tile from Tiled's `(texture index, duration in ms)` frames. Uses bevy_ecs_tilemap's
+/// GPU-side [`AnimatedTile`] when the frames allow it and the tile is drawn by its layer
+/// (`on_gpu`), and [`TileAnimation`] otherwise.
+fn insert_tile_animation(tile: &mut EntityCommands, frames: &[(u32, u32)], on_gpu: bool) {
+    let [(first_index, first_duration), ..] = *frames else {
+        return;
+    };
+    if frames.len() < 2 {
+        return;
+    }
+    let consecutive = frames.windows(2).all(|pair| pair[1].0 == pair[0].0 + 1);
+    let uniform = frames
+        .iter()
+        .all(|&(_, duration)| duration == first_duration);
+    if on_gpu && consecutive && uniform && first_duration > 0 {
+        tile.insert(AnimatedTile {
+            start: first_index,
+            end: first_index + frames.len() as u32,
+            // Frames per second.
+            speed: 1000.0 / first_duration as f32,
+        });
+    } else {
+        tile.insert(TileAnimation {
+            frames: frames
+                .iter()
+                .map(|&(index, duration)| (index, Duration::from_millis(duration.into())))
+                .collect(),
+            current: 0,
+            elapsed: Duration::ZERO,
+        });
+    }
+}
+
+/// Convert Tiled's flip flags into a [`TileFlip`]. Its x, y and d flags are the
+/// horizontal, vertical and diagonal flags of Tiled's tile data, so they carry straight over:
+///
+/// | Tiled (h, v, d) | In the editor                     | `TileFlip` (x, y, d) |
+/// |-----------------|-----------------------------------|----------------------|
+/// | -, -, -         | as authored                       | -, -, -              |
+/// | h, -, -         | mirrored horizontally             | x, -, -              |
+/// | -, v, -         | mirrored vertically               | -, y, -              |
+/// | h, v, -         | rotated 180°                      | x, y, -              |
+/// | -, -, d         | mirrored along the diagonal       | -, -, d              |
+/// | h, -, d         | rotated 90° clockwise             | x, -, d              |
+/// | -, v, d         | rotated 90° counterclockwise      | -, y, d              |
+/// | h, v, d         | mirrored along the other diagonal | x, y, d              |
+///
+/// `assets/tilemaps/flip_test.tmx` holds one tile in each of these orientations.
+fn tile_flip(flip_h: bool, flip_v: bool, flip_d: bool) -> TileFlip {
+    TileFlip {
+        x: flip_h,
+        y: flip_v,
+        d: flip_d,
+    }
+}
+
+fn animate_tiles(
+    time: Res<Time>,
+    mut animation_q: Query<(&mut TileAnimation, &mut TileTextureIndex)>,
+) {
+    for (mut animation, mut texture_index) in animation_q.iter_mut() {
+        animation.elapsed += time.delta();
+        let start = animation.current;
+        // Skip over as many frames as have elapsed, stopping if none of them take any time.
+        while animation.elapsed >= animation.frames[animation.current].1 {
+            let duration = animation.frames[animation.current].1;
+            animation.elapsed -= duration;
+            animation.current = (animation.current + 1) % animation.frames.len();
+            if animation.current == start && duration.is_zero() {
+                break;
+            }
+        }
+        let index = animation.frames[animation.current].0;
+        if texture_index.0 != index {
+            texture_index.0 = index;
+        }
+    }
+}
+
+/// Positions of the [`Choppable`] tiles chopped down so far on each map, by map name, so
+/// they stay gone when the map is spawned again (e.g. coming back to it or loading a save).
+#[derive(Resource, Debug, Default, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct ChoppedTiles(pub HashMap<String, HashSet<UVec2>>);
+
+impl ChoppedTiles {
+    pub fn insert(&mut self, map: &str, tile_pos: TilePos) {
+        self.0
+            .entry(map.to_string())
+            .or_default()
+            .insert(tile_pos.into());
+    }
+
+    pub fn contains(&self, map: &str, tile_pos: TilePos) -> bool {
+        self.0
+            .get(map)
+            .is_some_and(|tiles| tiles.contains(&UVec2::from(tile_pos)))
+    }
+}
+
+/// Marks a highlighted tile, remembering its color so it can be restored afterwards.
+#[derive(Component, Default)]
+pub(crate) struct HighlightedTile {
+    original_color: Color,
+}
+
+/// Give a tile a new color, e.g. to show that it was tilled. A highlighted tile keeps
+/// showing the highlight and takes on `color` once it stops being highlighted.
+pub(crate) fn set_tile_color(
+    tile_color: &mut TileColor,
+    highlight: Option<&mut HighlightedTile>,
+    color: Color,
+) {
+    match highlight {
+        Some(highlight) => highlight.original_color = color,
+        None => tile_color.0 = color,
+    }
+}
+
+const HIGHLIGHT_COLOR: Color = Color::srgba(1.0, 0.95, 0.3, 0.75);
 
 #[derive(TypePath, Asset)]
 pub struct TiledMap {
@@ -80,10 +255,111 @@ pub struct TiledMap {
     pub tile_image_offsets: HashMap<(usize, tiled::TileId), u32>,
 }
 
-// Stores a list of tiled layers.
+impl TiledMap {
+    /// World position of the object called `name` on any of the map's object layers.
+    pub fn object_position(&self, name: &str) -> Option<Vec2> {
+        let object = self.objects().find(|object| object.name == name)?;
+        Some(self.to_world(Vec2::new(object.x, object.y)))
+    }
+
+    /// Every object on the map's object layers.
+    pub fn objects(&self) -> impl Iterator<Item = tiled::Object<'_>> {
+        self.map
+            .layers()
+            .filter_map(|layer| layer.as_object_layer())
+            .flat_map(|object_layer| object_layer.objects())
+    }
+
+    /// The images the map's tilesets are drawn from, as handed out by [`TiledLoader`], each
+    /// with Tiled's description of it.
+    fn tileset_images(&self) -> Vec<(AssetId<Image>, tiled::Image)> {
+        let mut images = Vec::new();
+        for (tileset_index, tileset) in self.map.tilesets().iter().enumerate() {
+            match (self.tilemap_textures.get(&tileset_index), &tileset.image) {
+                (Some(TilemapTexture::Single(handle)), Some(image)) => {
+                    images.push((handle.id(), image.clone()));
+                }
+                (Some(TilemapTexture::Vector(handles)), None) => {
+                    for (tile_id, tile) in tileset.tiles() {
+                        let handle = self
+                            .tile_image_offsets
+                            .get(&(tileset_index, tile_id))
+                            .and_then(|&offset| handles.get(offset as usize));
+                        if let (Some(handle), Some(image)) = (handle, &tile.image) {
+                            images.push((handle.id(), image.clone()));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        images
+    }
+
+    /// Convert a position in Tiled's pixel coordinates into a world position.
+    pub fn to_world(&self, tiled_pos: Vec2) -> Vec2 {
+        // Tiled measures from the top-left corner with y pointing down, while the
+        // layers are anchored at their center with y pointing up.
+        let map_size = Vec2::new(
+            (self.map.width * self.map.tile_width) as f32,
+            (self.map.height * self.map.tile_height) as f32,
+        );
+        Vec2::new(
+            tiled_pos.x - map_size.x / 2.0,
+            map_size.y / 2.0 - tiled_pos.y,
+        ) * TILE_SCALE
+    }
+}
+
+/// Size of the most recently loaded map, as its TMX file declares it. The camera, the screen
+/// wrap and the minimap fit themselves to this. Before any map has loaded it holds the size
+/// of the farm.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub struct MapDimensions {
+    /// Size of the map in tiles.
+    pub tiles: UVec2,
+    /// Size of a tile in the map's pixels, before [`TILE_SCALE`].
+    pub tile_size: UVec2,
+}
+
+impl Default for MapDimensions {
+    fn default() -> Self {
+        Self {
+            tiles: UVec2::new(54, 36),
+            tile_size: UVec2::splat(TILE_SIZE as u32),
+        }
+    }
+}
+
+impl MapDimensions {
+    fn of(map: &tiled::Map) -> Self {
+        let dimensions = Self {
+            tiles: UVec2::new(map.width, map.height),
+            tile_size: UVec2::new(map.tile_width, map.tile_height),
+        };
+        // Reaching for the tile in front of the player, hitboxes and interaction ranges are
+        // all measured in `TILE_SIZE`, so a map on a different grid would be out of step.
+        if dimensions.tile_size != UVec2::splat(TILE_SIZE as u32) {
+            warn!(
+                "Map tiles are {} pixels but the game expects {TILE_SIZE}",
+                dimensions.tile_size
+            );
+        }
+        dimensions
+    }
+
+    /// Size of the map in world units.
+    pub fn world_size(&self) -> Vec2 {
+        (self.tiles * self.tile_size).as_vec2() * TILE_SCALE
+    }
+}
+
+/// Every tilemap layer entity spawned for a map: one per combination of tileset and tile
+/// layer, so they can all be torn down when the map is reloaded.
 #[derive(Component, Default)]
 pub struct TiledLayersStorage {
-    pub storage: HashMap<u32, Entity>,
+    pub storage: HashMap<(usize, u32), Entity>,
 }
 
 #[derive(Component, Default)]
@@ -129,6 +405,117 @@ pub enum TiledAssetLoaderError {
     Io(#[from] std::io::Error),
 }
 
+/// Sample tileset images without filtering, so neighbouring tiles don't bleed into each other.
+fn nearest_sampling(settings: &mut ImageLoaderSettings) {
+    settings.sampler = ImageSampler::nearest();
+}
+
+/// Drawn in place of missing tileset images, so the tiles stand out instead of vanishing.
+const MISSING_IMAGE_COLOR: [u8; 4] = [255, 0, 255, 255];
+
+/// A magenta stand-in for a missing image of the given size.
+fn missing_image_placeholder(image: &tiled::Image) -> Image {
+    let mut placeholder = Image::new_fill(
+        Extent3d {
+            width: image.width.max(1) as u32,
+            height: image.height.max(1) as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &MISSING_IMAGE_COLOR,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    placeholder.sampler = ImageSampler::nearest();
+    placeholder
+}
+
+/// Load a tileset or tile image. If the file turns out to be missing,
+/// [`replace_missing_tileset_images`] puts a magenta placeholder in its place.
+fn load_tileset_image(
+    load_context: &mut LoadContext<'_>,
+    asset_path: AssetPath<'static>,
+) -> Handle<Image> {
+    load_context
+        .loader()
+        .with_settings(nearest_sampling)
+        .load(asset_path)
+}
+
+/// Give every tileset image that failed to load a magenta placeholder, since a broken handle
+/// would leave its tiles silently invisible. A map can come in before or after its images
+/// fail, so this looks again whenever either happens.
+fn replace_missing_tileset_images(
+    mut failed_images: MessageReader<AssetLoadFailedEvent<Image>>,
+    mut map_events: MessageReader<AssetEvent<TiledMap>>,
+    maps: Res<Assets<TiledMap>>,
+    asset_server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let image_failed = failed_images.read().count() > 0;
+    let map_changed = map_events.read().any(|event| {
+        matches!(
+            event,
+            AssetEvent::Added { .. } | AssetEvent::Modified { .. }
+        )
+    });
+    if !image_failed && !map_changed {
+        return;
+    }
+    for (_, tiled_map) in maps.iter() {
+        for (id, image) in tiled_map.tileset_images() {
+            if images.contains(id) || !asset_server.load_state(id).is_failed() {
+                continue;
+            }
+            warn!(
+                "Tileset image {} is missing, drawing its tiles magenta",
+                image.source.display()
+            );
+            if let Err(error) = images.insert(id, missing_image_placeholder(&image)) {
+                warn!(
+                    "Could not add a placeholder for {}: {error}",
+                    image.source.display()
+                );
+            }
+        }
+    }
+}
+
+/// Cut the margin Tiled leaves around a tileset's tiles off its image, so the first tile sits
+/// in the top-left corner where bevy_ecs_tilemap expects it. The spacing between tiles is kept.
+fn crop_tileset_margin(image: &Image, tileset: &tiled::Tileset) -> Option<Image> {
+    let data = image.data.as_ref()?;
+    let bytes_per_pixel = image.texture_descriptor.format.block_copy_size(None)? as usize;
+    let columns = tileset.columns;
+    let rows = tileset.tilecount.div_ceil(columns.max(1));
+    let width = columns * tileset.tile_width + columns.saturating_sub(1) * tileset.spacing;
+    let height = rows * tileset.tile_height + rows.saturating_sub(1) * tileset.spacing;
+    if tileset.margin + width > image.width() || tileset.margin + height > image.height() {
+        return None;
+    }
+
+    let margin = tileset.margin as usize;
+    let row_bytes = width as usize * bytes_per_pixel;
+    let mut cropped = Vec::with_capacity(row_bytes * height as usize);
+    for y in 0..height as usize {
+        let start = ((margin + y) * image.width() as usize + margin) * bytes_per_pixel;
+        cropped.extend_from_slice(data.get(start..start + row_bytes)?);
+    }
+    let mut cropped = Image::new(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        cropped,
+        image.texture_descriptor.format,
+        image.asset_usage,
+    );
+    cropped.sampler = image.sampler.clone();
+    Some(cropped)
+}
+
 impl AssetLoader for TiledLoader {
     type Asset = TiledMap;
     type Settings = ();
@@ -173,7 +560,7 @@ impl AssetLoader for TiledLoader {
                                 info!(
                                     "Loading tile image from {asset_path:?} as image ({tileset_index}, {tile_id})"
                                 );
-                                let texture: Handle<Image> = load_context.load(asset_path.clone());
+                                let texture = load_tileset_image(load_context, asset_path);
                                 tile_image_offsets
                                     .insert((tileset_index, tile_id), tile_images.len() as u32);
                                 tile_images.push(texture.clone());
@@ -184,17 +571,38 @@ impl AssetLoader for TiledLoader {
                     }
                 }
                 Some(img) => {
-                    // The load context path is the TMX file itself. If the file is at the root of the
-                    // assets/ directory structure then the tmx_dir will be empty, which is fine.
-                    // let tmx_dir = load_context
-                    //     .path()
-                    //     .parent()
-                    //     .expect("The asset load context was empty.");
-                    let tile_path = img.source.clone();
-                    let asset_path = AssetPath::from(tile_path);
-                    let texture: Handle<Image> = load_context.load(asset_path.clone());
-
-                    TilemapTexture::Single(texture.clone())
+                    let asset_path = AssetPath::from(img.source.clone());
+                    let texture: Handle<Image> = if tileset.margin == 0 {
+                        load_tileset_image(load_context, asset_path)
+                    } else {
+                        // bevy_ecs_tilemap only knows about the spacing between tiles,
+                        // so cut the margin around them off the image instead.
+                        let image = match load_context
+                            .loader()
+                            .with_settings(nearest_sampling)
+                            .immediate()
+                            .load::<Image>(asset_path.clone())
+                            .await
+                        {
+                            Ok(image) => image.take(),
+                            Err(error) => {
+                                warn!(
+                                    "Tileset image {asset_path} is missing, drawing its tiles magenta: {error}"
+                                );
+                                missing_image_placeholder(img)
+                            }
+                        };
+                        let image = crop_tileset_margin(&image, tileset).unwrap_or_else(|| {
+                            warn!(
+                                "Could not remove the margin of tileset {}, its tiles may be offset.",
+                                tileset.name
+                            );
+                            image
+                        });
+                        load_context.add_labeled_asset(format!("tileset{tileset_index}"), image)
+                    };
+
+                    TilemapTexture::Single(texture)
                 }
             };
 
@@ -222,6 +630,7 @@ pub fn process_loaded_maps(
     mut commands: Commands,
     mut map_events: MessageReader<AssetEvent<TiledMap>>,
     maps: Res<Assets<TiledMap>>,
+    mut atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
     tile_storage_query: Query<(Entity, &TileStorage)>,
     mut map_query: Query<(
         &TiledMapHandle,
@@ -229,6 +638,7 @@ pub fn process_loaded_maps(
         &TilemapRenderSettings,
     )>,
     new_maps: Query<&TiledMapHandle, Added<TiledMapHandle>>,
+    mut map_dimensions: ResMut<MapDimensions>,
 ) {
     let mut changed_maps = Vec::<AssetId<TiledMap>>::default();
     for event in map_events.read() {
@@ -245,7 +655,7 @@ pub fn process_loaded_maps(
                 info!("Map removed!");
                 // if mesh was modified and removed in the same update, ignore the modification
                 // events are ordered so future modification events are ok
-                changed_maps.retain(|changed_handle| changed_handle == id);
+                changed_maps.retain(|changed_handle| changed_handle != id);
             }
             _ => continue,
         }
@@ -263,14 +673,17 @@ pub fn process_loaded_maps(
                 continue;
             }
             if let Some(tiled_map) = maps.get(&map_handle.0) {
-                // TODO: Create a RemoveMap component..
-                for layer_entity in layer_storage.storage.values() {
-                    if let Ok((_, layer_tile_storage)) = tile_storage_query.get(*layer_entity) {
+                map_dimensions.set_if_neq(MapDimensions::of(&tiled_map.map));
+
+                // Tear down what an earlier version of the map spawned, so a reload starts clean.
+                // Tiles aren't children of their layer, so they're despawned on their own.
+                for (_, layer_entity) in layer_storage.storage.drain() {
+                    if let Ok((_, layer_tile_storage)) = tile_storage_query.get(layer_entity) {
                         for tile in layer_tile_storage.iter().flatten() {
-                            commands.entity(*tile).despawn()
+                            commands.entity(*tile).try_despawn();
                         }
                     }
-                    // commands.entity(*layer_entity).despawn_recursive();
+                    commands.entity(layer_entity).try_despawn();
                 }
 
                 // The TilemapBundle requires that all tile images come exclusively from a single
@@ -294,6 +707,8 @@ pub fn process_loaded_maps(
                         x: tileset.spacing as f32,
                         y: tileset.spacing as f32,
                     };
+                    // Slices the tileset for the sprites of its tall tiles, made for the first one.
+                    let mut tall_tile_atlas = None;
 
                     // Once materials have been created/added we need to then create the layers.
                     for (layer_index, layer) in tiled_map.map.layers().enumerate() {
@@ -339,16 +754,17 @@ pub fn process_loaded_maps(
                             tiled::Orientation::Orthogonal => TilemapType::Square,
                         };
 
+                        let layer_transform =
+                            Transform::from_xyz(offset_x, -offset_y, layer_index as f32)
+                                .with_scale(Vec2::splat(TILE_SCALE).extend(1.0));
+
                         let mut tile_storage = TileStorage::empty(map_size);
                         let layer_entity = commands.spawn_empty().id();
 
                         for x in 0..map_size.x {
                             for y in 0..map_size.y {
-                                // Transform TMX coords into bevy coords.
-                                let mapped_y = tiled_map.map.height - 1 - y;
-
                                 let mapped_x = x as i32;
-                                let mapped_y = mapped_y as i32;
+                                let mapped_y = flip_tiled_row(y, tiled_map.map.height) as i32;
 
                                 let layer_tile = match layer_data.get_tile(mapped_x, mapped_y) {
                                     Some(t) => t,
@@ -390,6 +806,9 @@ pub fn process_loaded_maps(
                                     _ => unreachable!()
                                 };
 
+                                let tall = tile_properties.get("tall")
+                                    == Some(&tiled::PropertyValue::BoolValue(true));
+
                                 let tile_pos = TilePos { x, y };
                                 let tile_entity = commands
                                     .spawn((
@@ -397,23 +816,44 @@ pub fn process_loaded_maps(
                                             position: tile_pos,
                                             tilemap_id: TilemapId(layer_entity),
                                             texture_index: TileTextureIndex(texture_index),
-                                            flip: TileFlip {
-                                                x: layer_tile_data.flip_h,
-                                                y: layer_tile_data.flip_v,
-                                                d: layer_tile_data.flip_d,
-                                            },
+                                            flip: tile_flip(
+                                                layer_tile_data.flip_h,
+                                                layer_tile_data.flip_v,
+                                                layer_tile_data.flip_d,
+                                            ),
                                             ..Default::default()
                                         },
                                         Name::new(format!(
                                             "Tile ({}, {}, {})",
                                             tile_pos.x, tile_pos.y, layer_index
                                         )),
-                                        DestroyOnEnter(vec![GameState::Title]),
+                                        DestroyOnEnter(vec![GameState::Title, GameState::GameOver]),
                                     ))
                                     .id();
-                                if tile_properties.get("type").is_none() {
-                                    warn!("Tile type are empty for tile id {}", layer_tile.id());
-                                } else if let Some(tile_type_value) = tile_properties.get("type") {
+                                if let Some(frames) = tileset
+                                    .get_tile(layer_tile.id())
+                                    .and_then(|tile_def| tile_def.animation.clone())
+                                {
+                                    let frames: Vec<(u32, u32)> = frames
+                                        .iter()
+                                        .filter_map(|frame| {
+                                            let index = match tilemap_texture {
+                                                TilemapTexture::Single(_) => Some(frame.tile_id),
+                                                _ => tiled_map
+                                                    .tile_image_offsets
+                                                    .get(&(tileset_index, frame.tile_id))
+                                                    .copied(),
+                                            };
+                                            Some((index?, frame.duration))
+                                        })
+                                        .collect();
+                                    insert_tile_animation(
+                                        &mut commands.entity(tile_entity),
+                                        &frames,
+                                        !tall,
+                                    );
+                                }
+                                if let Some(tile_type_value) = tile_properties.get("type") {
                                     let tile_type = match tile_type_value {
                                         tiled::PropertyValue::StringValue(s) => match s.as_str() {
                                             "Grass" => TileType::Grass,
@@ -424,19 +864,72 @@ pub fn process_loaded_maps(
                                             _ => TileType::Grass,
                                         },
                                         _ => {
-                                            panic!(
-                                                "Tile type is not a valid string for tile id {}",
+                                            warn!(
+                                                "Tile type is not a string for tile id {}, treating it as grass",
                                                 layer_tile.id()
                                             );
+                                            TileType::Grass
                                         }
                                     };
                                     commands.entity(tile_entity).insert(tile_type);
+                                } else {
+                                    debug!("Tile type is empty for tile id {}", layer_tile.id());
                                 }
                                 if let Some(is_obstacle) = tile_properties.get("obstacle")
                                     && is_obstacle == &tiled::PropertyValue::BoolValue(true)
                                 {
                                     commands.entity(tile_entity).insert(Obstacle);
                                 }
+                                if let Some(is_choppable) = tile_properties.get("choppable")
+                                    && is_choppable == &tiled::PropertyValue::BoolValue(true)
+                                {
+                                    commands.entity(tile_entity).insert(Choppable);
+                                }
+                                if tall {
+                                    if layer_tile_data.flip_d {
+                                        warn!(
+                                            "Tall tile id {} is flipped diagonally, which its sprite can't show",
+                                            layer_tile.id()
+                                        );
+                                    }
+                                    let mut sprite = match tilemap_texture {
+                                        TilemapTexture::Single(image) => Sprite::from_atlas_image(
+                                            image.clone(),
+                                            TextureAtlas {
+                                                layout: tall_tile_atlas
+                                                    .get_or_insert_with(|| {
+                                                        atlas_layouts.add(tileset_atlas(tileset))
+                                                    })
+                                                    .clone(),
+                                                index: texture_index as usize,
+                                            },
+                                        ),
+                                        TilemapTexture::Vector(images) => Sprite::from_image(
+                                            images[texture_index as usize].clone(),
+                                        ),
+                                        _ => unreachable!(),
+                                    };
+                                    sprite.flip_x = layer_tile_data.flip_h;
+                                    sprite.flip_y = layer_tile_data.flip_v;
+                                    // Right where the layer would have drawn it.
+                                    let center = tile_pos.center_in_world(
+                                        &map_size,
+                                        &grid_size,
+                                        &tile_size,
+                                        &map_type,
+                                        &TilemapAnchor::Center,
+                                    );
+                                    commands.entity(tile_entity).insert((
+                                        TallTile,
+                                        TileVisible(false),
+                                        sprite,
+                                        layer_transform.with_translation(
+                                            layer_transform.transform_point(center.extend(0.0)),
+                                        ),
+                                        YSort,
+                                        VisibleInState(vec![GameState::Gameplay]),
+                                    ));
+                                }
                                 tile_storage.set(&tile_pos, tile_entity);
                             }
                         }
@@ -450,24 +943,19 @@ pub fn process_loaded_maps(
                                 tile_size,
                                 spacing: tile_spacing,
                                 anchor: TilemapAnchor::Center,
-                                transform: Transform::from_xyz(
-                                    offset_x,
-                                    -offset_y,
-                                    layer_index as f32,
-                                )
-                                .with_scale(Vec2::splat(TILE_SCALE).extend(1.0)),
+                                transform: layer_transform,
                                 map_type,
                                 render_settings: *render_settings,
 
                                 ..Default::default()
                             },
-                            DestroyOnEnter(vec![GameState::Title]),
+                            DestroyOnEnter(vec![GameState::Title, GameState::GameOver]),
                             VisibleInState(vec![GameState::Gameplay]),
                         ));
 
                         layer_storage
                             .storage
-                            .insert(layer_index as u32, layer_entity);
+                            .insert((tileset_index, layer_index as u32), layer_entity);
                     }
                 }
             }
@@ -475,75 +963,245 @@ pub fn process_loaded_maps(
     }
 }
 
-fn apply_highlight_effect(
-    mut highlighted_tiles_q: Query<&mut TileVisible, Added<HighlightedTile>>,
+/// Texture atlas layout cutting a single-image `tileset` into its tiles, numbered like
+/// bevy_ecs_tilemap's texture indices. The image's margin was already cropped when loading.
+fn tileset_atlas(tileset: &tiled::Tileset) -> TextureAtlasLayout {
+    TextureAtlasLayout::from_grid(
+        UVec2::new(tileset.tile_width, tileset.tile_height),
+        tileset.columns,
+        tileset.tilecount.div_ceil(tileset.columns),
+        Some(UVec2::splat(tileset.spacing)),
+        None,
+    )
+}
+
+/// Keep [`TallTile`] sprites showing what their layer would: the current animation frame
+/// and any tint, like the highlight or watered soil.
+fn sync_tall_tile_sprites(
+    mut tall_tile_q: Query<
+        (&TileTextureIndex, &TileColor, &TilemapId, &mut Sprite),
+        (
+            With<TallTile>,
+            Or<(Changed<TileTextureIndex>, Changed<TileColor>)>,
+        ),
+    >,
+    texture_q: Query<&TilemapTexture>,
 ) {
-    for mut visible in highlighted_tiles_q.iter_mut() {
-        visible.0 = !visible.0;
+    for (texture_index, color, tilemap_id, mut sprite) in &mut tall_tile_q {
+        sprite.color = color.0;
+        let index = texture_index.0 as usize;
+        match texture_q.get(tilemap_id.0) {
+            Ok(TilemapTexture::Vector(images)) => {
+                if let Some(image) = images.get(index) {
+                    sprite.image = image.clone();
+                }
+            }
+            _ => {
+                if let Some(atlas) = &mut sprite.texture_atlas {
+                    atlas.index = index;
+                }
+            }
+        }
     }
 }
-fn handle_mouse_highlight(
+
+/// Convert between a row of Tiled's map, counted from the top, and a [`TilePos`] row,
+/// counted from the bottom. The flip is its own inverse, so it works in both directions.
+pub fn flip_tiled_row(y: u32, map_height: u32) -> u32 {
+    map_height - 1 - y
+}
+
+/// Wood dropped by a chopped tile.
+const CHOPPED_WOOD: u32 = 2;
+/// [`ScreenShake`] trauma added when a chop fells a tile.
+const CHOP_TRAUMA: f32 = 0.35;
+
+/// Remove the [`Choppable`] tiles the player chopped, dropping some wood in their place.
+fn chop_tiles(
     mut commands: Commands,
-    cursor_pos: Res<CursorPos>,
-    mut mouse_button_input_events: MessageReader<MouseButtonInput>,
-    tilemap_q: Query<(
+    mut tile_interactions: MessageReader<TileInteraction>,
+    choppable_q: Query<&TilemapId, With<Choppable>>,
+    mut tilemap_q: Query<(
         &TilemapSize,
         &TilemapGridSize,
         &TilemapTileSize,
         &TilemapType,
-        &TileStorage,
+        &mut TileStorage,
         &Transform,
         &TilemapAnchor,
     )>,
-    highlighted_tiles_q: Query<Entity, With<HighlightedTile>>,
+    mut chopped_tiles: ResMut<ChoppedTiles>,
+    current_map: Res<CurrentMap>,
+    mut screen_shake: ResMut<ScreenShake>,
 ) {
-    let mut clicked = false;
-    for event in mouse_button_input_events.read() {
-        if event.button == MouseButton::Left && event.state == ButtonState::Pressed {
-            clicked = true;
-            break;
+    for interaction in tile_interactions.read() {
+        if interaction.action != ActionType::Chopping {
+            continue;
         }
+        let Ok(tilemap_id) = choppable_q.get(interaction.tile_entity) else {
+            continue;
+        };
+        let Ok((map_size, grid_size, tile_size, map_type, mut tile_storage, map_transform, anchor)) =
+            tilemap_q.get_mut(tilemap_id.0)
+        else {
+            continue;
+        };
+        tile_storage.remove(&interaction.tile_pos);
+        commands.entity(interaction.tile_entity).despawn();
+        chopped_tiles.insert(&current_map.0, interaction.tile_pos);
+
+        let tile_center = interaction
+            .tile_pos
+            .center_in_world(map_size, grid_size, tile_size, map_type, anchor);
+        let tile_center = map_transform.transform_point(tile_center.extend(0.0)).xy();
+        commands.spawn(pickup(ItemKind::Wood, CHOPPED_WOOD, tile_center));
+        screen_shake.add_trauma(CHOP_TRAUMA);
     }
-    if !clicked {
-        return;
+}
+
+fn reset_chopped_tiles(mut chopped_tiles: ResMut<ChoppedTiles>) {
+    chopped_tiles.0.clear();
+}
+
+/// Despawn freshly spawned tiles that were already chopped down.
+fn remove_chopped_tiles(
+    mut commands: Commands,
+    chopped_tiles: Res<ChoppedTiles>,
+    current_map: Res<CurrentMap>,
+    new_tiles_q: Query<(Entity, &TilePos, &TilemapId), Added<Choppable>>,
+    mut tile_storage_q: Query<&mut TileStorage>,
+) {
+    for (tile_entity, tile_pos, tilemap_id) in new_tiles_q.iter() {
+        if !chopped_tiles.contains(&current_map.0, *tile_pos) {
+            continue;
+        }
+        if let Ok(mut tile_storage) = tile_storage_q.get_mut(tilemap_id.0) {
+            tile_storage.remove(tile_pos);
+        }
+        commands.entity(tile_entity).despawn();
     }
-    for highlighted_tile_entity in highlighted_tiles_q.iter() {
-        commands
-            .entity(highlighted_tile_entity)
-            .remove::<HighlightedTile>();
+}
+
+fn apply_highlight_effect(
+    mut highlighted_tiles_q: Query<(&mut TileColor, &mut HighlightedTile), Added<HighlightedTile>>,
+) {
+    for (mut color, mut highlight) in highlighted_tiles_q.iter_mut() {
+        highlight.original_color = color.0;
+        color.0 = HIGHLIGHT_COLOR;
     }
+}
+/// Highlight the tile in front of the player, which is the one their actions will target.
+fn highlight_facing_tile(
+    mut commands: Commands,
+    player_q: Query<(&GlobalTransform, &Facing), With<Player>>,
+    tiles: TileQuery,
+    highlighted_tiles_q: Query<(Entity, &HighlightedTile)>,
+) {
+    let Ok((player_transform, facing)) = player_q.single() else {
+        return;
+    };
+    let target = facing_tile_position(player_transform.translation().xy(), facing.0);
+    let facing_tiles: Vec<Entity> = tiles
+        .tiles_at(target)
+        .map(|(tile_entity, _)| tile_entity)
+        .collect();
 
-    for (map_size, grid_size, tile_size, map_type, tile_storage, map_transform, anchor) in
-        tilemap_q.iter()
-    {
-        let cursor_pos: Vec2 = cursor_pos.0;
-        let cursor_in_map_pos: Vec2 = {
-            let cursor_pos = Vec4::from((cursor_pos, 0.0, 1.0));
-            let cursor_in_map_pos = map_transform.to_matrix().inverse() * cursor_pos;
-            cursor_in_map_pos.xy()
-        };
-        if let Some(tile_pos) = TilePos::from_world_pos(
-            &cursor_in_map_pos,
-            map_size,
-            grid_size,
-            tile_size,
-            map_type,
-            anchor,
-        ) && let Some(tile_entity) = tile_storage.get(&tile_pos)
-        {
+    // Only touch tiles whose highlight actually changes, so the effect isn't re-applied every frame.
+    for (highlighted_tile_entity, highlight) in highlighted_tiles_q.iter() {
+        if !facing_tiles.contains(&highlighted_tile_entity) {
+            commands
+                .entity(highlighted_tile_entity)
+                .insert(TileColor(highlight.original_color))
+                .remove::<HighlightedTile>();
+        }
+    }
+    for tile_entity in facing_tiles {
+        if !highlighted_tiles_q.contains(tile_entity) {
             commands
                 .entity(tile_entity)
-                .insert(HighlightedTile)
-                .insert((
-                    Text2d::new(format!("({},{})", tile_pos.x, tile_pos.y)),
-                    TextColor::WHITE,
-                    TextFont {
-                        font_size: 10.0,
-                        ..default()
-                    },
-                    Transform::from_translation(Vec3::new(cursor_pos.x, cursor_pos.y, 0.1)),
-                    VisibleInState(vec![GameState::Gameplay]),
-                ));
+                .insert(HighlightedTile::default());
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tile_flip_maps_every_tiled_orientation_straight_across() {
+        let flip = |x, y, d| TileFlip { x, y, d };
+        // (h, v, d) as Tiled stores them, and the flip they should draw with.
+        let cases = [
+            ((false, false, false), flip(false, false, false)),
+            ((true, false, false), flip(true, false, false)),
+            ((false, true, false), flip(false, true, false)),
+            ((true, true, false), flip(true, true, false)),
+            ((false, false, true), flip(false, false, true)),
+            ((true, false, true), flip(true, false, true)),
+            ((false, true, true), flip(false, true, true)),
+            ((true, true, true), flip(true, true, true)),
+        ];
+        for ((flip_h, flip_v, flip_d), expected) in cases {
+            assert_eq!(
+                tile_flip(flip_h, flip_v, flip_d),
+                expected,
+                "Tiled flags h: {flip_h}, v: {flip_v}, d: {flip_d}"
+            );
+        }
+    }
+
+    #[test]
+    fn flip_tiled_row_swaps_top_and_bottom() {
+        assert_eq!(flip_tiled_row(0, 5), 4);
+        assert_eq!(flip_tiled_row(4, 5), 0);
+        // The middle row of an odd-sized map stays put.
+        assert_eq!(flip_tiled_row(2, 5), 2);
+        assert_eq!(flip_tiled_row(1, 4), 2);
+        assert_eq!(flip_tiled_row(0, 1), 0);
+    }
+
+    #[test]
+    fn flip_tiled_row_is_its_own_inverse() {
+        for map_height in [1, 2, 5, 8] {
+            for y in 0..map_height {
+                assert_eq!(flip_tiled_row(flip_tiled_row(y, map_height), map_height), y);
+            }
+        }
+    }
+
+    #[test]
+    fn tall_tile_sprites_follow_their_tile() {
+        use bevy::ecs::system::RunSystemOnce;
+
+        let mut world = World::new();
+        let layer = world.spawn(TilemapTexture::Single(Handle::default())).id();
+        let sprite = Sprite::from_atlas_image(
+            Handle::default(),
+            TextureAtlas {
+                layout: Handle::default(),
+                index: 0,
+            },
+        );
+        let tile = world
+            .spawn((
+                TallTile,
+                TileTextureIndex(0),
+                TileColor::default(),
+                TilemapId(layer),
+                sprite,
+            ))
+            .id();
+        world.run_system_once(sync_tall_tile_sprites).unwrap();
+
+        // A highlight and a step of animation later.
+        world
+            .entity_mut(tile)
+            .insert((TileTextureIndex(5), TileColor(HIGHLIGHT_COLOR)));
+        world.run_system_once(sync_tall_tile_sprites).unwrap();
+
+        let sprite = world.get::<Sprite>(tile).unwrap();
+        assert_eq!(sprite.color, HIGHLIGHT_COLOR);
+        assert_eq!(sprite.texture_atlas.as_ref().unwrap().index, 5);
+    }
+}
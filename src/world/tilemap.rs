@@ -1,28 +1,557 @@
-use bevy::prelude::*;
+//! Spawning the tilemaps, switching between them, and looking up their tiles by world position.
+
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use bevy::{ecs::system::SystemParam, math::Affine3A, platform::collections::HashMap, prelude::*};
+use bevy_ecs_tilemap::prelude::*;
 
 use crate::{
     AppSystems,
-    states::{DestroyOnEnter, GameState},
+    constants::TILE_WORLD_SIZE,
+    game::{
+        chest::Chest,
+        crops::Crop,
+        enemy::{Enemy, PendingEnemySpawn},
+        inventory::Pickup,
+        level::LevelSpawnSystems,
+        npc::Npc,
+        player::{ClickPath, Player},
+    },
+    states::{DestroyOnEnter, GameState, GameplayState},
+    world::tiledhelper::{TileType, TiledMap, TiledMapBundle, TiledMapHandle, process_loaded_maps},
 };
 
 pub(super) fn plugin(app: &mut App) {
+    app.register_type::<CurrentMap>();
+    app.init_resource::<CurrentMap>();
+    app.add_message::<LoadMapEvent>();
+
     app.add_systems(
-        OnTransition::<GameState> {
-            exited: GameState::Title,
-            entered: GameState::Gameplay,
-        },
-        spawn_tile_map.in_set(AppSystems::PreUpdate),
+        OnEnter(GameState::Gameplay),
+        spawn_tile_map.in_set(LevelSpawnSystems),
     );
+    // A new game always starts on the default map.
+    app.add_systems(OnEnter(GameState::Title), reset_current_map);
+    app.add_systems(OnEnter(GameState::GameOver), reset_current_map);
+    app.add_systems(
+        Update,
+        (
+            switch_map.before(process_loaded_maps),
+            place_player_at_spawn_point
+                .run_if(resource_exists::<PendingSpawnPoint>)
+                .after(process_loaded_maps),
+        )
+            .run_if(in_state(GameplayState::Running))
+            .in_set(AppSystems::PreUpdate),
+    );
+}
+
+/// The map the player starts a new game on.
+const DEFAULT_MAP: &str = "farm";
+/// The object the player is placed on when switching to a map without naming an entry point.
+const SPAWN_POINT: &str = "spawn";
+
+/// Name of the map being played, i.e. `assets/tilemaps/<name>.tmx`. Its size ends up in
+/// [`MapDimensions`](crate::world::tiledhelper::MapDimensions) once it has loaded.
+#[derive(Resource, Debug, Clone, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub struct CurrentMap(pub String);
+
+impl Default for CurrentMap {
+    fn default() -> Self {
+        Self(DEFAULT_MAP.to_string())
+    }
+}
+
+/// Replace the current map with the named one, keeping the player and placing them on the
+/// object called `entry` in the new map.
+#[derive(Message, Debug, Clone)]
+pub struct LoadMapEvent {
+    pub map: String,
+    pub entry: String,
 }
 
-fn spawn_tile_map(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let map_handle = super::tiledhelper::TiledMapHandle(asset_server.load("tilemaps/farm.tmx"));
+impl LoadMapEvent {
+    /// Switch to `map`, entering it at its spawn point.
+    pub fn new(map: impl Into<String>) -> Self {
+        Self {
+            map: map.into(),
+            entry: SPAWN_POINT.to_string(),
+        }
+    }
+}
+
+/// The spawn point to move the player to once the new map has loaded.
+#[derive(Resource)]
+struct PendingSpawnPoint(String);
+
+fn map_path(name: &str) -> String {
+    format!("tilemaps/{name}.tmx")
+}
 
+fn spawn_tile_map(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    current_map: Res<CurrentMap>,
+) {
     commands.spawn((
-        super::tiledhelper::TiledMapBundle {
-            tiled_map: map_handle,
+        Name::new(format!("Map {}", current_map.0)),
+        TiledMapBundle {
+            tiled_map: TiledMapHandle(asset_server.load(map_path(&current_map.0))),
             ..Default::default()
         },
-        DestroyOnEnter(vec![GameState::Title]),
+        DestroyOnEnter(vec![GameState::Title, GameState::GameOver]),
     ));
 }
+
+fn reset_current_map(mut current_map: ResMut<CurrentMap>) {
+    *current_map = CurrentMap::default();
+}
+
+/// Tear down the current map and spawn the requested one in its place, along with a fresh
+/// set of enemies for it.
+pub fn switch_map(
+    mut commands: Commands,
+    mut load_map_events: MessageReader<LoadMapEvent>,
+    mut current_map: ResMut<CurrentMap>,
+    asset_server: Res<AssetServer>,
+    map_q: Query<
+        Entity,
+        Or<(
+            With<TiledMapHandle>,
+            With<TileStorage>,
+            With<TilePos>,
+            With<Pickup>,
+            With<Crop>,
+            With<Npc>,
+            With<Chest>,
+            With<Enemy>,
+        )>,
+    >,
+    click_path_q: Query<Entity, With<ClickPath>>,
+) {
+    // Only the last request matters if several arrive in the same frame.
+    let Some(LoadMapEvent { map: name, entry }) = load_map_events.read().last() else {
+        return;
+    };
+    for entity in &map_q {
+        commands.entity(entity).despawn();
+    }
+    // A path clicked on the old map means nothing on the new one.
+    for entity in &click_path_q {
+        commands.entity(entity).remove::<ClickPath>();
+    }
+    current_map.0 = name.clone();
+    commands.spawn((
+        Name::new(format!("Map {name}")),
+        TiledMapBundle {
+            tiled_map: TiledMapHandle(asset_server.load(map_path(name))),
+            ..Default::default()
+        },
+        DestroyOnEnter(vec![GameState::Title, GameState::GameOver]),
+    ));
+    commands.insert_resource(PendingSpawnPoint(entry.clone()));
+    commands.init_resource::<PendingEnemySpawn>();
+    info!("Switching to map {name}");
+}
+
+/// Move the player onto the pending spawn point as soon as the new map is loaded.
+fn place_player_at_spawn_point(
+    mut commands: Commands,
+    pending: Res<PendingSpawnPoint>,
+    maps: Res<Assets<TiledMap>>,
+    map_q: Query<&TiledMapHandle>,
+    mut player_q: Query<&mut Transform, With<Player>>,
+) {
+    let Some(tiled_map) = map_q.iter().find_map(|handle| maps.get(&handle.0)) else {
+        return;
+    };
+    if let Some(position) = tiled_map.object_position(&pending.0) {
+        for mut transform in &mut player_q {
+            transform.translation = position.extend(transform.translation.z);
+        }
+    } else {
+        warn!("The map has no spawn point called {:?}", pending.0);
+    }
+    commands.remove_resource::<PendingSpawnPoint>();
+}
+
+/// The tiles sharing an edge with `tile_pos` that are still on a `map_size` map.
+pub fn grid_neighbors(tile_pos: TilePos, map_size: TilemapSize) -> impl Iterator<Item = TilePos> {
+    [(-1, 0), (1, 0), (0, -1), (0, 1)]
+        .into_iter()
+        .filter_map(move |(dx, dy)| {
+            let x = tile_pos.x.checked_add_signed(dx)?;
+            let y = tile_pos.y.checked_add_signed(dy)?;
+            (x < map_size.x && y < map_size.y).then_some(TilePos { x, y })
+        })
+}
+
+/// Which tiles of a map can be walked on, for finding paths across it. A plain value
+/// like [`TilemapLayout`], so it doesn't care who's asking or what they're walking.
+#[derive(Debug, Clone)]
+pub struct WalkabilityGrid {
+    size: TilemapSize,
+    /// Indexed by [`TilePos::to_index`].
+    walkable: Vec<bool>,
+}
+
+impl WalkabilityGrid {
+    /// A `size` map where everything but the `obstacles` can be walked on.
+    pub fn new(size: TilemapSize, obstacles: impl IntoIterator<Item = TilePos>) -> Self {
+        let mut walkable = vec![true; size.count()];
+        for tile_pos in obstacles {
+            if tile_pos.within_map_bounds(&size) {
+                walkable[tile_pos.to_index(&size)] = false;
+            }
+        }
+        Self { size, walkable }
+    }
+
+    /// Whether `tile_pos` is on the map and not an obstacle.
+    pub fn is_walkable(&self, tile_pos: &TilePos) -> bool {
+        tile_pos.within_map_bounds(&self.size) && self.walkable[tile_pos.to_index(&self.size)]
+    }
+
+    /// Shortest walkable path from `start` to `goal`, both included, or `None` if the goal
+    /// can't be reached. Steps only go to [`grid_neighbors`], so the path never cuts the
+    /// corner of an obstacle. `start` itself doesn't have to be walkable, so whoever is
+    /// wedged against an obstacle can still find their way out.
+    pub fn astar(&self, start: TilePos, goal: TilePos) -> Option<Vec<TilePos>> {
+        let heuristic = |tile: TilePos| tile.x.abs_diff(goal.x) + tile.y.abs_diff(goal.y);
+        let mut cost = HashMap::new();
+        cost.insert(start, 0);
+        let mut came_from = HashMap::new();
+        // `TilePos` isn't `Ord`, so the queue holds its coordinates.
+        let mut open = BinaryHeap::from([Reverse((heuristic(start), start.x, start.y))]);
+        while let Some(Reverse((_, x, y))) = open.pop() {
+            let tile = TilePos { x, y };
+            if tile == goal {
+                let mut path = vec![tile];
+                while let Some(&previous) = came_from.get(path.last()?) {
+                    path.push(previous);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            let next_cost = cost[&tile] + 1;
+            for neighbor in grid_neighbors(tile, self.size) {
+                if !self.is_walkable(&neighbor)
+                    || cost.get(&neighbor).is_some_and(|&known| known <= next_cost)
+                {
+                    continue;
+                }
+                cost.insert(neighbor, next_cost);
+                came_from.insert(neighbor, tile);
+                open.push(Reverse((
+                    next_cost + heuristic(neighbor),
+                    neighbor.x,
+                    neighbor.y,
+                )));
+            }
+        }
+        None
+    }
+}
+
+/// How world positions map onto the tile positions of a tilemap layer. A plain value
+/// without any ECS access, so the conversion can be done and checked anywhere.
+#[derive(Debug, Clone, Copy)]
+pub struct TilemapLayout {
+    map_size: TilemapSize,
+    grid_size: TilemapGridSize,
+    tile_size: TilemapTileSize,
+    map_type: TilemapType,
+    anchor: TilemapAnchor,
+    world_to_map: Affine3A,
+}
+
+impl TilemapLayout {
+    /// The layout of a layer drawn with `map_transform`.
+    pub fn new(
+        map_size: TilemapSize,
+        grid_size: TilemapGridSize,
+        tile_size: TilemapTileSize,
+        map_type: TilemapType,
+        anchor: TilemapAnchor,
+        map_transform: Affine3A,
+    ) -> Self {
+        Self {
+            map_size,
+            grid_size,
+            tile_size,
+            map_type,
+            anchor,
+            world_to_map: map_transform.inverse(),
+        }
+    }
+
+    /// Size of the map in tiles.
+    pub fn map_size(&self) -> TilemapSize {
+        self.map_size
+    }
+
+    /// The part of the world the map covers.
+    pub fn world_rect(&self) -> Rect {
+        let last_tile = TilePos {
+            x: self.map_size.x - 1,
+            y: self.map_size.y - 1,
+        };
+        Rect::from_corners(
+            self.tile_center(&TilePos { x: 0, y: 0 }),
+            self.tile_center(&last_tile),
+        )
+        .inflate(TILE_WORLD_SIZE / 2.0)
+    }
+
+    /// The tile under `world_pos`, or `None` off the map.
+    pub fn tile_pos(&self, world_pos: Vec2) -> Option<TilePos> {
+        let in_map_pos = self
+            .world_to_map
+            .transform_point3(world_pos.extend(0.0))
+            .xy();
+        TilePos::from_world_pos(
+            &in_map_pos,
+            &self.map_size,
+            &self.grid_size,
+            &self.tile_size,
+            &self.map_type,
+            &self.anchor,
+        )
+    }
+
+    /// World position of the center of `tile_pos`, the inverse of [`Self::tile_pos`].
+    pub fn tile_center(&self, tile_pos: &TilePos) -> Vec2 {
+        let center = tile_pos.center_in_world(
+            &self.map_size,
+            &self.grid_size,
+            &self.tile_size,
+            &self.map_type,
+            &self.anchor,
+        );
+        self.world_to_map
+            .inverse()
+            .transform_point3(center.extend(0.0))
+            .xy()
+    }
+}
+
+/// Finds the tiles under a world position across every tilemap layer.
+///
+/// Uses the layers' [`GlobalTransform`] so it can sit next to systems that move
+/// entities through their [`Transform`].
+#[derive(SystemParam)]
+pub struct TileQuery<'w, 's> {
+    tilemap_q: Query<
+        'w,
+        's,
+        (
+            &'static TilemapSize,
+            &'static TilemapGridSize,
+            &'static TilemapTileSize,
+            &'static TilemapType,
+            &'static TileStorage,
+            &'static GlobalTransform,
+            &'static TilemapAnchor,
+        ),
+    >,
+    tile_type_q: Query<'w, 's, &'static TileType>,
+}
+
+impl TileQuery<'_, '_> {
+    /// Every tile under `world_pos`, one per tilemap layer that has a tile there.
+    pub fn tiles_at(&self, world_pos: Vec2) -> impl Iterator<Item = (Entity, TilePos)> + '_ {
+        self.tilemap_q.iter().filter_map(
+            move |(
+                map_size,
+                grid_size,
+                tile_size,
+                map_type,
+                tile_storage,
+                map_transform,
+                anchor,
+            )| {
+                let layout = TilemapLayout::new(
+                    *map_size,
+                    *grid_size,
+                    *tile_size,
+                    *map_type,
+                    *anchor,
+                    map_transform.affine(),
+                );
+                let tile_pos = layout.tile_pos(world_pos)?;
+                Some((tile_storage.get(&tile_pos)?, tile_pos))
+            },
+        )
+    }
+
+    /// World position of the center of `tile_pos` in the given tilemap layer.
+    pub fn tile_center(&self, tilemap: Entity, tile_pos: &TilePos) -> Option<Vec2> {
+        let (map_size, grid_size, tile_size, map_type, _, map_transform, anchor) =
+            self.tilemap_q.get(tilemap).ok()?;
+        let layout = TilemapLayout::new(
+            *map_size,
+            *grid_size,
+            *tile_size,
+            *map_type,
+            *anchor,
+            map_transform.affine(),
+        );
+        Some(layout.tile_center(tile_pos))
+    }
+
+    /// The first tile under `world_pos` that has a [`TileType`].
+    pub fn tile_at(&self, world_pos: Vec2) -> Option<(Entity, TilePos, &TileType)> {
+        self.tiles_at(world_pos).find_map(|(entity, tile_pos)| {
+            Some((entity, tile_pos, self.tile_type_q.get(entity).ok()?))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAP_SIZE: TilemapSize = TilemapSize { x: 5, y: 3 };
+    /// World size of a tile in [`layout`]: 16 pixel tiles drawn three times as big.
+    const TILE_WORLD: f32 = 48.0;
+    const MAP_CENTER: Vec2 = Vec2::new(100.0, -50.0);
+
+    /// An odd-sized layer, scaled and moved off the origin, so that nothing lines up by accident.
+    fn layout() -> TilemapLayout {
+        TilemapLayout::new(
+            MAP_SIZE,
+            TilemapGridSize { x: 16.0, y: 16.0 },
+            TilemapTileSize { x: 16.0, y: 16.0 },
+            TilemapType::Square,
+            TilemapAnchor::Center,
+            Affine3A::from_scale_rotation_translation(
+                Vec3::new(3.0, 3.0, 1.0),
+                Quat::IDENTITY,
+                MAP_CENTER.extend(0.0),
+            ),
+        )
+    }
+
+    fn every_tile() -> impl Iterator<Item = TilePos> {
+        (0..MAP_SIZE.x).flat_map(|x| (0..MAP_SIZE.y).map(move |y| TilePos { x, y }))
+    }
+
+    #[test]
+    fn tile_center_round_trips_through_tile_pos() {
+        let layout = layout();
+        for tile_pos in every_tile() {
+            assert_eq!(
+                layout.tile_pos(layout.tile_center(&tile_pos)),
+                Some(tile_pos)
+            );
+        }
+    }
+
+    #[test]
+    fn tile_pos_covers_the_whole_tile() {
+        let layout = layout();
+        let just_inside = TILE_WORLD / 2.0 - 1.0;
+        for tile_pos in every_tile() {
+            let center = layout.tile_center(&tile_pos);
+            for corner in [
+                Vec2::new(-1.0, -1.0),
+                Vec2::new(1.0, -1.0),
+                Vec2::new(-1.0, 1.0),
+                Vec2::new(1.0, 1.0),
+            ] {
+                assert_eq!(
+                    layout.tile_pos(center + corner * just_inside),
+                    Some(tile_pos)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn tile_center_follows_the_map_transform() {
+        let layout = layout();
+        // An odd-sized map centered on its transform has its middle tile right there.
+        let middle = layout.tile_center(&TilePos { x: 2, y: 1 });
+        assert!(middle.abs_diff_eq(MAP_CENTER, 1e-3), "{middle}");
+        // Row 0 is the bottom row, and neighboring tiles are a scaled tile apart.
+        let bottom_left = layout.tile_center(&TilePos { x: 0, y: 0 });
+        let top_right = layout.tile_center(&TilePos { x: 4, y: 2 });
+        let expected = MAP_CENTER - Vec2::new(2.0, 1.0) * TILE_WORLD;
+        assert!(bottom_left.abs_diff_eq(expected, 1e-3), "{bottom_left}");
+        let expected = MAP_CENTER + Vec2::new(2.0, 1.0) * TILE_WORLD;
+        assert!(top_right.abs_diff_eq(expected, 1e-3), "{top_right}");
+    }
+
+    #[test]
+    fn tile_pos_is_none_off_the_map() {
+        let layout = layout();
+        let bottom_left = layout.tile_center(&TilePos { x: 0, y: 0 });
+        let top_right = layout.tile_center(&TilePos { x: 4, y: 2 });
+        for world_pos in [
+            bottom_left - Vec2::X * TILE_WORLD,
+            bottom_left - Vec2::Y * TILE_WORLD,
+            top_right + Vec2::X * TILE_WORLD,
+            top_right + Vec2::Y * TILE_WORLD,
+            Vec2::splat(10_000.0),
+        ] {
+            assert_eq!(layout.tile_pos(world_pos), None, "{world_pos}");
+        }
+    }
+
+    /// Checks `path` runs from `start` to `goal` in single steps over walkable tiles.
+    fn assert_valid_path(grid: &WalkabilityGrid, path: &[TilePos], start: TilePos, goal: TilePos) {
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&goal));
+        for step in path.windows(2) {
+            assert_eq!(
+                step[0].x.abs_diff(step[1].x) + step[0].y.abs_diff(step[1].y),
+                1,
+                "{path:?}"
+            );
+        }
+        assert!(path.iter().all(|tile| grid.is_walkable(tile)), "{path:?}");
+    }
+
+    #[test]
+    fn astar_crosses_an_open_map_in_the_fewest_steps() {
+        let grid = WalkabilityGrid::new(MAP_SIZE, []);
+        let (start, goal) = (TilePos { x: 0, y: 0 }, TilePos { x: 4, y: 2 });
+        let path = grid.astar(start, goal).unwrap();
+        assert_valid_path(&grid, &path, start, goal);
+        assert_eq!(path.len(), 7);
+    }
+
+    #[test]
+    fn astar_walks_around_a_wall() {
+        // A wall down the middle column with a gap in the top row.
+        let grid = WalkabilityGrid::new(MAP_SIZE, [TilePos { x: 2, y: 0 }, TilePos { x: 2, y: 1 }]);
+        let (start, goal) = (TilePos { x: 0, y: 0 }, TilePos { x: 4, y: 0 });
+        let path = grid.astar(start, goal).unwrap();
+        assert_valid_path(&grid, &path, start, goal);
+        assert!(path.contains(&TilePos { x: 2, y: 2 }));
+        assert_eq!(path.len(), 9);
+    }
+
+    #[test]
+    fn astar_gives_up_on_unreachable_goals() {
+        let wall = (0..MAP_SIZE.y).map(|y| TilePos { x: 2, y });
+        let grid = WalkabilityGrid::new(MAP_SIZE, wall);
+        assert_eq!(
+            grid.astar(TilePos { x: 0, y: 0 }, TilePos { x: 4, y: 0 }),
+            None
+        );
+        // An obstacle can't be the goal either.
+        assert_eq!(
+            grid.astar(TilePos { x: 0, y: 0 }, TilePos { x: 2, y: 1 }),
+            None
+        );
+    }
+
+    #[test]
+    fn astar_from_the_goal_is_just_the_goal() {
+        let grid = WalkabilityGrid::new(MAP_SIZE, []);
+        let tile = TilePos { x: 3, y: 1 };
+        assert_eq!(grid.astar(tile, tile), Some(vec![tile]));
+    }
+}
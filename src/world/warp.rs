@@ -0,0 +1,128 @@
+//! Warps that move the player to another map when they step on them.
+//!
+//! A warp is a rectangle object on one of the map's object layers with a `warp_to` property
+//! naming the target map, and optionally an `entry` property naming the object in that map to
+//! place the player on.
+
+use bevy::prelude::*;
+
+use crate::{
+    AppSystems,
+    game::player::Player,
+    states::{GameState, GameplayState},
+    world::{
+        tiledhelper::{TiledMap, TiledMapHandle},
+        tilemap::LoadMapEvent,
+    },
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (
+            enter_warps.in_set(AppSystems::Update),
+            update_warp_fade.in_set(AppSystems::PostUpdate),
+        )
+            .run_if(in_state(GameplayState::Running)),
+    );
+}
+
+/// How long the screen takes to fade out, and then back in, when warping.
+const WARP_FADE_SECS: f32 = 0.25;
+
+/// A full-screen overlay that fades to black, switches maps, and fades back in.
+#[derive(Component)]
+struct WarpFade {
+    timer: Timer,
+    /// The map switch to make once the screen is black. `None` while fading back in.
+    load_map: Option<LoadMapEvent>,
+}
+
+/// Start a warp when the player steps onto a warp they weren't already standing on.
+fn enter_warps(
+    mut commands: Commands,
+    maps: Res<Assets<TiledMap>>,
+    map_q: Query<&TiledMapHandle>,
+    player_q: Query<&GlobalTransform, With<Player>>,
+    fade_q: Query<(), With<WarpFade>>,
+    mut was_on_warp: Local<bool>,
+) {
+    let Ok(player_transform) = player_q.single() else {
+        return;
+    };
+    let Some(tiled_map) = map_q.iter().find_map(|handle| maps.get(&handle.0)) else {
+        return;
+    };
+    let player_pos = player_transform.translation().xy();
+    let warp = tiled_map.objects().find_map(|object| {
+        let tiled::PropertyValue::StringValue(map) = object.properties.get("warp_to")? else {
+            return None;
+        };
+        let tiled::ObjectShape::Rect { width, height } = object.shape else {
+            return None;
+        };
+        let corners = [
+            tiled_map.to_world(Vec2::new(object.x, object.y)),
+            tiled_map.to_world(Vec2::new(object.x + width, object.y + height)),
+        ];
+        if !Rect::from_corners(corners[0], corners[1]).contains(player_pos) {
+            return None;
+        }
+        let mut load_map = LoadMapEvent::new(map.clone());
+        if let Some(tiled::PropertyValue::StringValue(entry)) = object.properties.get("entry") {
+            load_map.entry = entry.clone();
+        }
+        Some(load_map)
+    });
+
+    // Only warp on the step onto a warp, so arriving on top of one doesn't bounce straight back.
+    let on_warp = warp.is_some();
+    if std::mem::replace(&mut *was_on_warp, on_warp) || !fade_q.is_empty() {
+        return;
+    }
+    let Some(load_map) = warp else {
+        return;
+    };
+    commands.spawn((
+        Name::new("Warp Fade"),
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            ..default()
+        },
+        BackgroundColor(Color::BLACK.with_alpha(0.0)),
+        GlobalZIndex(i32::MAX),
+        Pickable::IGNORE,
+        DespawnOnExit(GameState::Gameplay),
+        WarpFade {
+            timer: Timer::from_seconds(WARP_FADE_SECS, TimerMode::Once),
+            load_map: Some(load_map),
+        },
+    ));
+}
+
+fn update_warp_fade(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut fade_q: Query<(Entity, &mut WarpFade, &mut BackgroundColor)>,
+    mut load_map_events: MessageWriter<LoadMapEvent>,
+) {
+    for (entity, mut fade, mut background) in &mut fade_q {
+        fade.timer.tick(time.delta());
+        let progress = fade.timer.fraction();
+        let fading_out = fade.load_map.is_some();
+        let alpha = if fading_out { progress } else { 1.0 - progress };
+        background.0 = Color::BLACK.with_alpha(alpha);
+
+        if !fade.timer.is_finished() {
+            continue;
+        }
+        if let Some(load_map) = fade.load_map.take() {
+            load_map_events.write(load_map);
+            fade.timer.reset();
+        } else {
+            commands.entity(entity).despawn();
+        }
+    }
+}